@@ -1,7 +1,7 @@
 use std::{env, time::Duration};
 
 use async_nats::jetstream;
-use codequest_common::Error;
+use codequest_common::{Error, nats};
 use dotenv::dotenv;
 
 async fn ensure_stream(
@@ -74,6 +74,69 @@ async fn main() -> Result<(), Error> {
                 )
                 .await?;
 
+                ensure_stream(
+                    &js,
+                    "ACHIEVEMENT_EVENTS",
+                    vec!["achievement.events.*"]
+                        .into_iter()
+                        .map(|s| s.to_owned())
+                        .collect::<Vec<_>>(),
+                    Duration::from_secs(60 * 60 * 24 * 30),
+                )
+                .await?;
+
+                ensure_stream(
+                    &js,
+                    "STATISTICS_EVENTS",
+                    vec!["statistics.events.*"]
+                        .into_iter()
+                        .map(|s| s.to_owned())
+                        .collect::<Vec<_>>(),
+                    Duration::from_secs(60 * 60 * 24 * 30),
+                )
+                .await?;
+
+                ensure_stream(
+                    &js,
+                    "COMMENT_EVENTS",
+                    vec!["comment.events.*"]
+                        .into_iter()
+                        .map(|s| s.to_owned())
+                        .collect::<Vec<_>>(),
+                    Duration::from_secs(60 * 60 * 24 * 30),
+                )
+                .await?;
+
+                ensure_stream(
+                    &js,
+                    "NOTIFICATION_EVENTS",
+                    vec!["notification.events.*"]
+                        .into_iter()
+                        .map(|s| s.to_owned())
+                        .collect::<Vec<_>>(),
+                    Duration::from_secs(60 * 60 * 24 * 30),
+                )
+                .await?;
+
+                ensure_stream(
+                    &js,
+                    "ERASURE_EVENTS",
+                    vec!["erasure.events.*"]
+                        .into_iter()
+                        .map(|s| s.to_owned())
+                        .collect::<Vec<_>>(),
+                    Duration::from_secs(60 * 60 * 24 * 30),
+                )
+                .await?;
+
+                ensure_stream(
+                    &js,
+                    "DEAD_LETTERS",
+                    vec![format!("{}.>", nats::DEAD_LETTER_SUBJECT_PREFIX)],
+                    Duration::from_secs(60 * 60 * 24 * 30),
+                )
+                .await?;
+
                 println!("NATS JetStream bootstrap completed");
                 break;
             }
@@ -0,0 +1,11 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Achievement {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    /// `None` while the user hasn't unlocked the achievement.
+    pub unlocked_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{UserId, Username};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminAction {
+    BanUser,
+    UnbanUser,
+    ResetPassword,
+    ForceLogout,
+    SetOfficial,
+    ArchiveQuest,
+    UnarchiveQuest,
+    DeleteQuest,
+    ClearQuestContexts,
+    RegenerateQuestContexts,
+    ImportQuest,
+    HideComment,
+    UnhideComment,
+    DismissCommentReports,
+    GrantAdmin,
+    RevokeAdmin,
+}
+
+impl AdminAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::BanUser => "ban_user",
+            Self::UnbanUser => "unban_user",
+            Self::ResetPassword => "reset_password",
+            Self::ForceLogout => "force_logout",
+            Self::SetOfficial => "set_official",
+            Self::ArchiveQuest => "archive_quest",
+            Self::UnarchiveQuest => "unarchive_quest",
+            Self::DeleteQuest => "delete_quest",
+            Self::ClearQuestContexts => "clear_quest_contexts",
+            Self::RegenerateQuestContexts => "regenerate_quest_contexts",
+            Self::ImportQuest => "import_quest",
+            Self::HideComment => "hide_comment",
+            Self::UnhideComment => "unhide_comment",
+            Self::DismissCommentReports => "dismiss_comment_reports",
+            Self::GrantAdmin => "grant_admin",
+            Self::RevokeAdmin => "revoke_admin",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AuditEntry {
+    /// `None` for actions taken from the command line.
+    pub admin_id: Option<UserId>,
+    /// `None` once the admin's account has been deleted.
+    pub admin_username: Option<Username>,
+    pub action: String,
+    /// The user or quest the action was performed on.
+    pub target: String,
+    pub details: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,156 @@
+use chrono::{DateTime, Utc};
+use rocket::FromFormField;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{Error, QuestId, UserId};
+
+/// Longer comments are rejected.
+pub const MAX_COMMENT_LENGTH: usize = 10_000;
+pub const MAX_REPORT_REASON_LENGTH: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(transparent)]
+#[repr(transparent)]
+pub struct CommentId(Uuid);
+
+impl CommentId {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+
+    pub fn try_parse(input: impl AsRef<str>) -> Result<Self, Error> {
+        Ok(Self(Uuid::try_parse(input.as_ref())?))
+    }
+}
+
+impl Default for CommentId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Display for CommentId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<'r> rocket::request::FromParam<'r> for CommentId {
+    type Error = &'r str;
+
+    fn from_param(param: &'r str) -> Result<Self, Self::Error> {
+        Uuid::parse_str(param).map(CommentId).map_err(|_| param)
+    }
+}
+
+/// Every quest has one discussion per thread.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    FromFormField,
+    sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "comment_thread", rename_all = "snake_case")]
+pub enum CommentThread {
+    #[default]
+    Discussion,
+    /// Spoilers, only shown to users who completed the quest.
+    Solutions,
+}
+
+impl CommentThread {
+    pub fn is_spoiler(&self) -> bool {
+        *self == Self::Solutions
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Comment {
+    pub id: CommentId,
+    pub quest_id: QuestId,
+    pub thread: CommentThread,
+    /// The comment this one replies to, `None` for top-level comments.
+    pub parent_id: Option<CommentId>,
+    /// `None` once the comment was deleted or its author's account was.
+    pub author: Option<UserId>,
+    /// Markdown, empty once the comment was deleted. Deleted comments are kept so their replies
+    /// stay in place.
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub deleted: bool,
+    /// Hidden by a moderator.
+    pub hidden: bool,
+}
+
+impl Comment {
+    pub fn is_author(&self, user_id: &UserId) -> bool {
+        self.author.as_ref() == Some(user_id)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NewComment {
+    pub author: UserId,
+    /// Ignored for replies, which are posted in their parent's thread.
+    pub thread: CommentThread,
+    pub parent_id: Option<CommentId>,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CommentReport {
+    pub reporter: UserId,
+    pub reason: String,
+}
+
+/// A comment waiting for moderation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ReportedComment {
+    #[sqlx(flatten)]
+    pub comment: Comment,
+    pub reasons: Vec<String>,
+    pub last_reported_at: DateTime<Utc>,
+}
+
+/// An open report the user filed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FiledReport {
+    pub comment_id: CommentId,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The user's comments and reports, for their data export.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommentExport {
+    pub comments: Vec<Comment>,
+    pub reports: Vec<FiledReport>,
+}
+
+/// Checks the reason isn't too long.
+pub fn clean_report_reason(reason: &str) -> Result<String, Error> {
+    let reason = reason.trim().to_owned();
+    match reason.chars().count() > MAX_REPORT_REASON_LENGTH {
+        true => Err(Error::BadRequest),
+        false => Ok(reason),
+    }
+}
+
+/// Trims the body and checks it isn't empty or too long.
+pub fn clean_comment_body(body: &str) -> Result<String, Error> {
+    let body = body.replace("\r\n", "\n").trim().to_owned();
+    match body.is_empty() || body.chars().count() > MAX_COMMENT_LENGTH {
+        true => Err(Error::BadRequest),
+        false => Ok(body),
+    }
+}
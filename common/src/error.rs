@@ -23,7 +23,18 @@ pub enum Error {
         user: UserId,
         exit_status: ExitStatus,
     },
+    /// The quest context is still being generated, the request should be retried shortly.
+    QuestContextPending,
+    /// Generating the quest context failed, `0` describes why.
+    QuestContextGenerationFailed(String),
+    /// A quest package or an uploaded generator failed validation, `0` describes why.
+    InvalidQuestPackage(String),
     InvalidUuid(uuid::Error),
+    Crypto,
+    /// An event was published with a schema version this service doesn't know.
+    UnsupportedSchemaVersion(u32),
+    /// The user sent too many requests of this kind recently and has to wait.
+    TooManyRequests,
 }
 
 impl std::fmt::Display for Error {
@@ -83,14 +94,34 @@ impl From<uuid::Error> for Error {
     }
 }
 
+/// Seconds clients are asked to wait before retrying [`Error::QuestContextPending`].
+const QUEST_CONTEXT_RETRY_AFTER: &str = "2";
+
 impl<'r> Responder<'r, 'static> for Error {
     fn respond_to(self, _: &'r rocket::Request<'_>) -> rocket::response::Result<'static> {
+        if let Self::QuestContextPending = self {
+            let body = "Still being generated, please retry in a few seconds.";
+            return Response::build()
+                .status(http::Status::ServiceUnavailable)
+                .raw_header("Retry-After", QUEST_CONTEXT_RETRY_AFTER)
+                .sized_body(body.len(), std::io::Cursor::new(body))
+                .ok();
+        }
+
+        if let Self::InvalidQuestPackage(reason) = self {
+            return Response::build()
+                .status(http::Status::BadRequest)
+                .sized_body(reason.len(), std::io::Cursor::new(reason))
+                .ok();
+        }
+
         eprintln!("internal error: {:?}", self);
         Response::build()
             .status(match self {
                 Self::InvalidUsername(_) => http::Status::BadRequest,
                 Self::BadRequest => http::Status::BadRequest,
                 Self::Unauthorized => http::Status::Unauthorized,
+                Self::TooManyRequests => http::Status::TooManyRequests,
                 Self::Unsupported => http::Status::NotImplemented,
                 _ => http::Status::InternalServerError,
             })
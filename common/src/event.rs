@@ -1,15 +1,106 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use uuid::Uuid;
 
-use crate::{QuestId, UserId};
+use crate::{
+    Error, QuestId, UserId, Username,
+    comment::{CommentId, CommentThread},
+    notification::{Notification, NotificationId},
+};
 
 pub trait Event: Serialize + DeserializeOwned {
+    /// Bump this whenever the serialised form of the event changes incompatibly, and convert the
+    /// older forms in [`Event::upcast`].
+    const SCHEMA_VERSION: u32 = 1;
+
     fn get_subject(&self) -> &'static str;
+
+    /// Converts an event published with an older `schema_version` to the current form. Legacy
+    /// payloads published before envelopes existed have version `0` and the form of version 1.
+    fn upcast(schema_version: u32, event: serde_json::Value) -> Result<Self, Error> {
+        match schema_version {
+            0 => Ok(serde_json::from_value(event)?),
+            _ => Err(Error::UnsupportedSchemaVersion(schema_version)),
+        }
+    }
+}
+
+/// Legacy events are identified by their position in their stream, as UUIDs in this namespace.
+const LEGACY_EVENT_NAMESPACE: Uuid = Uuid::from_u128(0x635492e1_7367_4b58_be5f_39493e811e3a);
+
+/// Where JetStream stored a message. Gives events published without an envelope an identity that
+/// stays the same across redeliveries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamPosition<'a> {
+    pub stream: &'a str,
+    pub sequence: u64,
+    pub published_at: DateTime<Utc>,
+}
+
+/// Metadata published alongside every event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventEnvelope<E> {
+    /// Also sent as the `Nats-Msg-Id` header so JetStream drops duplicates.
+    pub id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    /// The service that published the event.
+    pub producer: String,
+    /// `0` for events that were published before envelopes existed.
+    pub schema_version: u32,
+    /// The ID of the event that (indirectly) caused this one.
+    pub correlation_id: Option<Uuid>,
+    pub event: E,
+}
+
+impl<E: Event> EventEnvelope<E> {
+    /// Decodes an envelope, falling back to the bare event payloads published by older
+    /// versions of the services. Those get an ID derived from `position` and the time JetStream
+    /// stored them as their occurrence time. Events of an older schema version are upcast, newer
+    /// ones are rejected.
+    pub fn decode(payload: &[u8], position: StreamPosition<'_>) -> Result<Self, Error> {
+        let envelope = match serde_json::from_slice::<EventEnvelope<serde_json::Value>>(payload) {
+            Ok(envelope) => envelope,
+            Err(_) => EventEnvelope {
+                id: Uuid::new_v5(
+                    &LEGACY_EVENT_NAMESPACE,
+                    format!("{}:{}", position.stream, position.sequence).as_bytes(),
+                ),
+                occurred_at: position.published_at,
+                producer: String::new(),
+                schema_version: 0,
+                correlation_id: None,
+                event: serde_json::from_slice(payload)?,
+            },
+        };
+        let event = match envelope.schema_version {
+            version if version == E::SCHEMA_VERSION => serde_json::from_value(envelope.event)?,
+            version if version > E::SCHEMA_VERSION => {
+                return Err(Error::UnsupportedSchemaVersion(version));
+            }
+            version => E::upcast(version, envelope.event)?,
+        };
+        Ok(Self {
+            id: envelope.id,
+            occurred_at: envelope.occurred_at,
+            producer: envelope.producer,
+            schema_version: envelope.schema_version,
+            correlation_id: envelope.correlation_id,
+            event,
+        })
+    }
+
+    /// The correlation ID to pass on to events caused by this one.
+    pub fn correlation_id(&self) -> Uuid {
+        self.correlation_id.unwrap_or(self.id)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum UserEvent {
     Created(UserId),
     Deleted(UserId),
+    LockedOut { user_id: UserId, retry_after: u64 },
+    Renamed { user_id: UserId, username: Username },
 }
 
 impl Event for UserEvent {
@@ -17,6 +108,8 @@ impl Event for UserEvent {
         match self {
             Self::Created(_) => "user.events.created",
             Self::Deleted(_) => "user.events.deleted",
+            Self::LockedOut { .. } => "user.events.locked_out",
+            Self::Renamed { .. } => "user.events.renamed",
         }
     }
 }
@@ -40,15 +133,211 @@ impl Event for QuestEvent {
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ProgressionEvent {
-    AnswerSubmitted { user_id: UserId, correct: bool },
-    QuestCompleted { user_id: UserId, quest_id: QuestId },
+    /// The user fetched the quest's input for the first time.
+    QuestStarted {
+        user_id: UserId,
+        quest_id: QuestId,
+    },
+    AnswerSubmitted {
+        user_id: UserId,
+        /// `None` in events published before answers were attributed to quests.
+        #[serde(default)]
+        quest_id: Option<QuestId>,
+        correct: bool,
+    },
+    QuestCompleted {
+        user_id: UserId,
+        quest_id: QuestId,
+    },
+    HintRevealed {
+        user_id: UserId,
+        quest_id: QuestId,
+        hint_index: u32,
+        /// Points deducted from the user's score.
+        penalty: u32,
+    },
 }
 
 impl Event for ProgressionEvent {
     fn get_subject(&self) -> &'static str {
         match self {
+            Self::QuestStarted { .. } => "progression.events.quest_started",
             Self::AnswerSubmitted { .. } => "progression.events.answer_submitted",
             Self::QuestCompleted { .. } => "progression.events.quest_completed",
+            Self::HintRevealed { .. } => "progression.events.hint_revealed",
         }
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AchievementEvent {
+    Unlocked {
+        user_id: UserId,
+        achievement_id: String,
+    },
+}
+
+impl Event for AchievementEvent {
+    fn get_subject(&self) -> &'static str {
+        match self {
+            Self::Unlocked { .. } => "achievement.events.unlocked",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatisticsEvent {
+    /// The empirical difficulty rating of a quest changed.
+    QuestRated { quest_id: QuestId, difficulty: i32 },
+}
+
+impl Event for StatisticsEvent {
+    fn get_subject(&self) -> &'static str {
+        match self {
+            Self::QuestRated { .. } => "statistics.events.quest_rated",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommentEvent {
+    Posted {
+        comment_id: CommentId,
+        quest_id: QuestId,
+        thread: CommentThread,
+        author: UserId,
+        /// The author of the comment this one replies to, if it still has one.
+        parent_author: Option<UserId>,
+    },
+    Edited {
+        comment_id: CommentId,
+        quest_id: QuestId,
+    },
+    Deleted {
+        comment_id: CommentId,
+        quest_id: QuestId,
+    },
+    Reported {
+        comment_id: CommentId,
+        quest_id: QuestId,
+        reporter: UserId,
+    },
+    /// A moderator hid or unhid the comment.
+    Hidden {
+        comment_id: CommentId,
+        quest_id: QuestId,
+        hidden: bool,
+    },
+}
+
+impl Event for CommentEvent {
+    fn get_subject(&self) -> &'static str {
+        match self {
+            Self::Posted { .. } => "comment.events.posted",
+            Self::Edited { .. } => "comment.events.edited",
+            Self::Deleted { .. } => "comment.events.deleted",
+            Self::Reported { .. } => "comment.events.reported",
+            Self::Hidden { .. } => "comment.events.hidden",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NotificationEvent {
+    Created(Notification),
+    /// The user read the notification, or all of their notifications if `None`.
+    Read {
+        user_id: UserId,
+        notification_id: Option<NotificationId>,
+    },
+}
+
+impl Event for NotificationEvent {
+    fn get_subject(&self) -> &'static str {
+        match self {
+            Self::Created(_) => "notification.events.created",
+            Self::Read { .. } => "notification.events.read",
+        }
+    }
+}
+
+/// Services that have to confirm a user's erasure before it counts as complete.
+pub const ERASURE_SERVICES: &[&str] = &[
+    "progression-service",
+    "quest-service",
+    "statistics-service",
+    "notification-service",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErasureEvent {
+    Completed { user_id: UserId, service: String },
+}
+
+impl Event for ErasureEvent {
+    fn get_subject(&self) -> &'static str {
+        match self {
+            Self::Completed { .. } => "erasure.events.completed",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(sequence: u64) -> StreamPosition<'static> {
+        StreamPosition {
+            stream: "USER_EVENTS",
+            sequence,
+            published_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn decodes_envelopes() {
+        let envelope = EventEnvelope {
+            id: Uuid::new_v4(),
+            occurred_at: DateTime::from_timestamp(1_600_000_000, 0).unwrap(),
+            producer: "user-service".to_owned(),
+            schema_version: UserEvent::SCHEMA_VERSION,
+            correlation_id: None,
+            event: UserEvent::Created(UserId::new()),
+        };
+        let payload = serde_json::to_vec(&envelope).unwrap();
+        let decoded = EventEnvelope::<UserEvent>::decode(&payload, position(1)).unwrap();
+        assert_eq!(decoded, envelope);
+    }
+
+    #[test]
+    fn legacy_payloads_keep_their_id_across_deliveries() {
+        let payload = serde_json::to_vec(&UserEvent::Created(UserId::new())).unwrap();
+        let first = EventEnvelope::<UserEvent>::decode(&payload, position(7)).unwrap();
+        let redelivered = EventEnvelope::<UserEvent>::decode(&payload, position(7)).unwrap();
+        assert_eq!(first.id, redelivered.id);
+        assert_eq!(first.schema_version, 0);
+        assert_eq!(first.occurred_at, position(7).published_at);
+
+        let republished = EventEnvelope::<UserEvent>::decode(&payload, position(8)).unwrap();
+        assert_ne!(first.id, republished.id);
+    }
+
+    #[test]
+    fn rejects_newer_schema_versions() {
+        let mut envelope = serde_json::to_value(EventEnvelope {
+            id: Uuid::new_v4(),
+            occurred_at: Utc::now(),
+            producer: "user-service".to_owned(),
+            schema_version: UserEvent::SCHEMA_VERSION,
+            correlation_id: None,
+            event: UserEvent::Created(UserId::new()),
+        })
+        .unwrap();
+        envelope["schema_version"] = (UserEvent::SCHEMA_VERSION + 1).into();
+        let payload = serde_json::to_vec(&envelope).unwrap();
+        assert!(matches!(
+            EventEnvelope::<UserEvent>::decode(&payload, position(1)),
+            Err(Error::UnsupportedSchemaVersion(version)) if version == UserEvent::SCHEMA_VERSION + 1
+        ));
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{Error, QuestId, UserId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(transparent)]
+#[repr(transparent)]
+pub struct SubmissionId(Uuid);
+
+impl SubmissionId {
+    pub fn try_parse(input: impl AsRef<str>) -> Result<Self, Error> {
+        Ok(Self(Uuid::try_parse(input.as_ref())?))
+    }
+}
+
+impl std::fmt::Display for SubmissionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<'r> rocket::request::FromParam<'r> for SubmissionId {
+    type Error = &'r str;
+
+    fn from_param(param: &'r str) -> Result<Self, Self::Error> {
+        Uuid::parse_str(param).map(SubmissionId).map_err(|_| param)
+    }
+}
+
+/// The outcome of judging a program, or of running it on a single test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "verdict")]
+pub enum Verdict {
+    #[serde(rename = "AC")]
+    #[sqlx(rename = "AC")]
+    Accepted,
+    #[serde(rename = "WA")]
+    #[sqlx(rename = "WA")]
+    WrongAnswer,
+    #[serde(rename = "TLE")]
+    #[sqlx(rename = "TLE")]
+    TimeLimitExceeded,
+    #[serde(rename = "RE")]
+    #[sqlx(rename = "RE")]
+    RuntimeError,
+    #[serde(rename = "CE")]
+    #[sqlx(rename = "CE")]
+    CompilationError,
+    /// The submission couldn't be judged, e.g. because the quest's generator failed.
+    #[serde(rename = "JE")]
+    #[sqlx(rename = "JE")]
+    JudgeError,
+}
+
+impl Verdict {
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            Self::Accepted => "AC",
+            Self::WrongAnswer => "WA",
+            Self::TimeLimitExceeded => "TLE",
+            Self::RuntimeError => "RE",
+            Self::CompilationError => "CE",
+            Self::JudgeError => "JE",
+        }
+    }
+
+    pub fn get_display_name(&self) -> &'static str {
+        match self {
+            Self::Accepted => "Accepted",
+            Self::WrongAnswer => "Wrong answer",
+            Self::TimeLimitExceeded => "Time limit exceeded",
+            Self::RuntimeError => "Runtime error",
+            Self::CompilationError => "Compilation error",
+            Self::JudgeError => "Judge error",
+        }
+    }
+}
+
+/// A language the judge can run programs in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Language {
+    pub id: String,
+    pub name: String,
+}
+
+/// One input a submitted program is run on, and the output it has to produce.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TestCase {
+    pub input: String,
+    pub answer: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TestResult {
+    pub test_index: i32,
+    pub verdict: Verdict,
+    /// Wall-clock run time in milliseconds.
+    pub time_ms: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ProgramSubmission {
+    pub id: SubmissionId,
+    pub user_id: UserId,
+    pub quest_id: QuestId,
+    pub language: String,
+    /// `None` while the submission waits to be judged.
+    pub verdict: Option<Verdict>,
+    pub submitted_at: DateTime<Utc>,
+    pub judged_at: Option<DateTime<Utc>>,
+}
+
+/// A submission with its source code and everything the judge reported about it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgramSubmissionDetails {
+    #[serde(flatten)]
+    pub submission: ProgramSubmission,
+    pub source: String,
+    /// What the compiler printed for failed compilations, or why the submission couldn't be
+    /// judged.
+    pub compiler_output: Option<String>,
+    pub tests: Vec<TestResult>,
+}
@@ -8,19 +8,34 @@ use std::{
     path::Path,
 };
 
+pub mod achievement;
+pub mod admin;
+pub mod comment;
 mod credentials;
 mod error;
 pub mod event;
+pub mod judge;
 pub mod nats;
+pub mod notification;
+pub mod outbox;
+pub mod package;
+pub mod progression;
 mod quest;
+pub mod rating;
 pub mod services;
 pub mod statistics;
 mod user;
 
 pub use credentials::Credentials;
 pub use error::Error;
-pub use quest::{PartialQuestData, Quest, QuestData, QuestDataFields, QuestEntry, QuestId};
-pub use user::{User, UserId, Username, UsernameRef};
+pub use quest::{
+    AnswerMode, GeneratorTestReport, Hint, PartialQuestData, PrewarmProgress, Quest, QuestData,
+    QuestDataFields, QuestEntry, QuestFilter, QuestId, QuestSort,
+};
+pub use user::{
+    ErasureStatus, Lockout, LoginResult, TotpEnrollment, User, UserAccount, UserId, UserProfile,
+    Username, UsernameRef,
+};
 
 pub fn load_salt(path: impl AsRef<Path>) -> io::Result<SaltString> {
     Ok(SaltString::from_b64(fs::read_to_string(path)?.trim()).expect("failed to load salt"))
@@ -59,3 +74,28 @@ pub fn load_or_generate_secret_key(path: impl AsRef<Path>) -> io::Result<String>
 
     Ok(generated_secret_key)
 }
+
+/// Escapes the wildcards of `LIKE` patterns, so `input` only matches itself.
+pub fn escape_like(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_like_escapes_wildcards() {
+        assert_eq!(escape_like("plain name"), "plain name");
+        assert_eq!(escape_like("100%"), "100\\%");
+        assert_eq!(escape_like("a_b"), "a\\_b");
+        assert_eq!(escape_like("back\\slash"), "back\\\\slash");
+    }
+}
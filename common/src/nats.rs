@@ -1,7 +1,36 @@
-use async_nats::jetstream;
-use rocket::futures::TryStreamExt as _;
+use std::{
+    collections::BTreeMap,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use crate::{Error, event::Event};
+use async_nats::{
+    HeaderMap, header,
+    jetstream::{self, AckKind, Message},
+};
+use chrono::{DateTime, Utc};
+use rocket::{
+    futures::TryStreamExt as _,
+    http,
+    response::{Responder, content::RawJson},
+    tokio,
+};
+use serde::Serialize;
+
+use crate::{
+    Error,
+    event::{Event, EventEnvelope, StreamPosition},
+};
+
+/// Messages that still fail after this many deliveries are moved to the dead-letter stream.
+pub const MAX_DELIVERIES: i64 = 5;
+/// Dead letters are published to `dead_letters.<consumer>.<original subject>`.
+pub const DEAD_LETTER_SUBJECT_PREFIX: &str = "dead_letters";
+
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+const MAX_ERROR_HEADER_LENGTH: usize = 1024;
 
 pub struct NatsClient {
     js: jetstream::Context,
@@ -15,38 +44,458 @@ impl NatsClient {
         Ok(Self { js })
     }
 
+    /// Feeds the events of `stream_name` to `handler` until the task is aborted.
+    ///
+    /// Failed events are redelivered with an increasing delay and moved to the dead-letter stream
+    /// after [`MAX_DELIVERIES`] attempts, events that can't be decoded are moved there right away.
+    /// If the subscription itself fails the consumer is recreated once NATS is reachable again.
     pub async fn consume<E: Event>(
         self,
         stream_name: impl AsRef<str>,
         consumer_name: String,
-        handler: impl AsyncFn(E) -> Result<(), Error>,
+        health: ConsumerHealth,
+        handler: impl AsyncFn(EventEnvelope<E>) -> Result<(), Error>,
+    ) {
+        let stream_name = stream_name.as_ref();
+        health.update(stream_name, &consumer_name, |_| ());
+
+        let mut reconnect_delay = RETRY_BASE_DELAY;
+        loop {
+            let res = self
+                .consume_messages(
+                    stream_name,
+                    &consumer_name,
+                    &health,
+                    &handler,
+                    &mut reconnect_delay,
+                )
+                .await;
+            let error = match res {
+                Ok(()) => "message stream ended".to_owned(),
+                Err(e) => e.to_string(),
+            };
+            eprintln!(
+                "NATS consumer {} on {}: {}, reconnecting in {:?}",
+                consumer_name, stream_name, error, reconnect_delay
+            );
+            health.update(stream_name, &consumer_name, |status| {
+                status.state = ConsumerState::Reconnecting;
+                status.record_error(error);
+            });
+
+            tokio::time::sleep(reconnect_delay).await;
+            reconnect_delay = (reconnect_delay * 2).min(MAX_RETRY_DELAY);
+        }
+    }
+
+    async fn consume_messages<E: Event>(
+        &self,
+        stream_name: &str,
+        consumer_name: &str,
+        health: &ConsumerHealth,
+        handler: &impl AsyncFn(EventEnvelope<E>) -> Result<(), Error>,
+        reconnect_delay: &mut Duration,
     ) -> Result<(), Error> {
-        let stream = self.js.get_stream(stream_name.as_ref()).await?;
+        let stream = self.js.get_stream(stream_name).await?;
 
         let consumer = stream
             .get_or_create_consumer(
-                &consumer_name,
+                consumer_name,
                 jetstream::consumer::pull::Config {
-                    durable_name: Some(consumer_name.clone()),
+                    durable_name: Some(consumer_name.to_owned()),
+                    max_deliver: MAX_DELIVERIES,
                     ..Default::default()
                 },
             )
             .await?;
 
         let mut messages = consumer.messages().await?;
+        println!("NATS consumer {} on {} running", consumer_name, stream_name);
+        health.update(stream_name, consumer_name, |status| {
+            status.state = ConsumerState::Running;
+        });
+        *reconnect_delay = RETRY_BASE_DELAY;
 
         while let Some(message) = messages.try_next().await? {
-            let event: E = serde_json::from_slice(&message.payload)?;
-            handler(event).await?;
-            message.ack().await?;
+            self.handle_message(&message, stream_name, consumer_name, health, handler)
+                .await?;
         }
 
         Ok(())
     }
 
-    pub async fn emit<E: Event>(&self, event: E) -> Result<(), Error> {
-        let payload = serde_json::to_vec(&event)?;
-        self.js.publish(event.get_subject(), payload.into()).await?;
+    async fn handle_message<E: Event>(
+        &self,
+        message: &Message,
+        stream_name: &str,
+        consumer_name: &str,
+        health: &ConsumerHealth,
+        handler: &impl AsyncFn(EventEnvelope<E>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let deliveries = message.info().map(|info| info.delivered).unwrap_or(1);
+
+        let error = match decode(message) {
+            Ok(envelope) => match handler(envelope).await {
+                Ok(()) => {
+                    message.ack().await?;
+                    health.update(stream_name, consumer_name, |status| status.processed += 1);
+                    return Ok(());
+                }
+                Err(e) if deliveries < MAX_DELIVERIES => {
+                    let delay = retry_delay(deliveries);
+                    eprintln!(
+                        "NATS consumer {} failed to handle {} (attempt {}), retrying in {:?}: {}",
+                        consumer_name, message.subject, deliveries, delay, e
+                    );
+                    message.ack_with(AckKind::Nak(Some(delay))).await?;
+                    health.update(stream_name, consumer_name, |status| {
+                        status.retried += 1;
+                        status.record_error(e.to_string());
+                    });
+                    return Ok(());
+                }
+                Err(e) => e.to_string(),
+            },
+            // Retrying won't make a malformed payload decode.
+            Err(e) => format!("failed to decode event: {}", e),
+        };
+
+        eprintln!(
+            "NATS consumer {} gave up on {} after {} attempt(s), moving it to the dead-letter stream: {}",
+            consumer_name, message.subject, deliveries, error
+        );
+        // JetStream won't deliver the message again after the last attempt, so it's only acked
+        // once the dead letter is stored.
+        let mut attempts = 1;
+        while let Err(e) = self
+            .dead_letter(message, consumer_name, deliveries, &error)
+            .await
+        {
+            let delay = retry_delay(attempts);
+            eprintln!(
+                "NATS consumer {} failed to dead-letter {} (attempt {}), retrying in {:?}: {}",
+                consumer_name, message.subject, attempts, delay, e
+            );
+            health.update(stream_name, consumer_name, |status| {
+                status.record_error(e.to_string());
+            });
+            // Keeps the message from being redelivered to another handler meanwhile.
+            let _ = message.ack_with(AckKind::Progress).await;
+            tokio::time::sleep(delay).await;
+            attempts += 1;
+        }
+        message.ack_with(AckKind::Term).await?;
+        health.update(stream_name, consumer_name, |status| {
+            status.dead_lettered += 1;
+            status.record_error(error);
+        });
         Ok(())
     }
+
+    async fn dead_letter(
+        &self,
+        message: &Message,
+        consumer_name: &str,
+        deliveries: i64,
+        error: &str,
+    ) -> Result<(), Error> {
+        let mut headers = message.headers.clone().unwrap_or_default();
+        headers.insert("Codequest-Original-Subject", message.subject.as_str());
+        headers.insert("Codequest-Consumer", consumer_name);
+        headers.insert("Codequest-Deliveries", deliveries.to_string());
+        headers.insert("Codequest-Error", header_safe(error));
+        self.js
+            .publish_with_headers(
+                format!(
+                    "{}.{}.{}",
+                    DEAD_LETTER_SUBJECT_PREFIX, consumer_name, message.subject
+                ),
+                headers,
+                message.payload.clone(),
+            )
+            .await?
+            .await?;
+        Ok(())
+    }
+
+    /// Reads every event currently stored in `stream_name`, oldest first, through an ephemeral
+    /// consumer, so durable consumers aren't affected.
+    pub async fn replay<E: Event>(&self, stream_name: impl AsRef<str>) -> Result<Replay<E>, Error> {
+        self.replay_from(stream_name, 1).await
+    }
+
+    /// Like [`NatsClient::replay`], but starts at the stream sequence `start_sequence`, e.g. to
+    /// continue after [`Replay::last_sequence`] of an earlier replay.
+    pub async fn replay_from<E: Event>(
+        &self,
+        stream_name: impl AsRef<str>,
+        start_sequence: u64,
+    ) -> Result<Replay<E>, Error> {
+        let stream_name = stream_name.as_ref();
+        let mut stream = self.js.get_stream(stream_name).await?;
+        let state = &stream.info().await?.state;
+        let is_empty = state.messages == 0 || state.last_sequence < start_sequence;
+
+        let consumer = stream
+            .create_consumer(jetstream::consumer::pull::Config {
+                deliver_policy: jetstream::consumer::DeliverPolicy::ByStartSequence {
+                    start_sequence,
+                },
+                ack_policy: jetstream::consumer::AckPolicy::None,
+                ..Default::default()
+            })
+            .await?;
+
+        Ok(Replay {
+            stream_name: stream_name.to_owned(),
+            messages: consumer.messages().await?,
+            done: is_empty,
+            last_sequence: start_sequence.saturating_sub(1),
+            _event: PhantomData,
+        })
+    }
+
+    /// Follows the events published to `stream_name` from now on through an ephemeral consumer.
+    /// Nothing is redelivered, so this is only suited for live updates that may be missed.
+    pub async fn subscribe<E: Event>(
+        &self,
+        stream_name: impl AsRef<str>,
+    ) -> Result<Subscription<E>, Error> {
+        let stream_name = stream_name.as_ref();
+        let stream = self.js.get_stream(stream_name).await?;
+
+        let consumer = stream
+            .create_consumer(jetstream::consumer::pull::Config {
+                deliver_policy: jetstream::consumer::DeliverPolicy::New,
+                ack_policy: jetstream::consumer::AckPolicy::None,
+                ..Default::default()
+            })
+            .await?;
+
+        Ok(Subscription {
+            stream_name: stream_name.to_owned(),
+            messages: consumer.messages().await?,
+            _event: PhantomData,
+        })
+    }
+
+    /// Publishes a raw payload and waits until JetStream has stored it. JetStream ignores
+    /// messages whose `message_id` it has already seen within its duplicate window.
+    pub async fn publish(
+        &self,
+        subject: String,
+        message_id: &str,
+        payload: Vec<u8>,
+    ) -> Result<(), Error> {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::NATS_MESSAGE_ID, message_id);
+        self.js
+            .publish_with_headers(subject, headers, payload.into())
+            .await?
+            .await?;
+        Ok(())
+    }
+}
+
+/// The events of a stream as they were when [`NatsClient::replay`] was called.
+pub struct Replay<E> {
+    stream_name: String,
+    messages: jetstream::consumer::pull::Stream,
+    done: bool,
+    last_sequence: u64,
+    _event: PhantomData<E>,
+}
+
+impl<E: Event> Replay<E> {
+    /// The stream sequence of the last message read, including skipped ones.
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence
+    }
+
+    /// Returns the next event, or `None` once the end of the stream is reached. Events that
+    /// can't be decoded are skipped.
+    pub async fn next(&mut self) -> Result<Option<EventEnvelope<E>>, Error> {
+        while !self.done {
+            let Some(message) = self.messages.try_next().await? else {
+                break;
+            };
+            let info = message.info()?;
+            self.done = info.pending == 0;
+            self.last_sequence = info.stream_sequence;
+            match decode(&message) {
+                Ok(envelope) => return Ok(Some(envelope)),
+                Err(e) => eprintln!(
+                    "skipping {} while replaying {}: failed to decode event: {}",
+                    message.subject, self.stream_name, e
+                ),
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The events published to a stream after [`NatsClient::subscribe`] was called.
+pub struct Subscription<E> {
+    stream_name: String,
+    messages: jetstream::consumer::pull::Stream,
+    _event: PhantomData<E>,
+}
+
+impl<E: Event> Subscription<E> {
+    /// Waits for the next event. Returns `None` if the subscription ended, events that can't be
+    /// decoded are skipped.
+    pub async fn next(&mut self) -> Result<Option<EventEnvelope<E>>, Error> {
+        while let Some(message) = self.messages.try_next().await? {
+            match decode(&message) {
+                Ok(envelope) => return Ok(Some(envelope)),
+                Err(e) => eprintln!(
+                    "skipping {} while following {}: failed to decode event: {}",
+                    message.subject, self.stream_name, e
+                ),
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn decode<E: Event>(message: &Message) -> Result<EventEnvelope<E>, Error> {
+    let info = message.info()?;
+    let position = StreamPosition {
+        stream: info.stream,
+        sequence: info.stream_sequence,
+        published_at: DateTime::from_timestamp(
+            info.published.unix_timestamp(),
+            info.published.nanosecond(),
+        )
+        .unwrap_or_else(Utc::now),
+    };
+    EventEnvelope::decode(&message.payload, position)
+}
+
+fn retry_delay(deliveries: i64) -> Duration {
+    let exponent = deliveries.clamp(1, 16) as u32 - 1;
+    (RETRY_BASE_DELAY * 2u32.pow(exponent)).min(MAX_RETRY_DELAY)
+}
+
+/// Header values can't contain line breaks.
+fn header_safe(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .take(MAX_ERROR_HEADER_LENGTH)
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsumerState {
+    Starting,
+    Running,
+    Reconnecting,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConsumerStatus {
+    pub stream: String,
+    pub consumer: String,
+    pub state: ConsumerState,
+    pub processed: u64,
+    pub retried: u64,
+    pub dead_lettered: u64,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+impl ConsumerStatus {
+    fn record_error(&mut self, error: String) {
+        self.last_error = Some(error);
+        self.last_error_at = Some(Utc::now());
+    }
+}
+
+/// The state of every consumer of a service. Serves as the response of its `/health` route,
+/// which answers with `503 Service Unavailable` while any consumer isn't running.
+#[derive(Debug, Clone, Default)]
+pub struct ConsumerHealth {
+    consumers: Arc<Mutex<BTreeMap<(String, String), ConsumerStatus>>>,
+}
+
+impl ConsumerHealth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn statuses(&self) -> Vec<ConsumerStatus> {
+        self.lock().values().cloned().collect()
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.lock()
+            .values()
+            .all(|status| status.state == ConsumerState::Running)
+    }
+
+    fn update(
+        &self,
+        stream_name: &str,
+        consumer_name: &str,
+        update: impl FnOnce(&mut ConsumerStatus),
+    ) {
+        let mut consumers = self.lock();
+        let status = consumers
+            .entry((stream_name.to_owned(), consumer_name.to_owned()))
+            .or_insert_with(|| ConsumerStatus {
+                stream: stream_name.to_owned(),
+                consumer: consumer_name.to_owned(),
+                state: ConsumerState::Starting,
+                processed: 0,
+                retried: 0,
+                dead_lettered: 0,
+                last_error: None,
+                last_error_at: None,
+            });
+        update(status);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<(String, String), ConsumerStatus>> {
+        self.consumers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ConsumerHealth {
+    fn respond_to(self, request: &'r rocket::Request<'_>) -> rocket::response::Result<'static> {
+        let status = match self.is_healthy() {
+            true => http::Status::Ok,
+            false => http::Status::ServiceUnavailable,
+        };
+        let body = serde_json::to_string(&self.statuses())
+            .map_err(|_| http::Status::InternalServerError)?;
+        (status, RawJson(body)).respond_to(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_maximum() {
+        assert_eq!(retry_delay(0), RETRY_BASE_DELAY);
+        assert_eq!(retry_delay(1), RETRY_BASE_DELAY);
+        assert_eq!(retry_delay(2), RETRY_BASE_DELAY * 2);
+        assert_eq!(retry_delay(4), RETRY_BASE_DELAY * 8);
+        assert_eq!(retry_delay(7), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(i64::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn header_safe_strips_line_breaks_and_truncates() {
+        assert_eq!(header_safe("first\r\nsecond"), "first  second");
+        assert_eq!(
+            header_safe(&"x".repeat(2 * MAX_ERROR_HEADER_LENGTH)).len(),
+            MAX_ERROR_HEADER_LENGTH
+        );
+    }
 }
@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{Error, QuestId, UserId, comment::CommentId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(transparent)]
+#[repr(transparent)]
+pub struct NotificationId(Uuid);
+
+impl NotificationId {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+
+    pub fn try_parse(input: impl AsRef<str>) -> Result<Self, Error> {
+        Ok(Self(Uuid::try_parse(input.as_ref())?))
+    }
+}
+
+impl Default for NotificationId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Display for NotificationId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<'r> rocket::request::FromParam<'r> for NotificationId {
+    type Error = &'r str;
+
+    fn from_param(param: &'r str) -> Result<Self, Self::Error> {
+        Uuid::parse_str(param)
+            .map(NotificationId)
+            .map_err(|_| param)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "notification_kind", rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// Someone else published a new quest.
+    QuestPublished,
+    /// Someone completed the user's quest.
+    QuestCompleted,
+    /// Someone commented on the user's quest.
+    QuestCommented,
+    /// Someone replied to the user's comment.
+    CommentReplied,
+    /// The user's quest was made official.
+    QuestApproved,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Notification {
+    pub id: NotificationId,
+    /// The user the notification is for.
+    pub user_id: UserId,
+    pub kind: NotificationKind,
+    pub quest_id: QuestId,
+    /// The user whose action caused the notification, `None` once their account was deleted.
+    pub actor: Option<UserId>,
+    pub comment_id: Option<CommentId>,
+    pub created_at: DateTime<Utc>,
+    pub read: bool,
+}
@@ -0,0 +1,201 @@
+//! Transactional outbox.
+//!
+//! Services write their events into an `outbox` table in the same transaction as the change that
+//! caused them, so an event is recorded if and only if the change is committed. [`OutboxRelay`]
+//! then wraps the recorded events in an [`EventEnvelope`] and publishes them to JetStream in
+//! order, retrying until NATS accepts them.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rocket::tokio::{self, task::JoinHandle, time::Instant};
+use sqlx::{FromRow, PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::{
+    Error,
+    event::{Event, EventEnvelope},
+    nats::NatsClient,
+};
+
+const BATCH_SIZE: i64 = 100;
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How long sent events are kept around before they are purged.
+const RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Records `event` in the outbox. Pass the transaction the domain change is written in.
+pub async fn enqueue<E: Event>(executor: impl PgExecutor<'_>, event: &E) -> Result<(), Error> {
+    insert(executor, event, None).await
+}
+
+/// Like [`enqueue`], for events caused by another event. Use
+/// [`EventEnvelope::correlation_id`] of the causing event.
+pub async fn enqueue_correlated<E: Event>(
+    executor: impl PgExecutor<'_>,
+    event: &E,
+    correlation_id: Uuid,
+) -> Result<(), Error> {
+    insert(executor, event, Some(correlation_id)).await
+}
+
+async fn insert<E: Event>(
+    executor: impl PgExecutor<'_>,
+    event: &E,
+    correlation_id: Option<Uuid>,
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT INTO outbox (subject, payload, schema_version, correlation_id) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(event.get_subject())
+    .bind(serde_json::to_string(event)?)
+    .bind(E::SCHEMA_VERSION as i32)
+    .bind(correlation_id)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+#[derive(FromRow)]
+struct OutboxRow {
+    id: i64,
+    event_id: Uuid,
+    subject: String,
+    payload: String,
+    schema_version: i32,
+    correlation_id: Option<Uuid>,
+    created_at: DateTime<Utc>,
+}
+
+/// Publishes the events in the outbox table of `pool` to JetStream.
+pub struct OutboxRelay {
+    pool: PgPool,
+    nats_address: String,
+    /// Recorded as the producer of every published event.
+    producer: String,
+}
+
+impl OutboxRelay {
+    pub fn new(pool: PgPool, nats_address: impl Into<String>, producer: impl Into<String>) -> Self {
+        Self {
+            pool,
+            nats_address: nats_address.into(),
+            producer: producer.into(),
+        }
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move { self.run().await })
+    }
+
+    async fn run(self) {
+        let nats_client = self.connect().await;
+        println!("outbox relay started");
+
+        let mut retry_delay = POLL_INTERVAL;
+        let mut last_purge = Instant::now();
+        loop {
+            match self.relay_batch(&nats_client).await {
+                Ok(sent) => {
+                    retry_delay = POLL_INTERVAL;
+                    if sent < BATCH_SIZE as usize {
+                        tokio::time::sleep(POLL_INTERVAL).await;
+                    }
+                }
+                Err(e) => {
+                    eprintln!(
+                        "outbox relay: failed to publish events, retrying in {:?}: {}",
+                        retry_delay, e
+                    );
+                    tokio::time::sleep(retry_delay).await;
+                    retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                }
+            }
+
+            if last_purge.elapsed() >= PURGE_INTERVAL {
+                if let Err(e) = self.purge().await {
+                    eprintln!("outbox relay: failed to purge sent events: {}", e);
+                }
+                last_purge = Instant::now();
+            }
+        }
+    }
+
+    async fn connect(&self) -> NatsClient {
+        let mut retry_delay = POLL_INTERVAL;
+        loop {
+            match NatsClient::new(&self.nats_address).await {
+                Ok(nats_client) => return nats_client,
+                Err(e) => {
+                    eprintln!(
+                        "outbox relay: failed to connect to NATS, retrying in {:?}: {}",
+                        retry_delay, e
+                    );
+                    tokio::time::sleep(retry_delay).await;
+                    retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                }
+            }
+        }
+    }
+
+    /// Publishes the oldest unsent events and returns how many were sent. Stops at the first
+    /// failure so events are published in the order they were recorded.
+    async fn relay_batch(&self, nats_client: &NatsClient) -> Result<usize, Error> {
+        let mut tx = self.pool.begin().await?;
+        let rows = sqlx::query_as::<_, OutboxRow>(
+            "SELECT id, event_id, subject, payload, schema_version, correlation_id, created_at
+                FROM outbox WHERE sent_at IS NULL
+                ORDER BY id LIMIT $1 FOR UPDATE SKIP LOCKED",
+        )
+        .bind(BATCH_SIZE)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut sent = Vec::with_capacity(rows.len());
+        let mut result = Ok(());
+        for row in rows {
+            if let Err(e) = self.publish(nats_client, &row).await {
+                result = Err(e);
+                break;
+            }
+            sent.push(row.id);
+        }
+
+        if !sent.is_empty() {
+            sqlx::query("UPDATE outbox SET sent_at = now() WHERE id = ANY($1)")
+                .bind(&sent)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        result.map(|_| sent.len())
+    }
+
+    async fn publish(&self, nats_client: &NatsClient, row: &OutboxRow) -> Result<(), Error> {
+        let envelope = EventEnvelope {
+            id: row.event_id,
+            occurred_at: row.created_at,
+            producer: self.producer.clone(),
+            schema_version: row.schema_version as u32,
+            correlation_id: row.correlation_id,
+            event: serde_json::from_str::<serde_json::Value>(&row.payload)?,
+        };
+        nats_client
+            .publish(
+                row.subject.clone(),
+                &row.event_id.to_string(),
+                serde_json::to_vec(&envelope)?,
+            )
+            .await
+    }
+
+    async fn purge(&self) -> Result<(), Error> {
+        sqlx::query("DELETE FROM outbox WHERE sent_at < now() - make_interval(secs => $1)")
+            .bind(RETENTION.as_secs_f64())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
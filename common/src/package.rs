@@ -0,0 +1,251 @@
+//! Self-contained quest packages, for moving quests between instances.
+//!
+//! A package is a JSON document holding the quest's [`QuestManifest`], its Markdown description
+//! and its generator executable. Authors, archival and statistics belong to an instance and aren't
+//! part of packages.
+
+use base64::{Engine as _, prelude::BASE64_STANDARD};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{AnswerMode, Error, Hint, Quest, QuestData, QuestId};
+
+/// The package format written by this version, packages in other formats are rejected.
+pub const PACKAGE_FORMAT_VERSION: u32 = 1;
+/// Generators are executables, but they shouldn't need to be larger than this.
+pub const MAX_GENERATOR_SIZE: usize = 32 * 1024 * 1024;
+const MAX_NAME_LENGTH: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QuestManifest {
+    /// Kept across instances, so the same quest can be imported again to update it.
+    pub id: QuestId,
+    pub name: String,
+    pub official: bool,
+    #[serde(default)]
+    pub answer_mode: AnswerMode,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hints: Vec<Hint>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QuestPackage {
+    pub format_version: u32,
+    pub manifest: QuestManifest,
+    /// Markdown.
+    pub description: String,
+    /// The generator executable, `None` for quests without one.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_base64",
+        deserialize_with = "deserialize_base64"
+    )]
+    pub generator: Option<Vec<u8>>,
+}
+
+impl QuestPackage {
+    pub fn new(quest: Quest, hints: Vec<Hint>, generator: Option<Vec<u8>>) -> Self {
+        Self {
+            format_version: PACKAGE_FORMAT_VERSION,
+            manifest: QuestManifest {
+                id: quest.id,
+                name: quest.name,
+                official: quest.official,
+                answer_mode: quest.answer_mode,
+                hints,
+            },
+            description: quest.text,
+            generator,
+        }
+    }
+
+    /// Checks everything that can be checked without installing the quest.
+    pub fn validate(&self) -> Result<(), Error> {
+        let invalid = |reason: &str| Err(Error::InvalidQuestPackage(reason.to_owned()));
+        if self.format_version != PACKAGE_FORMAT_VERSION {
+            return Err(Error::InvalidQuestPackage(format!(
+                "unsupported format version {}, expected {}",
+                self.format_version, PACKAGE_FORMAT_VERSION
+            )));
+        }
+        let name = self.manifest.name.trim();
+        if name.is_empty() {
+            return invalid("the quest has no name");
+        }
+        if name.chars().count() > MAX_NAME_LENGTH {
+            return invalid("the quest's name is too long");
+        }
+        if self.description.trim().is_empty() {
+            return invalid("the quest has no description");
+        }
+        if self
+            .manifest
+            .hints
+            .iter()
+            .any(|hint| hint.text.trim().is_empty())
+        {
+            return invalid("a hint is empty");
+        }
+        match &self.generator {
+            Some(generator) => validate_generator(generator)?,
+            // The judge needs generated test cases.
+            None if self.manifest.answer_mode == AnswerMode::Program => {
+                return invalid("program quests need a generator");
+            }
+            None => (),
+        }
+        Ok(())
+    }
+
+    /// The quest's data, for creating or updating it.
+    pub fn to_quest_data(&self) -> QuestData {
+        QuestData::new(
+            self.manifest.name.trim(),
+            None,
+            self.manifest.official,
+            self.description.clone(),
+        )
+        .with_hints(self.manifest.hints.clone())
+        .with_answer_mode(self.manifest.answer_mode)
+    }
+}
+
+/// Checks that `generator` looks like an executable of reasonable size, for packages and
+/// generators uploaded on their own.
+pub fn validate_generator(generator: &[u8]) -> Result<(), Error> {
+    let invalid = |reason: &str| Err(Error::InvalidQuestPackage(reason.to_owned()));
+    if generator.len() > MAX_GENERATOR_SIZE {
+        return invalid("the generator is too large");
+    }
+    if !is_executable(generator) {
+        return invalid("the generator is neither an ELF executable nor a script");
+    }
+    Ok(())
+}
+
+fn is_executable(file: &[u8]) -> bool {
+    file.starts_with(b"\x7fELF") || file.starts_with(b"#!")
+}
+
+fn serialize_base64<S: Serializer>(
+    bytes: &Option<Vec<u8>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match bytes {
+        Some(bytes) => serializer.serialize_some(&BASE64_STANDARD.encode(bytes)),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_base64<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<u8>>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|encoded| {
+            BASE64_STANDARD
+                .decode(encoded)
+                .map_err(serde::de::Error::custom)
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package() -> QuestPackage {
+        QuestPackage {
+            format_version: PACKAGE_FORMAT_VERSION,
+            manifest: QuestManifest {
+                id: QuestId::new(),
+                name: "Sum".to_owned(),
+                official: false,
+                answer_mode: AnswerMode::Answer,
+                hints: vec![Hint {
+                    text: "Add them up.".to_owned(),
+                    penalty: 0,
+                }],
+            },
+            description: "Sum the numbers.".to_owned(),
+            generator: Some(b"#!/bin/sh\nprintf '1\\0001'".to_vec()),
+        }
+    }
+
+    fn rejection(package: &QuestPackage) -> String {
+        match package.validate() {
+            Err(Error::InvalidQuestPackage(reason)) => reason,
+            result => panic!("expected a rejection, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn valid_packages_pass() {
+        package().validate().unwrap();
+
+        let mut without_generator = package();
+        without_generator.generator = None;
+        without_generator.validate().unwrap();
+
+        let mut elf = package();
+        elf.manifest.answer_mode = AnswerMode::Program;
+        elf.generator = Some(b"\x7fELF\x02\x01\x01".to_vec());
+        elf.validate().unwrap();
+    }
+
+    #[test]
+    fn other_format_versions_are_rejected() {
+        let mut package = package();
+        package.format_version = PACKAGE_FORMAT_VERSION + 1;
+        assert_eq!(
+            rejection(&package),
+            "unsupported format version 2, expected 1"
+        );
+    }
+
+    #[test]
+    fn names_must_be_present_and_short() {
+        let mut package = package();
+        package.manifest.name = "  ".to_owned();
+        assert_eq!(rejection(&package), "the quest has no name");
+        package.manifest.name = "é".repeat(MAX_NAME_LENGTH);
+        package.validate().unwrap();
+        package.manifest.name.push('a');
+        assert_eq!(rejection(&package), "the quest's name is too long");
+    }
+
+    #[test]
+    fn descriptions_and_hints_must_not_be_empty() {
+        let mut package = package();
+        package.description = "\n".to_owned();
+        assert_eq!(rejection(&package), "the quest has no description");
+
+        let mut package = self::package();
+        package.manifest.hints.push(Hint {
+            text: " ".to_owned(),
+            penalty: 5,
+        });
+        assert_eq!(rejection(&package), "a hint is empty");
+    }
+
+    #[test]
+    fn generators_must_be_executables_of_reasonable_size() {
+        let mut package = package();
+        package.generator = Some(b"MZ\x90\x00".to_vec());
+        assert_eq!(
+            rejection(&package),
+            "the generator is neither an ELF executable nor a script"
+        );
+        let mut generator = b"#!".to_vec();
+        generator.resize(MAX_GENERATOR_SIZE + 1, b' ');
+        package.generator = Some(generator);
+        assert_eq!(rejection(&package), "the generator is too large");
+    }
+
+    #[test]
+    fn program_quests_need_a_generator() {
+        let mut package = package();
+        package.manifest.answer_mode = AnswerMode::Program;
+        package.generator = None;
+        assert_eq!(rejection(&package), "program quests need a generator");
+    }
+}
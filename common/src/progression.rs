@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{QuestId, judge::ProgramSubmissionDetails};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StartedQuest {
+    pub quest_id: QuestId,
+    pub started_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CompletedQuest {
+    pub quest_id: QuestId,
+    pub completed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Submission {
+    pub quest_id: QuestId,
+    pub answer: String,
+    pub correct: bool,
+    pub submitted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RevealedHint {
+    pub quest_id: QuestId,
+    /// Position of the hint among the quest's hints, starting at 0.
+    pub hint_index: i32,
+    pub penalty: i32,
+    pub revealed_at: DateTime<Utc>,
+}
+
+/// Everything the progression service stores about a single user.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgressionExport {
+    pub started_quests: Vec<StartedQuest>,
+    pub completed_quests: Vec<CompletedQuest>,
+    pub submissions: Vec<Submission>,
+    #[serde(default)]
+    pub revealed_hints: Vec<RevealedHint>,
+    #[serde(default)]
+    pub program_submissions: Vec<ProgramSubmissionDetails>,
+}
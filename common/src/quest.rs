@@ -1,3 +1,5 @@
+use chrono::{DateTime, Utc};
+use rocket::{FromForm, FromFormField};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -39,6 +41,64 @@ pub struct QuestEntry {
     pub name: String,
     pub author: Option<UserId>,
     pub official: bool,
+    #[serde(default)]
+    pub archived: bool,
+    /// Empirical difficulty rating computed by the statistics service, `None` until enough
+    /// users attempted the quest.
+    #[serde(default)]
+    #[sqlx(default)]
+    pub difficulty: Option<i32>,
+    /// How many users rated the quest.
+    #[serde(default)]
+    #[sqlx(default)]
+    pub rating_count: i64,
+    /// The sum of the users' ratings, see [`Self::average_rating`].
+    #[serde(default)]
+    #[sqlx(default)]
+    pub rating_sum: i64,
+}
+
+/// How far the latest background generation of a quest's contexts got.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, FromRow)]
+pub struct PrewarmProgress {
+    pub quest_id: QuestId,
+    /// Contexts that were missing when the generation started.
+    pub total: i32,
+    /// Contexts still waiting to be generated, including the failed ones.
+    pub remaining: i64,
+    pub failed: i64,
+    pub started_at: DateTime<Utc>,
+}
+
+impl PrewarmProgress {
+    pub fn generated(&self) -> i64 {
+        self.total as i64 - self.remaining
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.remaining == self.failed
+    }
+}
+
+/// What running a quest's generator for synthetic users turned up.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeneratorTestReport {
+    pub users: usize,
+    /// Every user and test is generated twice, to check the generator is deterministic.
+    pub runs: usize,
+    /// Distinct inputs across the users.
+    pub unique_inputs: usize,
+    pub mean_runtime_ms: f64,
+    pub slowest_runtime_ms: f64,
+    pub largest_input_bytes: usize,
+    pub largest_answer_bytes: usize,
+    pub problems: Vec<String>,
+}
+
+impl GeneratorTestReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
 }
 
 impl QuestEntry {
@@ -48,8 +108,41 @@ impl QuestEntry {
             name: name.into(),
             author,
             official,
+            archived: false,
+            difficulty: None,
+            rating_count: 0,
+            rating_sum: 0,
         }
     }
+
+    /// From 1 to 5, `None` until someone rated the quest.
+    pub fn average_rating(&self) -> Option<f64> {
+        (self.rating_count > 0).then(|| self.rating_sum as f64 / self.rating_count as f64)
+    }
+}
+
+/// How users prove they solved a quest.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    FromFormField,
+    sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "answer_mode", rename_all = "snake_case")]
+pub enum AnswerMode {
+    /// Users paste the answer for their input.
+    #[default]
+    Answer,
+    /// Users submit a program, which the judge runs against several generated inputs.
+    Program,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, FromRow)]
@@ -59,8 +152,12 @@ pub struct Quest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub author: Option<UserId>,
     pub official: bool,
+    #[serde(default)]
+    pub archived: bool,
     #[sqlx(rename = "description")]
     pub text: String,
+    #[serde(default)]
+    pub answer_mode: AnswerMode,
 }
 
 impl Quest {
@@ -75,7 +172,9 @@ impl Quest {
             name: name.into(),
             author,
             official,
+            archived: false,
             text: text.into(),
+            answer_mode: AnswerMode::Answer,
         }
     }
 
@@ -85,6 +184,10 @@ impl Quest {
             name: self.name.clone(),
             author: self.author,
             official: self.official,
+            archived: self.archived,
+            difficulty: None,
+            rating_count: 0,
+            rating_sum: 0,
         }
     }
 
@@ -96,6 +199,16 @@ impl Quest {
     }
 }
 
+/// Help for users who are stuck on a quest. A quest's hints are revealed one at a time, in order.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, FromForm)]
+pub struct Hint {
+    pub text: String,
+    /// Points deducted from the user's score for revealing the hint, 0 for a free hint.
+    #[serde(default)]
+    #[field(default = 0)]
+    pub penalty: u32,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct QuestData {
     pub name: String,
@@ -103,6 +216,10 @@ pub struct QuestData {
     pub author: Option<UserId>,
     pub official: bool,
     pub text: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hints: Vec<Hint>,
+    #[serde(default)]
+    pub answer_mode: AnswerMode,
 }
 
 impl QuestData {
@@ -117,8 +234,20 @@ impl QuestData {
             author,
             official,
             text: text.into(),
+            hints: Vec::new(),
+            answer_mode: AnswerMode::Answer,
         }
     }
+
+    pub fn with_hints(mut self, hints: Vec<Hint>) -> Self {
+        self.hints = hints;
+        self
+    }
+
+    pub fn with_answer_mode(mut self, answer_mode: AnswerMode) -> Self {
+        self.answer_mode = answer_mode;
+        self
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -129,8 +258,15 @@ pub struct PartialQuestData {
     pub author: Option<Option<UserId>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub official: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text: Option<String>,
+    /// Replaces all of the quest's hints.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hints: Option<Vec<Hint>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub answer_mode: Option<AnswerMode>,
 }
 
 impl PartialQuestData {
@@ -139,7 +275,10 @@ impl PartialQuestData {
             name: None,
             author: None,
             official: None,
+            archived: None,
             text: None,
+            hints: None,
+            answer_mode: None,
         }
     }
 
@@ -158,11 +297,26 @@ impl PartialQuestData {
         return self;
     }
 
+    pub fn with_archived(mut self, archived: bool) -> Self {
+        self.archived = Some(archived);
+        self
+    }
+
     pub fn with_text(mut self, text: impl Into<String>) -> Self {
         self.text = Some(text.into());
         return self;
     }
 
+    pub fn with_hints(mut self, hints: Vec<Hint>) -> Self {
+        self.hints = Some(hints);
+        self
+    }
+
+    pub fn with_answer_mode(mut self, answer_mode: AnswerMode) -> Self {
+        self.answer_mode = Some(answer_mode);
+        self
+    }
+
     pub fn set_name(&mut self, name: impl Into<String>) {
         self.name = Some(name.into());
     }
@@ -175,15 +329,97 @@ impl PartialQuestData {
         self.official = Some(official);
     }
 
+    pub fn set_archived(&mut self, archived: bool) {
+        self.archived = Some(archived);
+    }
+
     pub fn set_text(&mut self, text: impl Into<String>) {
         self.text = Some(text.into());
     }
 
+    pub fn set_hints(&mut self, hints: Vec<Hint>) {
+        self.hints = Some(hints);
+    }
+
+    pub fn set_answer_mode(&mut self, answer_mode: AnswerMode) {
+        self.answer_mode = Some(answer_mode);
+    }
+
     pub fn is_empty(&self) -> bool {
         self.name.is_none()
             && self.author.is_none()
             && self.official.is_none()
+            && self.archived.is_none()
             && self.text.is_none()
+            && self.hints.is_none()
+            && self.answer_mode.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, FromFormField)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestSort {
+    Name,
+    /// Easiest first, unrated quests last.
+    Difficulty,
+    /// Best rated first, unrated quests last.
+    Rating,
+}
+
+/// Filters for the quest listings. Unset fields match every quest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize, FromForm)]
+pub struct QuestFilter {
+    pub name: Option<String>,
+    pub official: Option<bool>,
+    pub archived: Option<bool>,
+    /// Unrated quests don't match once a difficulty bound is set.
+    pub min_difficulty: Option<i32>,
+    pub max_difficulty: Option<i32>,
+    /// Sorts by name if unset.
+    pub sort_by: Option<QuestSort>,
+    /// Only the quests this user marked as favourites.
+    pub favourites_of: Option<UserId>,
+}
+
+impl QuestFilter {
+    /// Sorts `quests` as requested by [`Self::sort_by`].
+    pub fn sort(&self, quests: &mut [QuestEntry]) {
+        match self.sort_by.unwrap_or(QuestSort::Name) {
+            QuestSort::Name => quests.sort_by(|a, b| a.name.cmp(&b.name)),
+            QuestSort::Difficulty => quests.sort_by(|a, b| {
+                (a.difficulty.is_none(), a.difficulty, &a.name).cmp(&(
+                    b.difficulty.is_none(),
+                    b.difficulty,
+                    &b.name,
+                ))
+            }),
+            QuestSort::Rating => quests.sort_by(|a, b| {
+                let rating = |quest: &QuestEntry| quest.average_rating().unwrap_or(f64::MIN);
+                rating(b)
+                    .total_cmp(&rating(a))
+                    .then_with(|| a.name.cmp(&b.name))
+            }),
+        }
+    }
+
+    /// Favourites aren't part of [`QuestEntry`], so filtering by them never matches here.
+    pub fn matches(&self, quest: &QuestEntry) -> bool {
+        self.name
+            .as_ref()
+            .is_none_or(|name| quest.name.to_lowercase().contains(&name.to_lowercase()))
+            && self
+                .official
+                .is_none_or(|official| quest.official == official)
+            && self
+                .archived
+                .is_none_or(|archived| quest.archived == archived)
+            && self
+                .min_difficulty
+                .is_none_or(|min| quest.difficulty.is_some_and(|difficulty| difficulty >= min))
+            && self
+                .max_difficulty
+                .is_none_or(|max| quest.difficulty.is_some_and(|difficulty| difficulty <= max))
+            && self.favourites_of.is_none()
     }
 }
 
@@ -241,3 +477,43 @@ impl QuestDataFields {
         return self;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rated(name: &str, ratings: &[i64]) -> QuestEntry {
+        let mut quest = QuestEntry::new(name, None, false);
+        quest.rating_count = ratings.len() as i64;
+        quest.rating_sum = ratings.iter().sum();
+        quest
+    }
+
+    #[test]
+    fn average_ratings_need_a_rating() {
+        assert_eq!(rated("a", &[]).average_rating(), None);
+        assert_eq!(rated("a", &[4]).average_rating(), Some(4.0));
+        assert_eq!(rated("a", &[5, 4, 4, 1]).average_rating(), Some(3.5));
+    }
+
+    #[test]
+    fn rating_sort_puts_the_best_first_and_unrated_last() {
+        let mut quests = vec![
+            rated("unrated", &[]),
+            rated("b", &[3, 4]),
+            rated("poor", &[1]),
+            rated("best", &[5]),
+            rated("a", &[4, 3]),
+        ];
+        let filter = QuestFilter {
+            sort_by: Some(QuestSort::Rating),
+            ..Default::default()
+        };
+        filter.sort(&mut quests);
+        let names = quests
+            .iter()
+            .map(|quest| quest.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["best", "a", "b", "poor", "unrated"]);
+    }
+}
@@ -0,0 +1,107 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, QuestId, UserId};
+
+pub const MIN_RATING: i16 = 1;
+pub const MAX_RATING: i16 = 5;
+pub const MAX_FEEDBACK_LENGTH: usize = 2000;
+
+/// A user's rating of a quest they completed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RatingData {
+    pub user_id: UserId,
+    pub rating: i16,
+    /// For the quest's author.
+    pub feedback: Option<String>,
+}
+
+impl RatingData {
+    /// Checks the rating is in range, trims the feedback and drops it if it's empty.
+    pub fn clean(mut self) -> Result<Self, Error> {
+        if !(MIN_RATING..=MAX_RATING).contains(&self.rating) {
+            return Err(Error::BadRequest);
+        }
+        self.feedback = self
+            .feedback
+            .map(|feedback| feedback.replace("\r\n", "\n").trim().to_owned())
+            .filter(|feedback| !feedback.is_empty());
+        if self
+            .feedback
+            .as_ref()
+            .is_some_and(|feedback| feedback.chars().count() > MAX_FEEDBACK_LENGTH)
+        {
+            return Err(Error::BadRequest);
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::FromRow)]
+pub struct QuestRating {
+    pub user_id: UserId,
+    pub rating: i16,
+    pub feedback: Option<String>,
+    pub rated_at: DateTime<Utc>,
+}
+
+/// A rating the user gave, for their data export.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserRating {
+    pub quest_id: QuestId,
+    pub rating: i16,
+    pub feedback: Option<String>,
+    pub rated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Favourite {
+    pub quest_id: QuestId,
+    pub created_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rating(rating: i16, feedback: Option<&str>) -> RatingData {
+        RatingData {
+            user_id: UserId::new(),
+            rating,
+            feedback: feedback.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn ratings_must_be_in_range() {
+        assert!(rating(MIN_RATING, None).clean().is_ok());
+        assert!(rating(MAX_RATING, None).clean().is_ok());
+        assert!(matches!(
+            rating(MIN_RATING - 1, None).clean(),
+            Err(Error::BadRequest)
+        ));
+        assert!(matches!(
+            rating(MAX_RATING + 1, None).clean(),
+            Err(Error::BadRequest)
+        ));
+    }
+
+    #[test]
+    fn feedback_is_trimmed_and_dropped_when_empty() {
+        let cleaned = rating(3, Some("  Great\r\nquest!\r\n ")).clean().unwrap();
+        assert_eq!(cleaned.feedback.as_deref(), Some("Great\nquest!"));
+        let cleaned = rating(3, Some(" \r\n\t")).clean().unwrap();
+        assert_eq!(cleaned.feedback, None);
+    }
+
+    #[test]
+    fn feedback_length_is_counted_in_characters() {
+        let feedback = "é".repeat(MAX_FEEDBACK_LENGTH);
+        assert!(rating(4, Some(&feedback)).clean().is_ok());
+        let feedback = "é".repeat(MAX_FEEDBACK_LENGTH + 1);
+        assert!(matches!(
+            rating(4, Some(&feedback)).clean(),
+            Err(Error::BadRequest)
+        ));
+    }
+}
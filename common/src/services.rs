@@ -1,20 +1,39 @@
+use std::net::IpAddr;
+
 use rocket::async_trait;
 
 use crate::{
-    Error, PartialQuestData, Quest, QuestData, QuestEntry, QuestId, User, UserId, Username,
-    statistics::Metric,
+    ErasureStatus, Error, GeneratorTestReport, Hint, LoginResult, PartialQuestData,
+    PrewarmProgress, Quest, QuestData, QuestEntry, QuestFilter, QuestId, TotpEnrollment, User,
+    UserAccount, UserId, UserProfile, Username,
+    achievement::Achievement,
+    admin::{AdminAction, AuditEntry},
+    comment::{Comment, CommentExport, CommentId, CommentThread, NewComment, ReportedComment},
+    judge::{Language, ProgramSubmission, ProgramSubmissionDetails, SubmissionId, TestCase},
+    notification::{Notification, NotificationId},
+    package::QuestPackage,
+    progression::ProgressionExport,
+    rating::{Favourite, QuestRating, RatingData, UserRating},
+    statistics::{DailyActivity, Metric, QuestStatistics},
 };
 
 #[async_trait]
 pub trait UserService: Send + Sync {
     async fn get_user(&self, id: &UserId) -> Result<Option<User>, Error>;
-    async fn login(&self, username: &Username, password: &str) -> Result<Option<UserId>, Error>;
+    async fn get_user_profile(&self, username: &Username) -> Result<Option<UserProfile>, Error>;
+    async fn login(
+        &self,
+        username: &Username,
+        password: &str,
+        client_ip: Option<IpAddr>,
+    ) -> Result<LoginResult, Error>;
     async fn create_user(
         &self,
         username: Username,
         password: &str,
     ) -> Result<Option<UserId>, Error>;
     async fn delete_user(&self, id: &UserId) -> Result<bool, Error>;
+    async fn get_erasure_status(&self, id: &UserId) -> Result<Option<ErasureStatus>, Error>;
     async fn user_exists(&self, id: &UserId) -> Result<bool, Error>;
 
     async fn change_password(
@@ -23,12 +42,66 @@ pub trait UserService: Send + Sync {
         old_password: &str,
         new_password: &str,
     ) -> Result<bool, Error>;
+    async fn change_username(&self, id: &UserId, username: Username) -> Result<bool, Error>;
+
+    async fn is_totp_enabled(&self, id: &UserId) -> Result<bool, Error>;
+    /// Completes a login that required a TOTP code. Wrong codes are throttled per account, so
+    /// the result is either `Success`, `InvalidCredentials` or `LockedOut`.
+    async fn verify_totp(&self, id: &UserId, code: &str) -> Result<LoginResult, Error>;
+    async fn begin_totp_enrollment(&self, id: &UserId) -> Result<Option<TotpEnrollment>, Error>;
+    async fn confirm_totp_enrollment(
+        &self,
+        id: &UserId,
+        code: &str,
+    ) -> Result<Option<Box<[String]>>, Error>;
+    async fn disable_totp(&self, id: &UserId, password: &str, code: &str) -> Result<bool, Error>;
+
+    async fn get_user_account(&self, id: &UserId) -> Result<Option<UserAccount>, Error>;
+    async fn search_users(&self, query: &str) -> Result<Box<[UserAccount]>, Error>;
+    async fn ban_user(&self, id: &UserId, reason: &str) -> Result<bool, Error>;
+    async fn unban_user(&self, id: &UserId) -> Result<bool, Error>;
+    /// Replaces the user's password with a generated one and returns it.
+    async fn reset_password(&self, id: &UserId) -> Result<Option<String>, Error>;
+    async fn revoke_sessions(&self, id: &UserId) -> Result<bool, Error>;
+    /// Grants or revokes admin rights. Only done from the user-service's command line, so the
+    /// audit entry it writes has no admin.
+    async fn set_admin(&self, id: &UserId, is_admin: bool) -> Result<bool, Error>;
+
+    async fn record_admin_action(
+        &self,
+        admin_id: &UserId,
+        action: AdminAction,
+        target: &str,
+        details: Option<&str>,
+    ) -> Result<(), Error>;
+    async fn list_admin_actions(&self, limit: u32) -> Result<Box<[AuditEntry]>, Error>;
 }
 
 #[async_trait]
 pub trait QuestService: Send + Sync {
     async fn list_quests(&self) -> Result<Box<[QuestEntry]>, Error>;
+    async fn list_quests_by_author(&self, author: &UserId) -> Result<Box<[QuestEntry]>, Error> {
+        Ok(self
+            .list_quests()
+            .await?
+            .into_iter()
+            .filter(|quest| quest.author.as_ref() == Some(author))
+            .collect())
+    }
+    async fn search_quests(&self, filter: &QuestFilter) -> Result<Box<[QuestEntry]>, Error> {
+        let mut quests = self
+            .list_quests()
+            .await?
+            .into_iter()
+            .filter(|quest| filter.matches(quest))
+            .collect::<Box<[_]>>();
+        filter.sort(&mut quests);
+        Ok(quests)
+    }
     async fn get_quest(&self, id: &QuestId) -> Result<Option<Quest>, Error>;
+    /// The quest's hints in reveal order, `None` if the quest doesn't exist. Hints aren't part
+    /// of [`Quest`], users only get to see the ones they revealed.
+    async fn get_hints(&self, id: &QuestId) -> Result<Option<Box<[Hint]>>, Error>;
     async fn get_quest_author(&self, id: &QuestId) -> Result<Option<Option<UserId>>, Error> {
         self.get_quest(id)
             .await
@@ -61,28 +134,240 @@ pub trait QuestService: Send + Sync {
             .await?
             .map(|correct_answer| answer == correct_answer))
     }
+    /// The judge's test case at `index` for the user's submission, generated like the user's
+    /// input. `None` if the quest has no generator.
+    async fn get_test_case(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+        index: u32,
+    ) -> Result<Option<TestCase>, Error>;
 
     async fn create_quest(&self, quest: QuestData) -> Result<QuestId, Error>;
     async fn update_quest(&self, id: &QuestId, data: QuestData) -> Result<bool, Error>;
     async fn modify_quest(&self, id: &QuestId, data: PartialQuestData) -> Result<bool, Error>;
+    async fn delete_quest(&self, id: &QuestId) -> Result<bool, Error>;
+
+    /// Drops every cached input and answer of the quest so they get regenerated on next access.
+    async fn clear_quest_contexts(&self, id: &QuestId) -> Result<(), Error>;
+    /// Drops the quest's contexts like [`Self::clear_quest_contexts`] and generates them again
+    /// for every user in the background. Returns `false` if the quest doesn't exist.
+    async fn regenerate_quest_contexts(&self, id: &QuestId) -> Result<bool, Error> {
+        if !self.quest_exists(id).await? {
+            return Ok(false);
+        }
+        self.clear_quest_contexts(id).await?;
+        Ok(true)
+    }
+    /// The progress of the latest background generation of each quest's contexts.
+    async fn list_prewarm_progress(&self) -> Result<Box<[PrewarmProgress]>, Error> {
+        Ok(Box::new([]))
+    }
+
+    /// Packs the quest with its hints and generator, `None` if the quest doesn't exist.
+    async fn export_quest(&self, _id: &QuestId) -> Result<Option<QuestPackage>, Error> {
+        Err(Error::Unsupported)
+    }
+    /// Creates the packaged quest, or replaces it if a quest with its ID exists already. Fails
+    /// with [`Error::InvalidQuestPackage`] if the package doesn't pass validation.
+    async fn import_quest(&self, _package: QuestPackage) -> Result<QuestId, Error> {
+        Err(Error::Unsupported)
+    }
+    /// Runs the quest's generator for `users` synthetic users and reports what went wrong, `None`
+    /// if the quest doesn't exist.
+    async fn test_generator(
+        &self,
+        _id: &QuestId,
+        _users: usize,
+    ) -> Result<Option<GeneratorTestReport>, Error> {
+        Err(Error::Unsupported)
+    }
+    /// Replaces the quest's generator and drops the inputs generated by the old one. Returns
+    /// `false` if the quest doesn't exist, fails with [`Error::InvalidQuestPackage`] if the
+    /// generator isn't acceptable and with [`Error::Unsupported`] if generators can't be
+    /// sandboxed.
+    async fn upload_generator(&self, _id: &QuestId, _generator: Vec<u8>) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+
+    /// The comments in one of the quest's threads, oldest first. Replies refer to their parents
+    /// through [`Comment::parent_id`].
+    async fn list_comments(
+        &self,
+        _quest_id: &QuestId,
+        _thread: CommentThread,
+    ) -> Result<Box<[Comment]>, Error> {
+        Err(Error::Unsupported)
+    }
+    async fn get_comment(&self, _id: &CommentId) -> Result<Option<Comment>, Error> {
+        Err(Error::Unsupported)
+    }
+    /// Returns `None` if the quest doesn't exist. Fails with [`Error::BadRequest`] if the body is
+    /// empty or too long, or the parent isn't a comment on the quest.
+    async fn post_comment(
+        &self,
+        _quest_id: &QuestId,
+        _comment: NewComment,
+    ) -> Result<Option<CommentId>, Error> {
+        Err(Error::Unsupported)
+    }
+    async fn edit_comment(&self, _id: &CommentId, _body: String) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+    /// Drops the comment's body and author, its replies are kept.
+    async fn delete_comment(&self, _id: &CommentId) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+    /// Reporting a comment again replaces the user's previous report.
+    async fn report_comment(
+        &self,
+        _id: &CommentId,
+        _reporter: &UserId,
+        _reason: String,
+    ) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+    /// Hides or unhides the comment and resolves its reports.
+    async fn set_comment_hidden(&self, _id: &CommentId, _hidden: bool) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+    /// Resolves the comment's reports without hiding it.
+    async fn dismiss_comment_reports(&self, _id: &CommentId) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+    /// Comments with open reports, most recently reported first.
+    async fn list_reported_comments(&self) -> Result<Box<[ReportedComment]>, Error> {
+        Err(Error::Unsupported)
+    }
+    /// The user's comments, oldest first, and their open reports.
+    async fn export_comments(&self, _user_id: &UserId) -> Result<CommentExport, Error> {
+        Err(Error::Unsupported)
+    }
+
+    /// Rates the quest, replacing the user's previous rating. Returns `false` if the quest doesn't
+    /// exist. Fails with [`Error::BadRequest`] if the rating is out of range or the feedback is
+    /// too long.
+    async fn rate_quest(&self, _quest_id: &QuestId, _rating: RatingData) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+    async fn get_rating(
+        &self,
+        _quest_id: &QuestId,
+        _user_id: &UserId,
+    ) -> Result<Option<QuestRating>, Error> {
+        Err(Error::Unsupported)
+    }
+    /// The quest's ratings, most recent first.
+    async fn list_ratings(&self, _quest_id: &QuestId) -> Result<Box<[QuestRating]>, Error> {
+        Err(Error::Unsupported)
+    }
+    /// The user's ratings, most recent first.
+    async fn list_user_ratings(&self, _user_id: &UserId) -> Result<Box<[UserRating]>, Error> {
+        Err(Error::Unsupported)
+    }
+    /// Returns `false` if the quest doesn't exist.
+    async fn set_favourite(
+        &self,
+        _quest_id: &QuestId,
+        _user_id: &UserId,
+        _favourite: bool,
+    ) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+    async fn is_favourite(&self, _quest_id: &QuestId, _user_id: &UserId) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+    /// The user's favourite quests, most recently added first.
+    async fn list_favourites(&self, _user_id: &UserId) -> Result<Box<[Favourite]>, Error> {
+        Err(Error::Unsupported)
+    }
 }
 
 #[async_trait]
 pub trait ProgressionService: Send + Sync {
+    async fn get_completed_quests(&self, user_id: &UserId) -> Result<Box<[QuestId]>, Error>;
+    async fn export_user_data(&self, user_id: &UserId) -> Result<ProgressionExport, Error>;
     async fn has_user_completed_quest(
         &self,
         user_id: &UserId,
         quest_id: &QuestId,
     ) -> Result<bool, Error>;
+    /// Records that the user fetched the quest's input. Only the first call per quest counts.
+    async fn start_quest(&self, user_id: &UserId, quest_id: &QuestId) -> Result<(), Error>;
     async fn submit_answer(
         &self,
         user_id: &UserId,
         quest_id: &QuestId,
         answer: &str,
     ) -> Result<Option<bool>, Error>;
+    /// How many of the quest's hints the user revealed.
+    async fn get_revealed_hints(&self, user_id: &UserId, quest_id: &QuestId) -> Result<u32, Error>;
+    /// Records that the user revealed the quest's hint at `hint_index`, which costs `penalty`
+    /// points. Returns `false` if the hint was revealed before or isn't the next one in order.
+    async fn reveal_hint(
+        &self,
+        user_id: &UserId,
+        quest_id: &QuestId,
+        hint_index: u32,
+        penalty: u32,
+    ) -> Result<bool, Error>;
+
+    /// The languages programs can be submitted in.
+    async fn list_languages(&self) -> Result<Box<[Language]>, Error>;
+    /// Queues the program for judging. Returns `None` if the quest doesn't exist or doesn't take
+    /// programs, fails with [`Error::BadRequest`] for unknown languages and oversized sources and
+    /// with [`Error::TooManyRequests`] if the user has too many programs waiting or submitted too
+    /// many recently.
+    async fn submit_program(
+        &self,
+        user_id: &UserId,
+        quest_id: &QuestId,
+        language: &str,
+        source: &str,
+    ) -> Result<Option<SubmissionId>, Error>;
+    async fn get_program_submission(
+        &self,
+        id: &SubmissionId,
+    ) -> Result<Option<ProgramSubmissionDetails>, Error>;
+    /// The user's submissions for the quest, newest first.
+    async fn list_program_submissions(
+        &self,
+        user_id: &UserId,
+        quest_id: &QuestId,
+    ) -> Result<Box<[ProgramSubmission]>, Error>;
 }
 
 #[async_trait]
 pub trait StatisticsService: Send + Sync {
     async fn get_user_metrics(&self, user_id: &UserId) -> Result<Vec<Metric>, Error>;
+    /// The user's activity on each of the last `days` days, oldest first. Days without
+    /// activity are included.
+    async fn get_user_activity(
+        &self,
+        user_id: &UserId,
+        days: u32,
+    ) -> Result<Vec<DailyActivity>, Error>;
+    async fn get_quest_statistics(&self, quest_id: &QuestId) -> Result<QuestStatistics, Error>;
+    /// Every achievement, with the time the user unlocked it where they did.
+    async fn get_user_achievements(&self, user_id: &UserId) -> Result<Vec<Achievement>, Error>;
+}
+
+#[async_trait]
+pub trait NotificationService: Send + Sync {
+    /// The user's most recent notifications, newest first.
+    async fn list_notifications(
+        &self,
+        user_id: &UserId,
+        limit: u32,
+    ) -> Result<Box<[Notification]>, Error>;
+    /// All of the user's notifications, newest first, for their data export.
+    async fn export_notifications(&self, user_id: &UserId) -> Result<Box<[Notification]>, Error>;
+    async fn count_unread(&self, user_id: &UserId) -> Result<u64, Error>;
+    /// Returns the notification, or `None` if the user has no notification with that ID.
+    async fn mark_read(
+        &self,
+        user_id: &UserId,
+        id: &NotificationId,
+    ) -> Result<Option<Notification>, Error>;
+    async fn mark_all_read(&self, user_id: &UserId) -> Result<(), Error>;
 }
@@ -1,20 +1,158 @@
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::FromRow)]
-pub struct Metric {
-    key: String,
-    pub value: String,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "key", content = "value", rename_all = "snake_case")]
+pub enum Metric {
+    AnswersSubmitted(u64),
+    QuestsCompleted(u64),
+    QuestsCreated(u64),
+    QuestsModified(u64),
+    QuestsDeleted(u64),
+    HintsRevealed(u64),
+    /// Points deducted for revealed hints.
+    HintPenalty(u64),
+    /// Average seconds from the first input fetch to solving a quest.
+    AverageSolveTime(u64),
+    /// Share of attempted quests that were solved with the first answer, from 0 to 1.
+    FirstTrySuccessRate(f64),
+    /// Average number of wrong answers per attempted quest.
+    WrongAnswersPerQuest(f64),
+    /// Most consecutive days with at least one submitted answer.
+    LongestDailyStreak(u64),
+    /// Elo-like rating from the user's answers, rated against the difficulty of the quests.
+    SkillRating(i32),
 }
 
 impl Metric {
+    /// Creates the counter stored under `key`, or `None` if there is no such counter.
+    pub fn counter(key: &str, value: u64) -> Option<Self> {
+        Some(match key {
+            "answers_submitted" => Self::AnswersSubmitted(value),
+            "quests_completed" => Self::QuestsCompleted(value),
+            "quests_created" => Self::QuestsCreated(value),
+            "quests_modified" => Self::QuestsModified(value),
+            "quests_deleted" => Self::QuestsDeleted(value),
+            "hints_revealed" => Self::HintsRevealed(value),
+            "hint_penalty" => Self::HintPenalty(value),
+            _ => return None,
+        })
+    }
+
     pub fn get_display_name(&self) -> &'static str {
-        match self.key.as_str() {
-            "answers_submitted" => "Total answers submitted",
-            "quests_completed" => "Total quests completed",
-            "quests_created" => "Total quests created",
-            "quests_modified" => "Total quests modified",
-            "quests_deleted" => "Total quests deleted",
-            _ => "unknown_metric",
+        match self {
+            Self::AnswersSubmitted(_) => "Total answers submitted",
+            Self::QuestsCompleted(_) => "Total quests completed",
+            Self::QuestsCreated(_) => "Total quests created",
+            Self::QuestsModified(_) => "Total quests modified",
+            Self::QuestsDeleted(_) => "Total quests deleted",
+            Self::HintsRevealed(_) => "Hints revealed",
+            Self::HintPenalty(_) => "Points lost to hints",
+            Self::AverageSolveTime(_) => "Average time to solve",
+            Self::FirstTrySuccessRate(_) => "Solved on the first try",
+            Self::WrongAnswersPerQuest(_) => "Wrong answers per quest",
+            Self::LongestDailyStreak(_) => "Longest daily streak",
+            Self::SkillRating(_) => "Skill rating",
+        }
+    }
+
+    pub fn get_display_value(&self) -> String {
+        match self {
+            Self::AnswersSubmitted(value)
+            | Self::QuestsCompleted(value)
+            | Self::QuestsCreated(value)
+            | Self::QuestsModified(value)
+            | Self::QuestsDeleted(value)
+            | Self::HintsRevealed(value)
+            | Self::HintPenalty(value) => value.to_string(),
+            Self::AverageSolveTime(seconds) => format_duration(*seconds),
+            Self::FirstTrySuccessRate(rate) => format!("{:.0}%", rate * 100.0),
+            Self::WrongAnswersPerQuest(value) => format!("{:.1}", value),
+            Self::LongestDailyStreak(1) => "1 day".to_owned(),
+            Self::LongestDailyStreak(days) => format!("{} days", days),
+            Self::SkillRating(rating) => rating.to_string(),
         }
     }
 }
+
+/// Formats `seconds` using its two most significant units, e.g. `2h 5m`.
+pub fn format_duration(seconds: u64) -> String {
+    let units = [
+        (seconds / 86400, "d"),
+        (seconds / 3600 % 24, "h"),
+        (seconds / 60 % 60, "m"),
+        (seconds % 60, "s"),
+    ];
+    let first = units
+        .iter()
+        .position(|(value, _)| *value > 0)
+        .unwrap_or(units.len() - 1);
+    units[first..]
+        .iter()
+        .take(2)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DailyActivity {
+    pub day: NaiveDate,
+    pub answers_submitted: i64,
+    pub quests_completed: i64,
+}
+
+/// Users with this many wrong answers or more share the last bucket of
+/// [`QuestStatistics::wrong_answer_distribution`].
+pub const WRONG_ANSWER_BUCKETS: usize = 5;
+
+/// Aggregates over everyone who attempted a quest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuestStatistics {
+    /// Users who fetched their input.
+    pub players: u64,
+    pub solvers: u64,
+    /// Average number of answers solvers submitted, including the correct one.
+    pub average_attempts_to_solve: Option<f64>,
+    /// Median seconds from the first input fetch to solving the quest.
+    pub median_solve_time: Option<u64>,
+    /// How many users who submitted an answer gave `i` wrong answers, for `i` from 0 up to
+    /// [`WRONG_ANSWER_BUCKETS`].
+    pub wrong_answer_distribution: Vec<u64>,
+    /// Empirical difficulty rating, `None` while too few users attempted the quest.
+    #[serde(default)]
+    pub difficulty: Option<i32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_are_created_from_their_keys() {
+        assert_eq!(
+            Metric::counter("answers_submitted", 3),
+            Some(Metric::AnswersSubmitted(3))
+        );
+        assert_eq!(
+            Metric::counter("quests_completed", 0),
+            Some(Metric::QuestsCompleted(0))
+        );
+        assert_eq!(
+            Metric::counter("hint_penalty", 7),
+            Some(Metric::HintPenalty(7))
+        );
+        assert_eq!(Metric::counter("average_solve_time", 60), None);
+        assert_eq!(Metric::counter("unknown", 1), None);
+    }
+
+    #[test]
+    fn durations_use_the_two_most_significant_units() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(59), "59s");
+        assert_eq!(format_duration(60), "1m 0s");
+        assert_eq!(format_duration(7500), "2h 5m");
+        assert_eq!(format_duration(90061), "1d 1h");
+        assert_eq!(format_duration(86400 * 3 + 59), "3d 0h");
+    }
+}
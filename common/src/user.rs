@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -15,6 +16,31 @@ impl User {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserProfile {
+    pub id: UserId,
+    pub username: Username,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserAccount {
+    pub id: UserId,
+    pub username: Username,
+    pub created_at: DateTime<Utc>,
+    pub is_admin: bool,
+    pub banned_at: Option<DateTime<Utc>>,
+    pub ban_reason: Option<String>,
+    /// Sessions issued before this point in time are no longer accepted.
+    pub sessions_revoked_at: Option<DateTime<Utc>>,
+}
+
+impl UserAccount {
+    pub fn is_banned(&self) -> bool {
+        self.banned_at.is_some()
+    }
+}
+
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::FromRow, sqlx::Type,
 )]
@@ -38,6 +64,15 @@ impl std::fmt::Display for UserId {
     }
 }
 
+#[rocket::async_trait]
+impl<'v> rocket::form::FromFormField<'v> for UserId {
+    fn from_value(field: rocket::form::ValueField<'v>) -> rocket::form::Result<'v, Self> {
+        Uuid::parse_str(field.value)
+            .map(UserId)
+            .map_err(|_| rocket::form::Error::validation("invalid user ID").into())
+    }
+}
+
 impl<'r> rocket::request::FromParam<'r> for UserId {
     type Error = &'r str;
 
@@ -125,3 +160,45 @@ impl std::fmt::Display for UsernameRef<'_> {
         self.0.fmt(f)
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LoginResult {
+    Success(UserId),
+    TotpRequired(UserId),
+    InvalidCredentials,
+    LockedOut(Lockout),
+    Banned,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockout {
+    /// Seconds until the next login attempt will be accepted.
+    pub retry_after: u64,
+    /// The account behind the attempted username, if it exists.
+    pub user_id: Option<UserId>,
+    /// Whether this attempt started the lockout rather than being rejected by an existing one.
+    pub started: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// Progress of a deleted user's data erasure across the services that hold data about them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErasureStatus {
+    pub user_id: UserId,
+    pub requested_at: DateTime<Utc>,
+    /// Services that have confirmed purging or anonymising the user's data.
+    pub confirmed: Vec<String>,
+    /// Services that haven't confirmed yet.
+    pub pending: Vec<String>,
+}
+
+impl ErasureStatus {
+    pub fn is_complete(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
@@ -17,6 +17,10 @@ psql -v ON_ERROR_STOP=1 $POSTGRES_DB $POSTGRES_USER <<-SQL
     CREATE ROLE ${DB_USERNAME_STATISTICS_SERVICE} LOGIN;
     CREATE SCHEMA statistics_schema AUTHORIZATION ${DB_USERNAME_STATISTICS_SERVICE};
     ALTER ROLE ${DB_USERNAME_STATISTICS_SERVICE} SET search_path = statistics_schema;
+
+    CREATE ROLE ${DB_USERNAME_NOTIFICATION_SERVICE} LOGIN;
+    CREATE SCHEMA notification_schema AUTHORIZATION ${DB_USERNAME_NOTIFICATION_SERVICE};
+    ALTER ROLE ${DB_USERNAME_NOTIFICATION_SERVICE} SET search_path = notification_schema;
 SQL
 
 psql -v ON_ERROR_STOP=1 $POSTGRES_DB $POSTGRES_USER <<-SQL
@@ -24,4 +28,5 @@ psql -v ON_ERROR_STOP=1 $POSTGRES_DB $POSTGRES_USER <<-SQL
     ALTER ROLE ${DB_USERNAME_QUEST_SERVICE} PASSWORD '${DB_PASSWORD_QUEST_SERVICE}';
     ALTER ROLE ${DB_USERNAME_PROGRESSION_SERVICE} PASSWORD '${DB_PASSWORD_PROGRESSION_SERVICE}';
     ALTER ROLE ${DB_USERNAME_STATISTICS_SERVICE} PASSWORD '${DB_PASSWORD_STATISTICS_SERVICE}';
+    ALTER ROLE ${DB_USERNAME_NOTIFICATION_SERVICE} PASSWORD '${DB_PASSWORD_NOTIFICATION_SERVICE}';
 SQL
@@ -1,40 +1,105 @@
-use std::sync::Arc;
+use std::{
+    io::{self, Write as _},
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use codequest_common::{Error, User, UserId, Username, services::UserService};
+use codequest_common::{
+    Error, LoginResult, UserAccount, UserId, UserProfile, Username,
+    services::{
+        NotificationService, ProgressionService, QuestService, StatisticsService, UserService,
+    },
+};
+use qrcode::{QrCode, render::svg};
 use rocket::{
-    FromForm, Request, State, async_trait,
+    FromForm, Request, Responder, State, async_trait,
     form::Form,
-    http::{self, Cookie, CookieJar},
+    http::{self, Cookie, CookieJar, Header},
     request::{FromRequest, Outcome},
     response::Redirect,
-    serde::json::Json,
+    serde::json::{Json, serde_json},
 };
+use rocket_dyn_templates::{Template, context};
 use serde::Serialize;
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+use crate::pages::PageContext;
+
+const SESSION_COOKIE: &str = "user_id";
+const PENDING_TOTP_COOKIE: &str = "pending_totp";
+/// Remembers the deleted account so its erasure can still be followed after logging out.
+const ERASURE_COOKIE: &str = "erasure_request";
+const ERASURE_COOKIE_LIFETIME: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+const PENDING_TOTP_LIFETIME: Duration = Duration::from_secs(5 * 60);
+/// Wrong codes accepted for a pending login before the password has to be entered again.
+const PENDING_TOTP_ATTEMPTS: u32 = 3;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct AuthUser {
     pub(crate) id: UserId,
     pub(crate) username: Username,
+    pub(crate) is_admin: bool,
 }
 
 impl AuthUser {
-    pub fn from_user(user: User) -> Self {
+    pub fn from_account(account: UserAccount) -> Self {
         Self {
-            id: user.id,
-            username: user.username,
+            id: account.id,
+            username: account.username,
+            is_admin: account.is_admin,
         }
     }
 }
 
+/// The value of the session cookie. Sessions remember when they were issued so they can be
+/// revoked by banning the user or forcing a logout.
+struct Session {
+    user_id: UserId,
+    issued_at: i64,
+}
+
+impl Session {
+    fn new(user_id: UserId) -> Self {
+        Self {
+            user_id,
+            issued_at: unix_time_millis(),
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self, Error> {
+        // Cookies issued before sessions carried a timestamp only contain the user id.
+        let (user_id, issued_at) = value.split_once(':').unwrap_or((value, "0"));
+        Ok(Self {
+            user_id: UserId::try_parse(user_id)?,
+            issued_at: issued_at.parse().map_err(|_| Error::Unauthorized)?,
+        })
+    }
+
+    fn is_valid_for(&self, account: &UserAccount) -> bool {
+        !account.is_banned()
+            && account
+                .sessions_revoked_at
+                .is_none_or(|revoked_at| self.issued_at >= revoked_at.timestamp_millis())
+    }
+
+    fn to_cookie(&self) -> Cookie<'static> {
+        Cookie::new(
+            SESSION_COOKIE,
+            format!("{}:{}", self.user_id, self.issued_at),
+        )
+    }
+}
+
 #[async_trait]
 impl<'r> FromRequest<'r> for AuthUser {
     type Error = Error;
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
         let jar = request.cookies();
 
-        if let Some(cookie) = jar.get_private("user_id") {
-            let user_id = match UserId::try_parse(cookie.value()) {
-                Ok(user_id) => user_id,
+        if let Some(cookie) = jar.get_private(SESSION_COOKIE) {
+            let session = match Session::parse(cookie.value()) {
+                Ok(session) => session,
                 Err(e) => return Outcome::Error((http::Status::Unauthorized, e)),
             };
 
@@ -42,10 +107,12 @@ impl<'r> FromRequest<'r> for AuthUser {
                 .guard::<&State<Arc<dyn UserService>>>()
                 .await
                 .expect("UserService not registered with rocket");
-            match user_service.get_user(&user_id).await {
-                Ok(Some(user)) => Outcome::Success(AuthUser::from_user(user)),
-                Ok(None) => {
-                    jar.remove_private("user_id");
+            match user_service.get_user_account(&session.user_id).await {
+                Ok(Some(account)) if session.is_valid_for(&account) => {
+                    Outcome::Success(AuthUser::from_account(account))
+                }
+                Ok(_) => {
+                    jar.remove_private(SESSION_COOKIE);
                     Outcome::Error((http::Status::Unauthorized, Error::Unauthorized))
                 }
                 Err(e) => Outcome::Error((http::Status::InternalServerError, e)),
@@ -58,7 +125,7 @@ impl<'r> FromRequest<'r> for AuthUser {
 
 #[rocket::post("/logout")]
 pub async fn logout(jar: &CookieJar<'_>) -> Redirect {
-    jar.remove_private("user_id");
+    jar.remove_private(SESSION_COOKIE);
     Redirect::to("/")
 }
 
@@ -106,7 +173,7 @@ pub async fn signup(
 
     Ok(
         if let Some(user_id) = user_service.create_user(username, password).await? {
-            jar.add_private(Cookie::new("user_id", user_id.to_string()));
+            jar.add_private(Session::new(user_id).to_cookie());
             (
                 http::Status::Ok,
                 Json(SignupResponse::success("/".to_owned())),
@@ -129,33 +196,118 @@ pub struct LoginForm<'a> {
 #[derive(Serialize)]
 pub struct LoginResponse {
     success: bool,
+    totp_required: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     redirect: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retry_after: Option<u64>,
 }
 
 impl LoginResponse {
     fn success(redirect: String) -> Self {
         Self {
             success: true,
+            totp_required: false,
             redirect: Some(redirect),
             error: None,
+            retry_after: None,
+        }
+    }
+
+    fn totp_required() -> Self {
+        Self {
+            success: false,
+            totp_required: true,
+            redirect: None,
+            error: None,
+            retry_after: None,
         }
     }
 
     fn error(error: impl Into<String>) -> Self {
         Self {
             success: false,
+            totp_required: false,
             redirect: None,
             error: Some(error.into()),
+            retry_after: None,
         }
     }
+
+    fn locked_out(retry_after: u64) -> Self {
+        Self {
+            success: false,
+            totp_required: false,
+            redirect: None,
+            error: Some(format!(
+                "Too many failed login attempts. Try again in {} seconds.",
+                retry_after
+            )),
+            retry_after: Some(retry_after),
+        }
+    }
+}
+
+/// The state kept between the password step and the TOTP step of a login.
+struct PendingTotpLogin {
+    user_id: UserId,
+    expires_at: u64,
+    /// Wrong codes entered so far.
+    failures: u32,
+}
+
+impl PendingTotpLogin {
+    fn new(user_id: UserId) -> Self {
+        Self {
+            user_id,
+            expires_at: unix_time() + PENDING_TOTP_LIFETIME.as_secs(),
+            failures: 0,
+        }
+    }
+
+    fn from_cookie(jar: &CookieJar<'_>) -> Option<Self> {
+        let cookie = jar.get_private(PENDING_TOTP_COOKIE)?;
+        let mut parts = cookie.value().split(':');
+        let pending = Self {
+            user_id: UserId::try_parse(parts.next()?).ok()?,
+            expires_at: parts.next()?.parse().ok()?,
+            failures: parts.next()?.parse().ok()?,
+        };
+        if pending.expires_at < unix_time() {
+            jar.remove_private(PENDING_TOTP_COOKIE);
+            return None;
+        }
+        Some(pending)
+    }
+
+    fn to_cookie(&self) -> Cookie<'static> {
+        Cookie::new(
+            PENDING_TOTP_COOKIE,
+            format!("{}:{}:{}", self.user_id, self.expires_at, self.failures),
+        )
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is before the unix epoch")
+        .as_secs()
+}
+
+fn unix_time_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is before the unix epoch")
+        .as_millis() as i64
 }
 
 #[rocket::post("/login", data = "<form>")]
 pub async fn login(
     form: Form<LoginForm<'_>>,
+    client_ip: Option<IpAddr>,
     jar: &CookieJar<'_>,
     user_service: &State<Arc<dyn UserService>>,
 ) -> Result<(http::Status, Json<LoginResponse>), Error> {
@@ -163,19 +315,98 @@ pub async fn login(
     let username = Username::new(username)?;
 
     Ok(
-        if let Some(user_id) = user_service.login(&username, password).await? {
-            jar.add_private(Cookie::new("user_id", user_id.to_string()));
-            (
-                http::Status::Ok,
-                Json(LoginResponse::success("/".to_owned())),
-            )
-        } else {
-            (
+        match user_service.login(&username, password, client_ip).await? {
+            LoginResult::Success(user_id) => {
+                jar.add_private(Session::new(user_id).to_cookie());
+                (
+                    http::Status::Ok,
+                    Json(LoginResponse::success("/".to_owned())),
+                )
+            }
+            LoginResult::TotpRequired(user_id) => {
+                jar.add_private(PendingTotpLogin::new(user_id).to_cookie());
+                (http::Status::Ok, Json(LoginResponse::totp_required()))
+            }
+            LoginResult::InvalidCredentials => (
                 http::Status::Unauthorized,
                 Json(LoginResponse::error(
                     "Invalid username or password".to_owned(),
                 )),
-            )
+            ),
+            LoginResult::LockedOut(lockout) => (
+                http::Status::TooManyRequests,
+                Json(LoginResponse::locked_out(lockout.retry_after)),
+            ),
+            LoginResult::Banned => (
+                http::Status::Forbidden,
+                Json(LoginResponse::error("This account has been banned")),
+            ),
+        },
+    )
+}
+
+#[derive(FromForm)]
+pub struct TotpLoginForm<'a> {
+    code: &'a str,
+}
+
+#[rocket::post("/login/totp", data = "<form>")]
+pub async fn login_totp(
+    form: Form<TotpLoginForm<'_>>,
+    jar: &CookieJar<'_>,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<(http::Status, Json<LoginResponse>), Error> {
+    let Some(pending) = PendingTotpLogin::from_cookie(jar) else {
+        return Ok((
+            http::Status::Unauthorized,
+            Json(LoginResponse::error(
+                "Your login has expired, please log in again.",
+            )),
+        ));
+    };
+
+    Ok(
+        match user_service
+            .verify_totp(&pending.user_id, form.code)
+            .await?
+        {
+            LoginResult::Success(user_id) => {
+                jar.remove_private(PENDING_TOTP_COOKIE);
+                jar.add_private(Session::new(user_id).to_cookie());
+                (
+                    http::Status::Ok,
+                    Json(LoginResponse::success("/".to_owned())),
+                )
+            }
+            LoginResult::LockedOut(lockout) => {
+                jar.remove_private(PENDING_TOTP_COOKIE);
+                (
+                    http::Status::TooManyRequests,
+                    Json(LoginResponse::locked_out(lockout.retry_after)),
+                )
+            }
+            _ if pending.failures + 1 >= PENDING_TOTP_ATTEMPTS => {
+                jar.remove_private(PENDING_TOTP_COOKIE);
+                (
+                    http::Status::Unauthorized,
+                    Json(LoginResponse::error(
+                        "Too many invalid authentication codes, please log in again.",
+                    )),
+                )
+            }
+            _ => {
+                jar.add_private(
+                    PendingTotpLogin {
+                        failures: pending.failures + 1,
+                        ..pending
+                    }
+                    .to_cookie(),
+                );
+                (
+                    http::Status::Unauthorized,
+                    Json(LoginResponse::error("Invalid authentication code")),
+                )
+            }
         },
     )
 }
@@ -232,14 +463,353 @@ pub async fn change_password(
     )
 }
 
+#[derive(FromForm)]
+pub struct ChangeUsernameForm<'a> {
+    #[field(name = "newUsername")]
+    new_username: &'a str,
+}
+
+#[derive(Serialize)]
+pub struct ChangeUsernameResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl ChangeUsernameResponse {
+    pub fn success() -> Self {
+        Self {
+            success: true,
+            error: None,
+        }
+    }
+
+    pub fn error(error: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            error: Some(error.into()),
+        }
+    }
+}
+
+#[rocket::post("/change-username", data = "<form>")]
+pub async fn change_username(
+    form: Form<ChangeUsernameForm<'_>>,
+    user: AuthUser,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<(http::Status, Json<ChangeUsernameResponse>), Error> {
+    let Ok(username) = Username::new(form.new_username) else {
+        return Ok((
+            http::Status::BadRequest,
+            Json(ChangeUsernameResponse::error("Invalid username")),
+        ));
+    };
+
+    Ok(if user_service.change_username(&user.id, username).await? {
+        (http::Status::Ok, Json(ChangeUsernameResponse::success()))
+    } else {
+        (
+            http::Status::Conflict,
+            Json(ChangeUsernameResponse::error("Username already taken")),
+        )
+    })
+}
+
 #[rocket::post("/delete")]
 pub async fn delete(
     user: AuthUser,
     jar: &CookieJar<'_>,
     user_service: &State<Arc<dyn UserService>>,
 ) -> Result<Redirect, Error> {
-    if user_service.delete_user(&user.id).await? {
-        jar.remove_private("user_id");
+    if !user_service.delete_user(&user.id).await? {
+        return Ok(Redirect::to("/"));
     }
-    Ok(Redirect::to("/"))
+    jar.remove_private(SESSION_COOKIE);
+    jar.add_private(
+        Cookie::build((ERASURE_COOKIE, user.id.to_string()))
+            .max_age(rocket::time::Duration::seconds(
+                ERASURE_COOKIE_LIFETIME.as_secs() as i64,
+            ))
+            .build(),
+    );
+    Ok(Redirect::to("/account/erasure"))
+}
+
+/// Shows which services have erased the data of the account deleted from this browser.
+#[rocket::get("/erasure")]
+pub async fn erasure_status(
+    user: Option<AuthUser>,
+    jar: &CookieJar<'_>,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<Result<Template, http::Status>, Error> {
+    let Some(user_id) = jar
+        .get_private(ERASURE_COOKIE)
+        .and_then(|cookie| UserId::try_parse(cookie.value()).ok())
+    else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    let Some(erasure_status) = user_service.get_erasure_status(&user_id).await? else {
+        jar.remove_private(ERASURE_COOKIE);
+        return Ok(Err(http::Status::NotFound));
+    };
+    Ok(Ok(Template::render(
+        "erasure",
+        PageContext::new(
+            &user,
+            context! {
+                requested_at: erasure_status.requested_at.format("%Y-%m-%d %H:%M").to_string(),
+                complete: erasure_status.is_complete(),
+                confirmed: erasure_status.confirmed,
+                pending: erasure_status.pending,
+            },
+        ),
+    )))
+}
+
+#[derive(Serialize)]
+struct ProfileExport<'a> {
+    #[serde(flatten)]
+    profile: &'a UserProfile,
+    totp_enabled: bool,
+}
+
+#[derive(Responder)]
+#[response(content_type = "application/zip")]
+pub struct ExportArchive {
+    archive: Vec<u8>,
+    content_disposition: Header<'static>,
+}
+
+fn build_archive(files: &[(&str, Vec<u8>)]) -> Result<Vec<u8>, Error> {
+    let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+    for (name, contents) in files {
+        writer
+            .start_file(*name, SimpleFileOptions::default())
+            .map_err(io::Error::from)?;
+        writer.write_all(contents)?;
+    }
+    Ok(writer.finish().map_err(io::Error::from)?.into_inner())
+}
+
+/// Gathers everything the services store about the user into a single zip archive.
+#[rocket::get("/export")]
+pub async fn export(
+    user: AuthUser,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+    statistics_service: &State<Arc<dyn StatisticsService>>,
+    notification_service: &State<Arc<dyn NotificationService>>,
+) -> Result<ExportArchive, Error> {
+    let profile = user_service
+        .get_user_profile(&user.username)
+        .await?
+        .ok_or(Error::IncoherentState)?;
+    let totp_enabled = user_service.is_totp_enabled(&user.id).await?;
+
+    let mut authored_quests = Vec::new();
+    for entry in quest_service.list_quests_by_author(&user.id).await? {
+        if let Some(quest) = quest_service.get_quest(&entry.id).await? {
+            authored_quests.push(quest);
+        }
+    }
+
+    let archive = build_archive(&[
+        (
+            "profile.json",
+            serde_json::to_vec_pretty(&ProfileExport {
+                profile: &profile,
+                totp_enabled,
+            })?,
+        ),
+        (
+            "progression.json",
+            serde_json::to_vec_pretty(&progression_service.export_user_data(&user.id).await?)?,
+        ),
+        (
+            "statistics.json",
+            serde_json::to_vec_pretty(&statistics_service.get_user_metrics(&user.id).await?)?,
+        ),
+        (
+            "achievements.json",
+            serde_json::to_vec_pretty(&statistics_service.get_user_achievements(&user.id).await?)?,
+        ),
+        ("quests.json", serde_json::to_vec_pretty(&authored_quests)?),
+        (
+            "comments.json",
+            serde_json::to_vec_pretty(&quest_service.export_comments(&user.id).await?)?,
+        ),
+        (
+            "ratings.json",
+            serde_json::to_vec_pretty(&quest_service.list_user_ratings(&user.id).await?)?,
+        ),
+        (
+            "favourites.json",
+            serde_json::to_vec_pretty(&quest_service.list_favourites(&user.id).await?)?,
+        ),
+        (
+            "notifications.json",
+            serde_json::to_vec_pretty(&notification_service.export_notifications(&user.id).await?)?,
+        ),
+    ])?;
+
+    Ok(ExportArchive {
+        archive,
+        content_disposition: Header::new(
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"codequest-{}.zip\"",
+                profile.username
+            ),
+        ),
+    })
+}
+
+#[derive(Serialize)]
+pub struct TotpEnrollmentResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    otpauth_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    qr_svg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl TotpEnrollmentResponse {
+    fn success(secret: String, otpauth_uri: String, qr_svg: String) -> Self {
+        Self {
+            success: true,
+            secret: Some(secret),
+            otpauth_uri: Some(otpauth_uri),
+            qr_svg: Some(qr_svg),
+            error: None,
+        }
+    }
+
+    fn error(error: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            secret: None,
+            otpauth_uri: None,
+            qr_svg: None,
+            error: Some(error.into()),
+        }
+    }
+}
+
+#[rocket::post("/two-factor/enroll")]
+pub async fn begin_totp_enrollment(
+    user: AuthUser,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<(http::Status, Json<TotpEnrollmentResponse>), Error> {
+    let Some(enrollment) = user_service.begin_totp_enrollment(&user.id).await? else {
+        return Ok((
+            http::Status::Conflict,
+            Json(TotpEnrollmentResponse::error(
+                "Two-factor authentication is already enabled",
+            )),
+        ));
+    };
+
+    let qr_svg = QrCode::new(enrollment.otpauth_uri.as_bytes())
+        .map_err(|_| Error::IncoherentState)?
+        .render::<svg::Color>()
+        .min_dimensions(200, 200)
+        .build();
+
+    Ok((
+        http::Status::Ok,
+        Json(TotpEnrollmentResponse::success(
+            enrollment.secret,
+            enrollment.otpauth_uri,
+            qr_svg,
+        )),
+    ))
+}
+
+#[derive(FromForm)]
+pub struct ConfirmTotpForm<'a> {
+    code: &'a str,
+}
+
+#[derive(Serialize)]
+pub struct ConfirmTotpResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recovery_codes: Option<Box<[String]>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl ConfirmTotpResponse {
+    fn success(recovery_codes: Box<[String]>) -> Self {
+        Self {
+            success: true,
+            recovery_codes: Some(recovery_codes),
+            error: None,
+        }
+    }
+
+    fn error(error: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            recovery_codes: None,
+            error: Some(error.into()),
+        }
+    }
+}
+
+#[rocket::post("/two-factor/confirm", data = "<form>")]
+pub async fn confirm_totp_enrollment(
+    form: Form<ConfirmTotpForm<'_>>,
+    user: AuthUser,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<(http::Status, Json<ConfirmTotpResponse>), Error> {
+    Ok(
+        match user_service
+            .confirm_totp_enrollment(&user.id, form.code)
+            .await?
+        {
+            Some(recovery_codes) => (
+                http::Status::Ok,
+                Json(ConfirmTotpResponse::success(recovery_codes)),
+            ),
+            None => (
+                http::Status::Unauthorized,
+                Json(ConfirmTotpResponse::error("Invalid authentication code")),
+            ),
+        },
+    )
+}
+
+#[derive(FromForm)]
+pub struct DisableTotpForm<'a> {
+    password: &'a str,
+    code: &'a str,
+}
+
+#[rocket::post("/two-factor/disable", data = "<form>")]
+pub async fn disable_totp(
+    form: Form<DisableTotpForm<'_>>,
+    user: AuthUser,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<(http::Status, Json<ChangePasswordResponse>), Error> {
+    Ok(
+        if user_service
+            .disable_totp(&user.id, form.password, form.code)
+            .await?
+        {
+            (http::Status::Ok, Json(ChangePasswordResponse::success()))
+        } else {
+            (
+                http::Status::Unauthorized,
+                Json(ChangePasswordResponse::error(
+                    "Wrong password or authentication code",
+                )),
+            )
+        },
+    )
 }
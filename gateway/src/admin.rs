@@ -0,0 +1,619 @@
+use std::{collections::HashMap, sync::Arc};
+
+use codequest_common::{
+    Error, PartialQuestData, QuestFilter, QuestId, UserId,
+    admin::AdminAction,
+    comment::CommentId,
+    package::QuestPackage,
+    services::{ProgressionService, QuestService, UserService},
+};
+use rocket::{
+    FromForm, Request, Responder, State, async_trait,
+    form::Form,
+    http::{self, Header},
+    request::{FromRequest, Outcome},
+    serde::json::Json,
+};
+use rocket_dyn_templates::{Template, context};
+use serde::Serialize;
+
+use crate::{
+    account::AuthUser,
+    comments::{render_markdown, username},
+    pages::PageContext,
+};
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+const AUDIT_LOG_LIMIT: u32 = 200;
+
+/// A logged in user with admin rights. Everyone else gets a 403.
+pub(crate) struct AdminUser(AuthUser);
+
+#[async_trait]
+impl<'r> FromRequest<'r> for AdminUser {
+    type Error = Error;
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match request.guard::<AuthUser>().await {
+            Outcome::Success(user) if user.is_admin => Outcome::Success(AdminUser(user)),
+            Outcome::Success(_) => Outcome::Error((http::Status::Forbidden, Error::Unauthorized)),
+            Outcome::Error(e) => Outcome::Error(e),
+            Outcome::Forward(status) => Outcome::Forward(status),
+        }
+    }
+}
+
+impl AdminUser {
+    /// Adds the action to the audit log. Called before performing the action, so that no
+    /// action goes unrecorded if the audit log can't be written.
+    async fn record(
+        &self,
+        user_service: &State<Arc<dyn UserService>>,
+        action: AdminAction,
+        target: impl ToString,
+        details: Option<&str>,
+    ) -> Result<(), Error> {
+        user_service
+            .record_admin_action(&self.0.id, action, &target.to_string(), details)
+            .await
+    }
+}
+
+#[derive(Serialize)]
+pub struct AdminActionResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl AdminActionResponse {
+    pub fn success() -> Self {
+        Self {
+            success: true,
+            message: None,
+            error: None,
+        }
+    }
+
+    pub fn message(message: impl Into<String>) -> Self {
+        Self {
+            success: true,
+            message: Some(message.into()),
+            error: None,
+        }
+    }
+
+    pub fn error(error: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            message: None,
+            error: Some(error.into()),
+        }
+    }
+}
+
+type ActionResult = Result<(http::Status, Json<AdminActionResponse>), Error>;
+
+fn user_not_found() -> (http::Status, Json<AdminActionResponse>) {
+    (
+        http::Status::NotFound,
+        Json(AdminActionResponse::error("User doesn't exist.")),
+    )
+}
+
+fn quest_not_found() -> (http::Status, Json<AdminActionResponse>) {
+    (
+        http::Status::NotFound,
+        Json(AdminActionResponse::error("Quest doesn't exist.")),
+    )
+}
+
+#[rocket::get("/users?<query>")]
+pub async fn users(
+    query: Option<&str>,
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<Template, Error> {
+    let query = query.unwrap_or_default().trim();
+    let users = user_service
+        .search_users(query)
+        .await?
+        .into_iter()
+        .map(|account| {
+            context! {
+                id: account.id,
+                banned: account.is_banned(),
+                username: account.username,
+                joined: account.created_at.format(DATE_FORMAT).to_string(),
+                is_admin: account.is_admin,
+            }
+        })
+        .collect::<Vec<_>>();
+    Ok(Template::render(
+        "admin-users",
+        PageContext::new(&Some(admin.0), context! { query, users }),
+    ))
+}
+
+#[rocket::get("/users/<user_id>")]
+pub async fn user(
+    user_id: UserId,
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<Result<Template, http::Status>, Error> {
+    let Some(account) = user_service.get_user_account(&user_id).await? else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    let progression = progression_service.export_user_data(&user_id).await?;
+
+    let quest_names = quest_service
+        .search_quests(&QuestFilter::default())
+        .await?
+        .into_iter()
+        .map(|quest| (quest.id, quest.name))
+        .collect::<HashMap<_, _>>();
+    let quest_name = |id: &QuestId| {
+        quest_names
+            .get(id)
+            .cloned()
+            .unwrap_or_else(|| id.to_string())
+    };
+
+    let completed_quests = progression
+        .completed_quests
+        .iter()
+        .map(|completed| {
+            context! {
+                id: completed.quest_id,
+                name: quest_name(&completed.quest_id),
+                completed_at: completed.completed_at.format(DATE_FORMAT).to_string(),
+            }
+        })
+        .collect::<Vec<_>>();
+    let submissions = progression
+        .submissions
+        .iter()
+        .rev()
+        .map(|submission| {
+            context! {
+                quest_id: submission.quest_id,
+                quest_name: quest_name(&submission.quest_id),
+                answer: &submission.answer,
+                correct: submission.correct,
+                submitted_at: submission.submitted_at.format(DATE_FORMAT).to_string(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Ok(Template::render(
+        "admin-user",
+        PageContext::new(
+            &Some(admin.0),
+            context! {
+                account: context! {
+                    id: account.id,
+                    username: &account.username,
+                    joined: account.created_at.format(DATE_FORMAT).to_string(),
+                    is_admin: account.is_admin,
+                    banned: account.is_banned(),
+                    banned_at: account
+                        .banned_at
+                        .map(|banned_at| banned_at.format(DATE_FORMAT).to_string()),
+                    ban_reason: &account.ban_reason,
+                    sessions_revoked_at: account
+                        .sessions_revoked_at
+                        .map(|revoked_at| revoked_at.format(DATE_FORMAT).to_string()),
+                },
+                completed_quests,
+                submissions,
+            },
+        ),
+    )))
+}
+
+#[derive(FromForm)]
+pub struct BanForm<'a> {
+    reason: &'a str,
+}
+
+#[rocket::post("/users/<user_id>/ban", data = "<form>")]
+pub async fn ban_user(
+    user_id: UserId,
+    form: Form<BanForm<'_>>,
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+) -> ActionResult {
+    if user_id == admin.0.id {
+        return Ok((
+            http::Status::BadRequest,
+            Json(AdminActionResponse::error("You can't ban yourself.")),
+        ));
+    }
+    let reason = form.reason.trim();
+    admin
+        .record(user_service, AdminAction::BanUser, user_id, Some(reason))
+        .await?;
+    if !user_service.ban_user(&user_id, reason).await? {
+        return Ok(user_not_found());
+    }
+    Ok((http::Status::Ok, Json(AdminActionResponse::success())))
+}
+
+#[rocket::post("/users/<user_id>/unban")]
+pub async fn unban_user(
+    user_id: UserId,
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+) -> ActionResult {
+    admin
+        .record(user_service, AdminAction::UnbanUser, user_id, None)
+        .await?;
+    if !user_service.unban_user(&user_id).await? {
+        return Ok(user_not_found());
+    }
+    Ok((http::Status::Ok, Json(AdminActionResponse::success())))
+}
+
+/// Replaces the user's password with a random one that the admin hands over to the user.
+#[rocket::post("/users/<user_id>/reset-password")]
+pub async fn reset_password(
+    user_id: UserId,
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+) -> ActionResult {
+    admin
+        .record(user_service, AdminAction::ResetPassword, user_id, None)
+        .await?;
+    let Some(password) = user_service.reset_password(&user_id).await? else {
+        return Ok(user_not_found());
+    };
+    Ok((
+        http::Status::Ok,
+        Json(AdminActionResponse::message(format!(
+            "Temporary password: {}",
+            password
+        ))),
+    ))
+}
+
+#[rocket::post("/users/<user_id>/logout")]
+pub async fn force_logout(
+    user_id: UserId,
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+) -> ActionResult {
+    admin
+        .record(user_service, AdminAction::ForceLogout, user_id, None)
+        .await?;
+    if !user_service.revoke_sessions(&user_id).await? {
+        return Ok(user_not_found());
+    }
+    Ok((http::Status::Ok, Json(AdminActionResponse::success())))
+}
+
+fn comment_not_found() -> (http::Status, Json<AdminActionResponse>) {
+    (
+        http::Status::NotFound,
+        Json(AdminActionResponse::error("Comment doesn't exist.")),
+    )
+}
+
+#[rocket::get("/quests?<filter..>")]
+pub async fn quests(
+    filter: QuestFilter,
+    admin: AdminUser,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Template, Error> {
+    let mut progress = quest_service
+        .list_prewarm_progress()
+        .await?
+        .into_iter()
+        .map(|progress| (progress.quest_id, progress))
+        .collect::<HashMap<_, _>>();
+    let quests = quest_service
+        .search_quests(&filter)
+        .await?
+        .into_iter()
+        .map(|quest| {
+            let prewarm = progress.remove(&quest.id).map(|progress| {
+                context! {
+                    generated: progress.generated(),
+                    total: progress.total,
+                    failed: progress.failed,
+                    done: progress.is_done(),
+                }
+            });
+            context! {
+                id: quest.id,
+                name: quest.name,
+                official: quest.official,
+                archived: quest.archived,
+                prewarm,
+            }
+        })
+        .collect::<Vec<_>>();
+    Ok(Template::render(
+        "admin-quests",
+        PageContext::new(&Some(admin.0), context! { filter, quests }),
+    ))
+}
+
+#[derive(FromForm)]
+pub struct ToggleForm {
+    value: bool,
+}
+
+#[rocket::post("/quests/<quest_id>/official", data = "<form>")]
+pub async fn set_official(
+    quest_id: QuestId,
+    form: Form<ToggleForm>,
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> ActionResult {
+    admin
+        .record(
+            user_service,
+            AdminAction::SetOfficial,
+            quest_id,
+            Some(&form.value.to_string()),
+        )
+        .await?;
+    let data = PartialQuestData::empty().with_official(form.value);
+    if !quest_service.modify_quest(&quest_id, data).await? {
+        return Ok(quest_not_found());
+    }
+    Ok((http::Status::Ok, Json(AdminActionResponse::success())))
+}
+
+#[rocket::post("/quests/<quest_id>/archive", data = "<form>")]
+pub async fn archive_quest(
+    quest_id: QuestId,
+    form: Form<ToggleForm>,
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> ActionResult {
+    let action = if form.value {
+        AdminAction::ArchiveQuest
+    } else {
+        AdminAction::UnarchiveQuest
+    };
+    admin.record(user_service, action, quest_id, None).await?;
+    let data = PartialQuestData::empty().with_archived(form.value);
+    if !quest_service.modify_quest(&quest_id, data).await? {
+        return Ok(quest_not_found());
+    }
+    Ok((http::Status::Ok, Json(AdminActionResponse::success())))
+}
+
+#[rocket::post("/quests/<quest_id>/clear-contexts")]
+pub async fn clear_quest_contexts(
+    quest_id: QuestId,
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> ActionResult {
+    if !quest_service.quest_exists(&quest_id).await? {
+        return Ok(quest_not_found());
+    }
+    admin
+        .record(
+            user_service,
+            AdminAction::ClearQuestContexts,
+            quest_id,
+            None,
+        )
+        .await?;
+    quest_service.clear_quest_contexts(&quest_id).await?;
+    Ok((http::Status::Ok, Json(AdminActionResponse::success())))
+}
+
+#[rocket::post("/quests/<quest_id>/regenerate-contexts")]
+pub async fn regenerate_quest_contexts(
+    quest_id: QuestId,
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> ActionResult {
+    admin
+        .record(
+            user_service,
+            AdminAction::RegenerateQuestContexts,
+            quest_id,
+            None,
+        )
+        .await?;
+    if !quest_service.regenerate_quest_contexts(&quest_id).await? {
+        return Ok(quest_not_found());
+    }
+    Ok((http::Status::Ok, Json(AdminActionResponse::success())))
+}
+
+/// A quest package, downloaded as a file.
+#[derive(Responder)]
+pub struct PackageDownload(Json<QuestPackage>, Header<'static>);
+
+#[rocket::get("/quests/<quest_id>/export")]
+pub async fn export_quest(
+    quest_id: QuestId,
+    _admin: AdminUser,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<PackageDownload, http::Status>, Error> {
+    Ok(match quest_service.export_quest(&quest_id).await? {
+        Some(package) => Ok(PackageDownload(
+            Json(package),
+            Header::new(
+                "Content-Disposition",
+                format!("attachment; filename=\"quest-{}.json\"", quest_id),
+            ),
+        )),
+        None => Err(http::Status::NotFound),
+    })
+}
+
+#[rocket::post("/quests/import", data = "<package>")]
+pub async fn import_quest(
+    package: Json<QuestPackage>,
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> ActionResult {
+    let name = package.manifest.name.clone();
+    admin
+        .record(
+            user_service,
+            AdminAction::ImportQuest,
+            package.manifest.id,
+            Some(&name),
+        )
+        .await?;
+    match quest_service.import_quest(package.0).await {
+        Ok(_) => (),
+        Err(Error::InvalidQuestPackage(reason)) => {
+            return Ok((
+                http::Status::BadRequest,
+                Json(AdminActionResponse::error(format!(
+                    "Invalid package: {}.",
+                    reason
+                ))),
+            ));
+        }
+        Err(e) => return Err(e),
+    }
+    Ok((
+        http::Status::Ok,
+        Json(AdminActionResponse::message(format!(
+            "Imported \"{}\".",
+            name
+        ))),
+    ))
+}
+
+#[rocket::post("/quests/<quest_id>/delete")]
+pub async fn delete_quest(
+    quest_id: QuestId,
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> ActionResult {
+    let Some(quest) = quest_service.get_quest(&quest_id).await? else {
+        return Ok(quest_not_found());
+    };
+    admin
+        .record(
+            user_service,
+            AdminAction::DeleteQuest,
+            quest_id,
+            Some(&quest.name),
+        )
+        .await?;
+    if !quest_service.delete_quest(&quest_id).await? {
+        return Ok(quest_not_found());
+    }
+    Ok((http::Status::Ok, Json(AdminActionResponse::success())))
+}
+
+#[rocket::get("/comments")]
+pub async fn reported_comments(
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Template, Error> {
+    let mut usernames = HashMap::new();
+    let mut comments = Vec::new();
+    for reported in quest_service.list_reported_comments().await? {
+        let comment = reported.comment;
+        comments.push(context! {
+            id: comment.id,
+            quest_id: comment.quest_id,
+            thread: comment.thread,
+            author_id: comment.author,
+            author: username(comment.author, &mut usernames, user_service).await?,
+            body_html: render_markdown(&comment.body),
+            hidden: comment.hidden,
+            reasons: reported.reasons,
+            last_reported_at: reported.last_reported_at.format(DATE_FORMAT).to_string(),
+        });
+    }
+    Ok(Template::render(
+        "admin-comments",
+        PageContext::new(&Some(admin.0), context! { comments }),
+    ))
+}
+
+#[rocket::post("/comments/<comment_id>/hide", data = "<form>")]
+pub async fn hide_comment(
+    comment_id: CommentId,
+    form: Form<ToggleForm>,
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> ActionResult {
+    let action = if form.value {
+        AdminAction::HideComment
+    } else {
+        AdminAction::UnhideComment
+    };
+    admin.record(user_service, action, comment_id, None).await?;
+    if !quest_service
+        .set_comment_hidden(&comment_id, form.value)
+        .await?
+    {
+        return Ok(comment_not_found());
+    }
+    Ok((http::Status::Ok, Json(AdminActionResponse::success())))
+}
+
+#[rocket::post("/comments/<comment_id>/dismiss")]
+pub async fn dismiss_comment_reports(
+    comment_id: CommentId,
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> ActionResult {
+    admin
+        .record(
+            user_service,
+            AdminAction::DismissCommentReports,
+            comment_id,
+            None,
+        )
+        .await?;
+    if !quest_service.dismiss_comment_reports(&comment_id).await? {
+        return Ok(comment_not_found());
+    }
+    Ok((http::Status::Ok, Json(AdminActionResponse::success())))
+}
+
+#[rocket::get("/audit")]
+pub async fn audit(
+    admin: AdminUser,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<Template, Error> {
+    let entries = user_service
+        .list_admin_actions(AUDIT_LOG_LIMIT)
+        .await?
+        .into_iter()
+        .map(|entry| {
+            context! {
+                admin: match (&entry.admin_username, entry.admin_id) {
+                    (Some(username), _) => username.to_string(),
+                    (None, Some(admin_id)) => admin_id.to_string(),
+                    (None, None) => "Command line".to_owned(),
+                },
+                action: entry.action,
+                target: entry.target,
+                details: entry.details,
+                created_at: entry.created_at.format(DATE_FORMAT).to_string(),
+            }
+        })
+        .collect::<Vec<_>>();
+    Ok(Template::render(
+        "admin-audit",
+        PageContext::new(&Some(admin.0), context! { entries }),
+    ))
+}
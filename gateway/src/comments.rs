@@ -0,0 +1,441 @@
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    sync::Arc,
+};
+
+use codequest_common::{
+    Error, Quest, QuestId, UserId, Username,
+    comment::{Comment, CommentId, CommentThread, NewComment},
+    services::{ProgressionService, QuestService, UserService},
+};
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, html};
+use rocket::{FromForm, State, form::Form, http, response::Redirect};
+use rocket_dyn_templates::{Template, context};
+
+use crate::{
+    account::AuthUser,
+    pages::{PageContext, visible_quest},
+};
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+/// Deeper replies aren't indented any further.
+const MAX_REPLY_DEPTH: usize = 6;
+
+/// Renders a comment's Markdown. Raw HTML is escaped and links may only point to the web, so
+/// comments can't inject markup or scripts.
+pub(crate) fn render_markdown(markdown: &str) -> String {
+    let parser = Parser::new_ext(
+        markdown,
+        Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES,
+    )
+    .map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        event => event,
+    });
+    let mut rendered = String::with_capacity(markdown.len() * 3 / 2);
+    html::push_html(&mut rendered, parser);
+    rendered
+}
+
+/// Relative URLs and the web and mail schemes are kept, anything else (`javascript:` in
+/// particular) is dropped.
+fn safe_url(url: CowStr<'_>) -> CowStr<'_> {
+    let scheme = url
+        .split_once(':')
+        .map(|(scheme, _)| scheme)
+        .filter(|scheme| !scheme.contains(['/', '?', '#']));
+    match scheme.map(str::to_ascii_lowercase).as_deref() {
+        None | Some("http" | "https" | "mailto") => url,
+        Some(_) => CowStr::Borrowed("#"),
+    }
+}
+
+/// Authors, admins and users who completed the quest can read its spoilers.
+async fn can_read_spoilers(
+    quest: &Quest,
+    user: &Option<AuthUser>,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<bool, Error> {
+    let Some(user) = user else {
+        return Ok(false);
+    };
+    if user.is_admin || quest.is_author(&user.id) {
+        return Ok(true);
+    }
+    progression_service
+        .has_user_completed_quest(&user.id, &quest.id)
+        .await
+}
+
+pub(crate) fn thread_url(quest_id: &QuestId, thread: CommentThread) -> String {
+    match thread {
+        CommentThread::Discussion => format!("/quests/{}/comments", quest_id),
+        CommentThread::Solutions => format!("/quests/{}/comments?thread=solutions", quest_id),
+    }
+}
+
+/// Orders the comments depth first, so replies follow their parents, and pairs them with their
+/// depth.
+fn thread_order(comments: Box<[Comment]>) -> Vec<(Comment, usize)> {
+    let mut replies = HashMap::<Option<CommentId>, Vec<Comment>>::new();
+    for comment in comments {
+        replies.entry(comment.parent_id).or_default().push(comment);
+    }
+    let mut ordered = Vec::new();
+    // Reversed, so popping visits the oldest comments first.
+    let mut stack = replies
+        .remove(&None)
+        .unwrap_or_default()
+        .into_iter()
+        .rev()
+        .map(|comment| (comment, 0))
+        .collect::<Vec<_>>();
+    while let Some((comment, depth)) = stack.pop() {
+        if let Some(children) = replies.remove(&Some(comment.id)) {
+            stack.extend(children.into_iter().rev().map(|child| (child, depth + 1)));
+        }
+        ordered.push((comment, depth));
+    }
+    ordered
+}
+
+pub(crate) async fn username(
+    author: Option<UserId>,
+    usernames: &mut HashMap<UserId, Option<Username>>,
+    user_service: &Arc<dyn UserService>,
+) -> Result<Option<Username>, Error> {
+    let Some(author) = author else {
+        return Ok(None);
+    };
+    Ok(match usernames.entry(author) {
+        Entry::Occupied(entry) => entry.get().clone(),
+        Entry::Vacant(entry) => entry
+            .insert(
+                user_service
+                    .get_user(&author)
+                    .await?
+                    .map(|user| user.username),
+            )
+            .clone(),
+    })
+}
+
+#[rocket::get("/quests/<quest_id>/comments?<thread>")]
+pub async fn comments(
+    quest_id: QuestId,
+    thread: Option<CommentThread>,
+    user: Option<AuthUser>,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<Result<Template, http::Status>, Error> {
+    let Some(quest) = visible_quest(&quest_id, user.as_ref(), quest_service).await? else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    let thread = thread.unwrap_or_default();
+    let locked =
+        thread.is_spoiler() && !can_read_spoilers(&quest, &user, progression_service).await?;
+    let is_admin = user.as_ref().is_some_and(|user| user.is_admin);
+
+    let mut usernames = HashMap::new();
+    let mut comments = Vec::new();
+    if !locked {
+        for (comment, depth) in thread_order(quest_service.list_comments(&quest_id, thread).await?)
+        {
+            let is_author = user
+                .as_ref()
+                .is_some_and(|user| comment.is_author(&user.id));
+            let visible = !comment.deleted && (!comment.hidden || is_admin || is_author);
+            comments.push(context! {
+                id: comment.id,
+                author: username(comment.author, &mut usernames, user_service).await?,
+                created_at: comment.created_at.format(DATE_FORMAT).to_string(),
+                edited: comment.edited_at.is_some(),
+                depth: depth.min(MAX_REPLY_DEPTH),
+                deleted: comment.deleted,
+                hidden: comment.hidden,
+                body: visible.then(|| comment.body.clone()),
+                body_html: visible.then(|| render_markdown(&comment.body)),
+                can_reply: user.is_some() && !comment.deleted,
+                can_edit: is_author && !comment.deleted,
+                can_report: user.is_some() && !is_author && !comment.deleted,
+            });
+        }
+    }
+
+    Ok(Ok(Template::render(
+        "comments",
+        PageContext::new(
+            &user,
+            context! {
+                quest: context! {
+                    id: &quest.id,
+                    name: &quest.name,
+                },
+                thread,
+                solutions: thread.is_spoiler(),
+                locked,
+                comments,
+            },
+        ),
+    )))
+}
+
+#[derive(FromForm)]
+pub(crate) struct CommentForm<'a> {
+    #[field(default = CommentThread::Discussion)]
+    thread: CommentThread,
+    parent_id: Option<&'a str>,
+    body: &'a str,
+}
+
+#[rocket::post("/quests/<quest_id>/comments", data = "<form>")]
+pub async fn post_comment(
+    quest_id: QuestId,
+    form: Form<CommentForm<'_>>,
+    user: AuthUser,
+    quest_service: &State<Arc<dyn QuestService>>,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<Result<Redirect, http::Status>, Error> {
+    let Some(quest) = visible_quest(&quest_id, Some(&user), quest_service).await? else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    let parent_id = match form.parent_id.filter(|id| !id.is_empty()) {
+        Some(id) => Some(CommentId::try_parse(id)?),
+        None => None,
+    };
+    // Replies are posted in their parent's thread.
+    let thread = match &parent_id {
+        Some(parent_id) => match quest_service.get_comment(parent_id).await? {
+            Some(parent) if parent.quest_id == quest_id => parent.thread,
+            _ => return Ok(Err(http::Status::NotFound)),
+        },
+        None => form.thread,
+    };
+    let author = user.id;
+    if thread.is_spoiler() && !can_read_spoilers(&quest, &Some(user), progression_service).await? {
+        return Ok(Err(http::Status::Forbidden));
+    }
+
+    let comment = NewComment {
+        author,
+        thread,
+        parent_id,
+        body: form.body.to_owned(),
+    };
+    let Some(comment_id) = quest_service.post_comment(&quest_id, comment).await? else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    Ok(Ok(Redirect::to(format!(
+        "{}#comment-{}",
+        thread_url(&quest_id, thread),
+        comment_id
+    ))))
+}
+
+#[derive(FromForm)]
+pub(crate) struct EditCommentForm<'a> {
+    body: &'a str,
+}
+
+#[rocket::post("/comments/<comment_id>/edit", data = "<form>")]
+pub async fn edit_comment(
+    comment_id: CommentId,
+    form: Form<EditCommentForm<'_>>,
+    user: AuthUser,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<Redirect, http::Status>, Error> {
+    let Some(comment) = quest_service.get_comment(&comment_id).await? else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    if !comment.is_author(&user.id) {
+        return Ok(Err(http::Status::Forbidden));
+    }
+    if !quest_service
+        .edit_comment(&comment_id, form.body.to_owned())
+        .await?
+    {
+        return Ok(Err(http::Status::NotFound));
+    }
+    Ok(Ok(Redirect::to(format!(
+        "{}#comment-{}",
+        thread_url(&comment.quest_id, comment.thread),
+        comment_id
+    ))))
+}
+
+#[rocket::post("/comments/<comment_id>/delete")]
+pub async fn delete_comment(
+    comment_id: CommentId,
+    user: AuthUser,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<Redirect, http::Status>, Error> {
+    let Some(comment) = quest_service.get_comment(&comment_id).await? else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    if !comment.is_author(&user.id) {
+        return Ok(Err(http::Status::Forbidden));
+    }
+    quest_service.delete_comment(&comment_id).await?;
+    Ok(Ok(Redirect::to(thread_url(
+        &comment.quest_id,
+        comment.thread,
+    ))))
+}
+
+#[derive(FromForm)]
+pub(crate) struct ReportCommentForm<'a> {
+    #[field(default = "")]
+    reason: &'a str,
+}
+
+#[rocket::post("/comments/<comment_id>/report", data = "<form>")]
+pub async fn report_comment(
+    comment_id: CommentId,
+    form: Form<ReportCommentForm<'_>>,
+    user: AuthUser,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<Redirect, http::Status>, Error> {
+    let Some(comment) = quest_service.get_comment(&comment_id).await? else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    if !quest_service
+        .report_comment(&comment_id, &user.id, form.reason.to_owned())
+        .await?
+    {
+        return Ok(Err(http::Status::NotFound));
+    }
+    Ok(Ok(Redirect::to(format!(
+        "{}#comment-{}",
+        thread_url(&comment.quest_id, comment.thread),
+        comment_id
+    ))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_html_is_escaped() {
+        let rendered =
+            render_markdown("<script>alert(1)</script>\n\nHi <img src=x onerror=alert(1)>");
+        assert!(!rendered.contains("<script"));
+        assert!(!rendered.contains("<img"));
+        assert!(rendered.contains("&lt;script&gt;"));
+    }
+
+    #[test]
+    fn script_links_are_dropped() {
+        for markdown in [
+            "[click](javascript:alert(1))",
+            "[click](JavaScript:alert(1))",
+            "[click](data:text/html;base64,PHNjcmlwdD4=)",
+            "![image](javascript:alert(1))",
+            "<javascript:alert(1)>",
+        ] {
+            let rendered = render_markdown(markdown);
+            assert!(
+                rendered.contains("href=\"#\"") || rendered.contains("src=\"#\""),
+                "{}",
+                rendered
+            );
+            assert!(
+                !rendered.to_lowercase().contains("=\"javascript:"),
+                "{}",
+                rendered
+            );
+            assert!(!rendered.contains("=\"data:"), "{}", rendered);
+        }
+    }
+
+    #[test]
+    fn web_links_are_kept() {
+        assert_eq!(
+            render_markdown("[docs](https://example.com/a?b=c)"),
+            "<p><a href=\"https://example.com/a?b=c\">docs</a></p>\n"
+        );
+        assert!(
+            render_markdown("[mail](mailto:a@example.com)")
+                .contains("href=\"mailto:a@example.com\"")
+        );
+        assert!(render_markdown("[quest](/quests/1)").contains("href=\"/quests/1\""));
+    }
+
+    #[test]
+    fn urls_are_filtered_by_scheme() {
+        let safe = |url| safe_url(CowStr::Borrowed(url)).into_string();
+        assert_eq!(safe("http://example.com"), "http://example.com");
+        assert_eq!(safe("HTTPS://example.com"), "HTTPS://example.com");
+        assert_eq!(safe("/path:with-colon"), "/path:with-colon");
+        assert_eq!(safe("?query=a:b"), "?query=a:b");
+        assert_eq!(safe("#a:b"), "#a:b");
+        assert_eq!(safe("javascript:alert(1)"), "#");
+        assert_eq!(safe("vbscript:msgbox"), "#");
+        assert_eq!(safe("file:///etc/passwd"), "#");
+    }
+
+    fn comment(parent_id: Option<CommentId>) -> Comment {
+        Comment {
+            id: CommentId::new(),
+            quest_id: QuestId::new(),
+            thread: CommentThread::Discussion,
+            parent_id,
+            author: None,
+            body: String::new(),
+            created_at: Default::default(),
+            edited_at: None,
+            deleted: false,
+            hidden: false,
+        }
+    }
+
+    #[test]
+    fn replies_follow_their_parents() {
+        let first = comment(None);
+        let second = comment(None);
+        let reply = comment(Some(first.id));
+        let nested = comment(Some(reply.id));
+        let late_reply = comment(Some(first.id));
+        let comments = [&first, &second, &reply, &nested, &late_reply]
+            .map(Clone::clone)
+            .into();
+
+        let ordered = thread_order(comments)
+            .into_iter()
+            .map(|(comment, depth)| (comment.id, depth))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ordered,
+            vec![
+                (first.id, 0),
+                (reply.id, 1),
+                (nested.id, 2),
+                (late_reply.id, 1),
+                (second.id, 0),
+            ]
+        );
+    }
+}
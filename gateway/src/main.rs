@@ -2,17 +2,26 @@ use std::{env, sync::Arc};
 
 use codequest_common::{
     load_secret_key,
-    services::{ProgressionService, QuestService, StatisticsService, UserService},
+    services::{
+        NotificationService, ProgressionService, QuestService, StatisticsService, UserService,
+    },
 };
+use codequest_notification_service::BackendNotificationService;
 use codequest_progression_service::BackendProgressionService;
 use codequest_quest_service::BackendQuestService;
 use codequest_statistics_service::BackendStatisticsService;
 use codequest_user_service::BackendUserService;
 use dotenv::dotenv;
-use rocket::routes;
+use rocket::{
+    data::{Limits, ToByteUnit as _},
+    routes,
+};
 use rocket_dyn_templates::Template;
 
 mod account;
+mod admin;
+mod comments;
+mod notifications;
 mod pages;
 
 mod defaults {
@@ -38,7 +47,9 @@ async fn main() -> Result<(), rocket::Error> {
 
     let rocket_config = rocket::Config::figment()
         .merge(("secret_key", secret_key))
-        .merge(("port", port));
+        .merge(("port", port))
+        // Quest packages carry generator executables.
+        .merge(("limits", Limits::default().limit("json", 64.mebibytes())));
 
     let user_service_address =
         env::var("USER_SERVICE_ADDRESS").expect("USER_SERVICE_ADDRESS not set");
@@ -48,11 +59,16 @@ async fn main() -> Result<(), rocket::Error> {
         env::var("PROGRESSION_SERVICE_ADDRESS").expect("PROGRESSION_SERVICE_ADDRESS not set");
     let statistics_service_address =
         env::var("STATISTICS_SERVICE_ADDRESS").expect("STATISTICS_SERVICE_ADDRESS not set");
+    let notification_service_address =
+        env::var("NOTIFICATION_SERVICE_ADDRESS").expect("NOTIFICATION_SERVICE_ADDRESS not set");
+
+    let nats_address = env::var("NATS_ADDRESS").expect("NATS_ADDRESS not set");
 
     let user_service = BackendUserService::new(user_service_address);
     let quest_service = BackendQuestService::new(quest_service_address);
     let progression_service = BackendProgressionService::new(progression_service_address);
     let statistics_service = BackendStatisticsService::new(statistics_service_address);
+    let notification_service = BackendNotificationService::new(notification_service_address);
 
     rocket::custom(&rocket_config)
         .mount(
@@ -68,21 +84,69 @@ async fn main() -> Result<(), rocket::Error> {
                 pages::create_quest_form,
                 pages::edit_quest_page,
                 pages::modify_quest,
+                pages::test_generator,
+                pages::upload_generator,
                 pages::quest,
                 pages::quest_input,
                 pages::quest_answer,
+                pages::reveal_hint,
+                pages::rate_quest,
+                pages::favourite_quest,
+                pages::submit_program,
+                pages::program_submissions,
+                pages::program_submission,
                 pages::account,
                 pages::account_statistics,
+                pages::profile,
+                comments::comments,
+                comments::post_comment,
+                comments::edit_comment,
+                comments::delete_comment,
+                comments::report_comment,
+                notifications::notifications,
+                notifications::read_notification,
+                notifications::read_all_notifications,
+                notifications::notification_stream,
             ],
         )
         .mount(
             "/account",
             routes![
                 account::login,
+                account::login_totp,
                 account::signup,
                 account::logout,
                 account::change_password,
+                account::change_username,
                 account::delete,
+                account::erasure_status,
+                account::export,
+                account::begin_totp_enrollment,
+                account::confirm_totp_enrollment,
+                account::disable_totp,
+            ],
+        )
+        .mount(
+            "/admin",
+            routes![
+                admin::users,
+                admin::user,
+                admin::ban_user,
+                admin::unban_user,
+                admin::reset_password,
+                admin::force_logout,
+                admin::quests,
+                admin::set_official,
+                admin::archive_quest,
+                admin::clear_quest_contexts,
+                admin::regenerate_quest_contexts,
+                admin::export_quest,
+                admin::import_quest,
+                admin::delete_quest,
+                admin::reported_comments,
+                admin::hide_comment,
+                admin::dismiss_comment_reports,
+                admin::audit,
             ],
         )
         .attach(Template::fairing())
@@ -90,6 +154,8 @@ async fn main() -> Result<(), rocket::Error> {
         .manage(Arc::new(quest_service) as Arc<dyn QuestService>)
         .manage(Arc::new(progression_service) as Arc<dyn ProgressionService>)
         .manage(Arc::new(statistics_service) as Arc<dyn StatisticsService>)
+        .manage(Arc::new(notification_service) as Arc<dyn NotificationService>)
+        .manage(notifications::NotificationFeed::spawn(nats_address))
         .launch()
         .await?;
 
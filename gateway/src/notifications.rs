@@ -0,0 +1,241 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use codequest_common::{
+    Error, UserId, Username,
+    event::NotificationEvent,
+    nats::NatsClient,
+    notification::{Notification, NotificationId, NotificationKind},
+    services::{NotificationService, QuestService, UserService},
+};
+use rocket::{
+    Shutdown, State, http,
+    response::{
+        Redirect,
+        stream::{Event, EventStream},
+    },
+    tokio::{
+        self, select,
+        sync::broadcast::{self, error::RecvError},
+    },
+};
+use rocket_dyn_templates::{Template, context};
+use serde::Serialize;
+
+use crate::{account::AuthUser, comments, pages::PageContext};
+
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+/// Notifications shown on the notifications page.
+const LISTED_NOTIFICATIONS: u32 = 50;
+/// Events buffered for slow streams before they start missing some.
+const FEED_CAPACITY: usize = 256;
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(5);
+
+/// The notification events published from now on, for the open notification streams.
+pub struct NotificationFeed(broadcast::Sender<NotificationEvent>);
+
+impl NotificationFeed {
+    /// Follows the notification events in the background, subscribing again whenever the
+    /// subscription fails.
+    pub fn spawn(nats_address: String) -> Self {
+        let (sender, _) = broadcast::channel(FEED_CAPACITY);
+        let feed = sender.clone();
+        tokio::spawn(async move {
+            loop {
+                let result = async {
+                    let mut subscription = NatsClient::new(&nats_address)
+                        .await?
+                        .subscribe::<NotificationEvent>("NOTIFICATION_EVENTS")
+                        .await?;
+                    while let Some(envelope) = subscription.next().await? {
+                        // Fails if no stream is open, which is fine.
+                        let _ = feed.send(envelope.event);
+                    }
+                    Ok::<_, Error>(())
+                }
+                .await;
+                if let Err(e) = result {
+                    eprintln!("failed to follow NOTIFICATION_EVENTS: {}", e);
+                }
+                tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+            }
+        });
+        Self(sender)
+    }
+}
+
+#[derive(Serialize)]
+struct NotificationContext {
+    id: NotificationId,
+    message: String,
+    url: String,
+    created_at: String,
+    read: bool,
+}
+
+/// Describes the notification, with the names as they are now.
+async fn notification_context(
+    notification: Notification,
+    usernames: &mut HashMap<UserId, Option<Username>>,
+    user_service: &Arc<dyn UserService>,
+    quest_service: &Arc<dyn QuestService>,
+) -> Result<NotificationContext, Error> {
+    let quest = quest_service.get_quest(&notification.quest_id).await?;
+    let quest_name = quest
+        .as_ref()
+        .map_or("a deleted quest", |quest| quest.name.as_str());
+    let actor = match comments::username(notification.actor, usernames, user_service).await? {
+        Some(username) => username.to_string(),
+        None => "A deleted user".to_owned(),
+    };
+    let message = match notification.kind {
+        // Imported official quests have no author.
+        NotificationKind::QuestPublished if notification.actor.is_none() => {
+            format!("A new quest was published: {}", quest_name)
+        }
+        NotificationKind::QuestPublished => {
+            format!("{} published a new quest: {}", actor, quest_name)
+        }
+        NotificationKind::QuestCompleted => {
+            format!("{} completed your quest {}", actor, quest_name)
+        }
+        NotificationKind::QuestCommented => {
+            format!("{} commented on your quest {}", actor, quest_name)
+        }
+        NotificationKind::CommentReplied => {
+            format!("{} replied to your comment on {}", actor, quest_name)
+        }
+        NotificationKind::QuestApproved => format!("Your quest {} is now official", quest_name),
+    };
+    let comment = match &notification.comment_id {
+        Some(comment_id) => quest_service.get_comment(comment_id).await?,
+        None => None,
+    };
+    let url = match comment {
+        Some(comment) => format!(
+            "{}#comment-{}",
+            comments::thread_url(&comment.quest_id, comment.thread),
+            comment.id
+        ),
+        None => format!("/quests/{}", notification.quest_id),
+    };
+    Ok(NotificationContext {
+        id: notification.id,
+        message,
+        url,
+        created_at: notification.created_at.format(DATE_FORMAT).to_string(),
+        read: notification.read,
+    })
+}
+
+#[rocket::get("/notifications")]
+pub async fn notifications(
+    user: AuthUser,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+    notification_service: &State<Arc<dyn NotificationService>>,
+) -> Result<Template, Error> {
+    let mut usernames = HashMap::new();
+    let mut notifications = Vec::new();
+    for notification in notification_service
+        .list_notifications(&user.id, LISTED_NOTIFICATIONS)
+        .await?
+    {
+        notifications.push(
+            notification_context(notification, &mut usernames, user_service, quest_service).await?,
+        );
+    }
+    let unread = notifications.iter().any(|notification| !notification.read);
+    Ok(Template::render(
+        "notifications",
+        PageContext::new(
+            &Some(user),
+            context! {
+                notifications,
+                unread,
+            },
+        ),
+    ))
+}
+
+/// Marks the notification as read and opens what it's about.
+#[rocket::post("/notifications/<id>/read")]
+pub async fn read_notification(
+    id: NotificationId,
+    user: AuthUser,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+    notification_service: &State<Arc<dyn NotificationService>>,
+) -> Result<Result<Redirect, http::Status>, Error> {
+    let Some(notification) = notification_service.mark_read(&user.id, &id).await? else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    let context = notification_context(
+        notification,
+        &mut HashMap::new(),
+        user_service,
+        quest_service,
+    )
+    .await?;
+    Ok(Ok(Redirect::to(context.url)))
+}
+
+#[rocket::post("/notifications/read")]
+pub async fn read_all_notifications(
+    user: AuthUser,
+    notification_service: &State<Arc<dyn NotificationService>>,
+) -> Result<Redirect, Error> {
+    notification_service.mark_all_read(&user.id).await?;
+    Ok(Redirect::to("/notifications"))
+}
+
+/// Pushes the user's new notifications and their number of unread notifications. The number is
+/// sent right away and again whenever notifications are read or events were missed.
+#[rocket::get("/notifications/stream")]
+pub async fn notification_stream(
+    user: AuthUser,
+    feed: &State<NotificationFeed>,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+    notification_service: &State<Arc<dyn NotificationService>>,
+    mut shutdown: Shutdown,
+) -> Result<EventStream![], Error> {
+    let mut events = feed.0.subscribe();
+    let unread = notification_service.count_unread(&user.id).await?;
+    let user_service = user_service.inner().clone();
+    let quest_service = quest_service.inner().clone();
+    let notification_service = notification_service.inner().clone();
+
+    Ok(EventStream! {
+        yield Event::json(&unread).event("unread");
+        let mut usernames = HashMap::new();
+        loop {
+            let event = select! {
+                event = events.recv() => event,
+                _ = &mut shutdown => break,
+            };
+            match event {
+                Ok(NotificationEvent::Created(notification)) if notification.user_id == user.id => {
+                    match notification_context(
+                        notification,
+                        &mut usernames,
+                        &user_service,
+                        &quest_service,
+                    )
+                    .await
+                    {
+                        Ok(notification) => yield Event::json(&notification).event("notification"),
+                        Err(e) => eprintln!("failed to describe notification: {}", e),
+                    }
+                }
+                Ok(NotificationEvent::Read { user_id, .. }) if user_id == user.id => (),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(_)) => (),
+                Err(RecvError::Closed) => break,
+            }
+            match notification_service.count_unread(&user.id).await {
+                Ok(unread) => yield Event::json(&unread).event("unread"),
+                Err(e) => eprintln!("failed to count unread notifications: {}", e),
+            }
+        }
+    })
+}
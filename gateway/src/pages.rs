@@ -1,45 +1,55 @@
 use std::{path::Path, sync::Arc};
 
 use codequest_common::{
-    Error, PartialQuestData, QuestData, QuestEntry, QuestId,
+    AnswerMode, Error, GeneratorTestReport, Hint, PartialQuestData, Quest, QuestData, QuestEntry,
+    QuestFilter, QuestId, Username,
+    achievement::Achievement,
+    judge::{ProgramSubmission, SubmissionId, Verdict},
+    package::MAX_GENERATOR_SIZE,
+    rating::{MAX_RATING, MIN_RATING, QuestRating, RatingData},
     services::{ProgressionService, QuestService, StatisticsService, UserService},
+    statistics::{DailyActivity, QuestStatistics, format_duration},
 };
 use rocket::{
-    FromForm, State, form::Form, fs::NamedFile, http, response::Redirect, serde::json::Json,
+    FromForm, State,
+    data::{Data, ToByteUnit as _},
+    form::Form,
+    fs::NamedFile,
+    http,
+    response::Redirect,
+    serde::json::Json,
 };
 use rocket_dyn_templates::{Template, context};
 use serde::{Deserialize, Serialize};
 
 use crate::account::AuthUser;
 
+/// Synthetic users the generator is run for when its author tests it.
+const GENERATOR_TEST_USERS: usize = 20;
+
 #[derive(Serialize)]
-struct PageContext<'a, MainContext: Serialize> {
+pub(crate) struct PageContext<'a, MainContext: Serialize> {
     user: Option<&'a str>,
+    admin: bool,
     #[serde(flatten)]
     content: MainContext,
 }
 
 impl<'a> PageContext<'a, ()> {
     fn simple(user: &'a Option<AuthUser>) -> Self {
-        Self {
-            user: if let Some(AuthUser { username, .. }) = &user {
-                Some(username.as_str())
-            } else {
-                None
-            },
-            content: (),
-        }
+        PageContext::new(user, ())
     }
 }
 
 impl<'a, MainContext: Serialize> PageContext<'a, MainContext> {
-    fn new(user: &'a Option<AuthUser>, content: MainContext) -> Self {
+    pub(crate) fn new(user: &'a Option<AuthUser>, content: MainContext) -> Self {
         Self {
             user: if let Some(AuthUser { username, .. }) = &user {
                 Some(username.as_str())
             } else {
                 None
             },
+            admin: user.as_ref().is_some_and(|user| user.is_admin),
             content,
         }
     }
@@ -80,30 +90,42 @@ pub async fn stylesheet() -> Option<NamedFile> {
 #[derive(Serialize)]
 struct QuestsPageContext<'a> {
     quests: Vec<QuestContext<'a>>,
+    filter: &'a QuestFilter,
+    favourites: bool,
 }
 
 #[derive(Serialize)]
 struct QuestContext<'a> {
     name: &'a str,
     uri: String,
+    difficulty: Option<i32>,
+    rating: Option<String>,
 }
 
-#[rocket::get("/quests")]
+#[rocket::get("/quests?<favourites>&<filter..>")]
 pub async fn quests(
+    mut filter: QuestFilter,
+    favourites: Option<bool>,
     user: Option<AuthUser>,
     quest_service: &State<Arc<dyn QuestService>>,
 ) -> Result<Template, Error> {
+    // Archived quests are only listed to admins.
+    filter.archived = Some(false);
+    // Users can only list their own favourites.
+    let favourites = favourites.unwrap_or(false) && user.is_some();
+    filter.favourites_of = user.as_ref().map(|user| user.id).filter(|_| favourites);
+    let quests = quest_service.search_quests(&filter).await?;
     Ok(Template::render(
         "quests",
         PageContext::new(
             &user,
             QuestsPageContext {
-                quests: quest_service
-                    .list_quests()
-                    .await?
+                quests: quests
                     .iter()
                     .map(|quest| QuestContext::from(quest))
                     .collect::<Vec<_>>(),
+                filter: &filter,
+                favourites,
             },
         ),
     ))
@@ -114,10 +136,24 @@ impl<'a> From<&'a QuestEntry> for QuestContext<'a> {
         Self {
             name: &quest.name,
             uri: format!("/quests/{}", &quest.id),
+            difficulty: quest.difficulty,
+            rating: quest
+                .average_rating()
+                .map(|rating| format!("{:.1}", rating)),
         }
     }
 }
 
+/// The quest, unless it's archived and the user isn't an admin.
+pub(crate) async fn visible_quest(
+    quest_id: &QuestId,
+    user: Option<&AuthUser>,
+    quest_service: &Arc<dyn QuestService>,
+) -> Result<Option<Quest>, Error> {
+    let quest = quest_service.get_quest(quest_id).await?;
+    Ok(quest.filter(|quest| !quest.archived || user.is_some_and(|user| user.is_admin)))
+}
+
 #[rocket::get("/quests/<quest_id>")]
 pub async fn quest(
     quest_id: QuestId,
@@ -125,12 +161,19 @@ pub async fn quest(
     user_service: &State<Arc<dyn UserService>>,
     quest_service: &State<Arc<dyn QuestService>>,
     progression_service: &State<Arc<dyn ProgressionService>>,
+    statistics_service: &State<Arc<dyn StatisticsService>>,
 ) -> Result<Result<Template, http::Status>, Error> {
-    let Some(quest) = quest_service.get_quest(&quest_id).await? else {
+    let Some(quest) = visible_quest(&quest_id, user.as_ref(), quest_service).await? else {
         return Ok(Err(http::Status::NotFound));
     };
 
     let user_is_quest_author = user.as_ref().is_some_and(|user| quest.is_author(&user.id));
+    let statistics = match user_is_quest_author {
+        true => Some(quest_statistics_context(
+            statistics_service.get_quest_statistics(&quest_id).await?,
+        )),
+        false => None,
+    };
 
     let author = if let Some(author_id) = &quest.author {
         if let Some(author) = user_service.get_user(author_id).await? {
@@ -141,18 +184,44 @@ pub async fn quest(
     } else {
         None
     };
-    let (quest_completed, quest_answer) = if let Some(user) = &user {
+    let program_mode = quest.answer_mode == AnswerMode::Program;
+    let languages = match (&user, program_mode) {
+        (Some(_), true) => progression_service.list_languages().await?.into_vec(),
+        _ => Vec::new(),
+    };
+    let ratings = quest_service.list_ratings(&quest_id).await?;
+    let (own_rating, favourite) = match &user {
+        Some(user) => (
+            quest_service.get_rating(&quest_id, &user.id).await?,
+            quest_service.is_favourite(&quest_id, &user.id).await?,
+        ),
+        None => (None, false),
+    };
+    let (quest_completed, quest_answer, hints) = if let Some(user) = &user {
         let quest_completed = progression_service
             .has_user_completed_quest(&user.id, &quest_id)
             .await?;
-        let quest_answer = if quest_completed {
+        let quest_answer = if quest_completed && !program_mode {
             quest_service.get_answer(&quest_id, &user.id).await?
         } else {
             None
         };
-        (quest_completed, quest_answer)
+        let hints = quest_service
+            .get_hints(&quest_id)
+            .await?
+            .unwrap_or_default();
+        let hints = match hints.is_empty() {
+            true => None,
+            false => {
+                let revealed = progression_service
+                    .get_revealed_hints(&user.id, &quest_id)
+                    .await? as usize;
+                Some(hints_context(hints, revealed))
+            }
+        };
+        (quest_completed, quest_answer, hints)
     } else {
-        (false, None)
+        (false, None, None)
     };
 
     Ok(Ok(Template::render(
@@ -167,13 +236,196 @@ pub async fn quest(
                     text: &quest.text,
                     completed: quest_completed,
                     answer: quest_answer,
+                    program_mode,
+                },
+                languages,
+                hints,
+                rating: ratings_context(&ratings),
+                can_rate: quest_completed && !user_is_quest_author,
+                own_rating,
+                rating_choices: (MIN_RATING..=MAX_RATING).rev().collect::<Vec<_>>(),
+                favourite,
+                feedback: match user_is_quest_author {
+                    true => feedback_context(ratings, user_service).await?,
+                    false => Vec::new(),
                 },
                 user_is_quest_author,
+                statistics,
             },
         ),
     )))
 }
 
+fn ratings_context(ratings: &[QuestRating]) -> impl Serialize + use<> {
+    let sum = ratings
+        .iter()
+        .map(|rating| rating.rating as i64)
+        .sum::<i64>();
+    context! {
+        count: ratings.len(),
+        average: (!ratings.is_empty()).then(|| format!("{:.1}", sum as f64 / ratings.len() as f64)),
+    }
+}
+
+/// The feedback left with the quest's ratings, most recent first.
+async fn feedback_context(
+    ratings: Box<[QuestRating]>,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<Vec<impl Serialize + use<>>, Error> {
+    let mut feedback = Vec::new();
+    for rating in ratings {
+        let Some(text) = rating.feedback else {
+            continue;
+        };
+        feedback.push(context! {
+            username: user_service
+                .get_user(&rating.user_id)
+                .await?
+                .map(|user| user.username),
+            rating: rating.rating,
+            rated_at: rating.rated_at.format("%Y-%m-%d").to_string(),
+            text,
+        });
+    }
+    Ok(feedback)
+}
+
+#[derive(FromForm)]
+pub(crate) struct RatingForm<'a> {
+    rating: i16,
+    #[field(default = "")]
+    feedback: &'a str,
+}
+
+/// Only users who completed the quest can rate it, and authors can't rate their own quests.
+#[rocket::post("/quests/<quest_id>/rating", data = "<form>")]
+pub async fn rate_quest(
+    quest_id: QuestId,
+    form: Form<RatingForm<'_>>,
+    user: AuthUser,
+    quest_service: &State<Arc<dyn QuestService>>,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<Result<Redirect, http::Status>, Error> {
+    let Some(quest) = visible_quest(&quest_id, Some(&user), quest_service).await? else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    if quest.is_author(&user.id)
+        || !progression_service
+            .has_user_completed_quest(&user.id, &quest_id)
+            .await?
+    {
+        return Ok(Err(http::Status::Forbidden));
+    }
+    let rating = RatingData {
+        user_id: user.id,
+        rating: form.rating,
+        feedback: Some(form.feedback.to_owned()),
+    };
+    if !quest_service.rate_quest(&quest_id, rating).await? {
+        return Ok(Err(http::Status::NotFound));
+    }
+    Ok(Ok(Redirect::to(format!("/quests/{}#rating", quest_id))))
+}
+
+#[derive(FromForm)]
+pub(crate) struct FavouriteForm {
+    value: bool,
+}
+
+#[rocket::post("/quests/<quest_id>/favourite", data = "<form>")]
+pub async fn favourite_quest(
+    quest_id: QuestId,
+    form: Form<FavouriteForm>,
+    user: AuthUser,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<Redirect, http::Status>, Error> {
+    if visible_quest(&quest_id, Some(&user), quest_service)
+        .await?
+        .is_none()
+        || !quest_service
+            .set_favourite(&quest_id, &user.id, form.value)
+            .await?
+    {
+        return Ok(Err(http::Status::NotFound));
+    }
+    Ok(Ok(Redirect::to(format!("/quests/{}", quest_id))))
+}
+
+/// The hints the user revealed and the cost of the next one.
+fn hints_context(hints: Box<[Hint]>, revealed: usize) -> impl Serialize {
+    context! {
+        remaining: hints.len().saturating_sub(revealed),
+        next_penalty: hints.get(revealed).map(|hint| hint.penalty),
+        revealed: hints
+            .into_iter()
+            .take(revealed)
+            .map(|hint| hint.text)
+            .collect::<Vec<_>>(),
+    }
+}
+
+#[rocket::post("/quests/<quest_id>/hints")]
+pub async fn reveal_hint(
+    quest_id: QuestId,
+    user: AuthUser,
+    quest_service: &State<Arc<dyn QuestService>>,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<Result<Redirect, http::Status>, Error> {
+    if visible_quest(&quest_id, Some(&user), quest_service)
+        .await?
+        .is_none()
+    {
+        return Ok(Err(http::Status::NotFound));
+    }
+    let Some(hints) = quest_service.get_hints(&quest_id).await? else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    let revealed = progression_service
+        .get_revealed_hints(&user.id, &quest_id)
+        .await?;
+    let Some(hint) = hints.get(revealed as usize) else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    // A concurrent reveal of the same hint is simply not recorded twice.
+    progression_service
+        .reveal_hint(&user.id, &quest_id, revealed, hint.penalty)
+        .await?;
+    Ok(Ok(Redirect::to(format!("/quests/{}#hints", quest_id))))
+}
+
+fn quest_statistics_context(statistics: QuestStatistics) -> impl Serialize {
+    let attempted = statistics
+        .wrong_answer_distribution
+        .iter()
+        .sum::<u64>()
+        .max(1);
+    let last_bucket = statistics.wrong_answer_distribution.len() - 1;
+    context! {
+        players: statistics.players,
+        solvers: statistics.solvers,
+        average_attempts_to_solve: statistics
+            .average_attempts_to_solve
+            .map(|attempts| format!("{:.1}", attempts)),
+        median_solve_time: statistics.median_solve_time.map(format_duration),
+        difficulty: statistics.difficulty,
+        wrong_answers: statistics
+            .wrong_answer_distribution
+            .iter()
+            .enumerate()
+            .map(|(wrong_answers, &users)| {
+                context! {
+                    label: match wrong_answers == last_bucket {
+                        true => format!("{}+", wrong_answers),
+                        false => wrong_answers.to_string(),
+                    },
+                    value: users,
+                    height: users * 100 / attempted,
+                }
+            })
+            .collect::<Vec<_>>(),
+    }
+}
+
 #[rocket::get("/quests/create")]
 pub async fn create_quest_page(user: AuthUser) -> Template {
     Template::render("create-quest", PageContext::new(&Some(user), context! {}))
@@ -183,6 +435,21 @@ pub async fn create_quest_page(user: AuthUser) -> Template {
 pub(crate) struct CreateQuestForm<'a> {
     name: &'a str,
     text: &'a str,
+    hints: Vec<Hint>,
+    #[field(default = AnswerMode::Answer)]
+    answer_mode: AnswerMode,
+}
+
+/// Drops hints the author left empty and normalizes line endings.
+fn clean_hints(hints: Vec<Hint>) -> Vec<Hint> {
+    hints
+        .into_iter()
+        .filter(|hint| !hint.text.trim().is_empty())
+        .map(|hint| Hint {
+            text: hint.text.replace("\r\n", "\n"),
+            ..hint
+        })
+        .collect()
 }
 
 #[rocket::post("/quests", data = "<form>")]
@@ -193,7 +460,10 @@ pub async fn create_quest_form(
 ) -> Result<Redirect, Error> {
     let author = Some(user.id);
     let official = false;
-    let quest = QuestData::new(form.name, author, official, form.text.replace("\r\n", "\n"));
+    let form = form.into_inner();
+    let quest = QuestData::new(form.name, author, official, form.text.replace("\r\n", "\n"))
+        .with_hints(clean_hints(form.hints))
+        .with_answer_mode(form.answer_mode);
     quest_service
         .create_quest(quest)
         .await
@@ -209,6 +479,7 @@ pub async fn edit_quest_page(
     if let Some(quest) = quest_service.get_quest(&id).await? {
         if let Some(author) = quest.author {
             if author == user.id {
+                let hints = quest_service.get_hints(&id).await?.unwrap_or_default();
                 return Ok(Ok(Template::render(
                     "edit-quest",
                     PageContext::new(
@@ -218,6 +489,8 @@ pub async fn edit_quest_page(
                                 id: &quest.id,
                                 name: &quest.name,
                                 text_json: rocket::serde::json::serde_json::to_string(&quest.text)?,
+                                hints_json: rocket::serde::json::serde_json::to_string(&hints)?,
+                                answer_mode: quest.answer_mode,
                             },
                         },
                     ),
@@ -234,6 +507,8 @@ pub async fn edit_quest_page(
 pub(crate) struct ModifyQuestRequest<'a> {
     name: Option<&'a str>,
     text: Option<String>,
+    hints: Option<Vec<Hint>>,
+    answer_mode: Option<AnswerMode>,
 }
 
 #[derive(Serialize)]
@@ -293,6 +568,12 @@ pub async fn modify_quest(
     if let Some(text) = request.text {
         quest_data.set_text(text);
     }
+    if let Some(hints) = request.hints {
+        quest_data.set_hints(clean_hints(hints));
+    }
+    if let Some(answer_mode) = request.answer_mode {
+        quest_data.set_answer_mode(answer_mode);
+    }
     Ok(match quest_service.modify_quest(&id, quest_data).await? {
         true => (
             http::Status::Ok,
@@ -305,21 +586,97 @@ pub async fn modify_quest(
     })
 }
 
+/// Runs the quest's generator for synthetic users, for its author to check it before players
+/// run into its problems.
+#[rocket::post("/quests/<id>/generator-test")]
+pub async fn test_generator(
+    id: QuestId,
+    user: AuthUser,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<Json<GeneratorTestReport>, http::Status>, Error> {
+    let Some(quest) = quest_service.get_quest(&id).await? else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    if !quest.is_author(&user.id) {
+        return Ok(Err(http::Status::Forbidden));
+    }
+    Ok(quest_service
+        .test_generator(&id, GENERATOR_TEST_USERS)
+        .await?
+        .map(Json)
+        .ok_or(http::Status::NotFound))
+}
+
+/// Replaces the quest's generator with the executable in the request body.
+#[rocket::put("/quests/<id>/generator", data = "<generator>")]
+pub async fn upload_generator(
+    id: QuestId,
+    generator: Data<'_>,
+    user: AuthUser,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<http::Status, (http::Status, String)>, Error> {
+    let Some(quest) = quest_service.get_quest(&id).await? else {
+        return Ok(Err((
+            http::Status::NotFound,
+            "Quest doesn't exist.".to_owned(),
+        )));
+    };
+    if !quest.is_author(&user.id) {
+        return Ok(Err((http::Status::Forbidden, String::new())));
+    }
+    // One byte more than allowed, so oversized generators are rejected instead of cut off.
+    let generator = generator
+        .open((MAX_GENERATOR_SIZE + 1).bytes())
+        .into_bytes()
+        .await?;
+    match quest_service
+        .upload_generator(&id, generator.into_inner())
+        .await
+    {
+        Ok(true) => Ok(Ok(http::Status::NoContent)),
+        Ok(false) => Ok(Err((
+            http::Status::NotFound,
+            "Quest doesn't exist.".to_owned(),
+        ))),
+        Err(Error::InvalidQuestPackage(reason)) => Ok(Err((http::Status::BadRequest, reason))),
+        Err(Error::Unsupported) => Ok(Err((
+            http::Status::NotImplemented,
+            "Generators can't be uploaded on this instance.".to_owned(),
+        ))),
+        Err(e) => Err(e),
+    }
+}
+
 #[rocket::get("/quests/<quest_id>/input")]
 pub async fn quest_input(
     quest_id: QuestId,
     user: Option<AuthUser>,
     quest_service: &State<Arc<dyn QuestService>>,
-) -> Result<String, http::Status> {
-    if let Some(user) = user {
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<Result<String, http::Status>, Error> {
+    Ok(if let Some(user) = user {
+        if visible_quest(&quest_id, Some(&user), quest_service)
+            .await?
+            .is_none()
+        {
+            return Ok(Err(http::Status::NotFound));
+        }
         match quest_service.get_input(&quest_id, &user.id).await {
-            Ok(Some(input)) => Ok(input),
+            Ok(Some(input)) => {
+                // Only feeds the solve time statistics, so a failure shouldn't hide the input.
+                if let Err(e) = progression_service.start_quest(&user.id, &quest_id).await {
+                    eprintln!("failed to record start of quest {}: {}", quest_id, e);
+                }
+                Ok(input)
+            }
             Ok(None) => Err(http::Status::NotFound),
+            // Answered with 503 and a `Retry-After` header.
+            Err(e @ Error::QuestContextPending) => return Err(e),
             Err(_) => Err(http::Status::InternalServerError),
         }
     } else {
         Err(http::Status::Unauthorized)
-    }
+    })
 }
 
 #[derive(FromForm)]
@@ -335,10 +692,13 @@ pub async fn quest_answer(
     quest_service: &State<Arc<dyn QuestService>>,
     progression_service: &State<Arc<dyn ProgressionService>>,
 ) -> Result<Result<Template, http::Status>, Error> {
-    let quest = match quest_service.get_quest(&quest_id).await? {
+    let quest = match visible_quest(&quest_id, user.as_ref(), quest_service).await? {
         Some(quest) => quest,
         None => return Ok(Err(http::Status::NotFound)),
     };
+    if quest.answer_mode == AnswerMode::Program {
+        return Ok(Err(http::Status::BadRequest));
+    }
     Ok(if let Some(user) = user {
         match progression_service
             .submit_answer(&user.id, &quest_id, &form.answer)
@@ -364,26 +724,231 @@ pub async fn quest_answer(
     })
 }
 
+#[derive(FromForm)]
+pub(crate) struct ProgramForm<'a> {
+    language: &'a str,
+    source: &'a str,
+}
+
+#[rocket::post("/quests/<quest_id>/programs", data = "<form>")]
+pub async fn submit_program(
+    form: Form<ProgramForm<'_>>,
+    quest_id: QuestId,
+    user: AuthUser,
+    quest_service: &State<Arc<dyn QuestService>>,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<Result<Redirect, http::Status>, Error> {
+    if visible_quest(&quest_id, Some(&user), quest_service)
+        .await?
+        .is_none()
+    {
+        return Ok(Err(http::Status::NotFound));
+    }
+    match progression_service
+        .submit_program(
+            &user.id,
+            &quest_id,
+            form.language,
+            &form.source.replace("\r\n", "\n"),
+        )
+        .await
+    {
+        Ok(Some(submission_id)) => Ok(Ok(Redirect::to(format!("/submissions/{}", submission_id)))),
+        Ok(None) => Ok(Err(http::Status::NotFound)),
+        Err(Error::BadRequest) => Ok(Err(http::Status::BadRequest)),
+        Err(Error::TooManyRequests) => Ok(Err(http::Status::TooManyRequests)),
+        Err(e) => Err(e),
+    }
+}
+
+fn verdict_context(verdict: Option<Verdict>) -> impl Serialize {
+    context! {
+        abbreviation: verdict.map_or("...", |verdict| verdict.abbreviation()),
+        name: verdict.map_or("Waiting to be judged", |verdict| verdict.get_display_name()),
+        accepted: verdict == Some(Verdict::Accepted),
+        pending: verdict.is_none(),
+    }
+}
+
+fn program_submission_context(submission: ProgramSubmission) -> impl Serialize {
+    context! {
+        id: submission.id,
+        language: submission.language,
+        verdict: verdict_context(submission.verdict),
+        submitted_at: submission
+            .submitted_at
+            .format("%B %-d, %Y %H:%M:%S")
+            .to_string(),
+    }
+}
+
+#[rocket::get("/quests/<quest_id>/submissions")]
+pub async fn program_submissions(
+    quest_id: QuestId,
+    user: AuthUser,
+    quest_service: &State<Arc<dyn QuestService>>,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<Result<Template, http::Status>, Error> {
+    let Some(quest) = quest_service.get_quest(&quest_id).await? else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    let submissions = progression_service
+        .list_program_submissions(&user.id, &quest_id)
+        .await?
+        .into_iter()
+        .map(program_submission_context)
+        .collect::<Vec<_>>();
+    Ok(Ok(Template::render(
+        "submissions",
+        PageContext::new(
+            &Some(user),
+            context! {
+                quest: context! {
+                    name: &quest.name,
+                    id: &quest.id,
+                },
+                submissions,
+            },
+        ),
+    )))
+}
+
+/// Users only get to see their own submissions.
+#[rocket::get("/submissions/<id>")]
+pub async fn program_submission(
+    id: SubmissionId,
+    user: AuthUser,
+    quest_service: &State<Arc<dyn QuestService>>,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<Result<Template, http::Status>, Error> {
+    let Some(details) = progression_service.get_program_submission(&id).await? else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    if details.submission.user_id != user.id {
+        return Ok(Err(http::Status::NotFound));
+    }
+    let quest_name = quest_service
+        .get_quest(&details.submission.quest_id)
+        .await?
+        .map(|quest| quest.name);
+    let quest_id = details.submission.quest_id;
+    let pending = details.submission.verdict.is_none();
+    let judge_error = details.submission.verdict == Some(Verdict::JudgeError);
+    Ok(Ok(Template::render(
+        "submission",
+        PageContext::new(
+            &Some(user),
+            context! {
+                quest: context! {
+                    name: quest_name,
+                    id: quest_id,
+                },
+                submission: program_submission_context(details.submission),
+                pending,
+                judge_error,
+                source: details.source,
+                compiler_output: details.compiler_output,
+                tests: details
+                    .tests
+                    .into_iter()
+                    .map(|test| {
+                        context! {
+                            number: test.test_index + 1,
+                            verdict: verdict_context(Some(test.verdict)),
+                            time_ms: test.time_ms,
+                        }
+                    })
+                    .collect::<Vec<_>>(),
+            },
+        ),
+    )))
+}
+
+fn achievement_context(achievement: Achievement) -> impl Serialize {
+    context! {
+        name: achievement.name,
+        description: achievement.description,
+        unlocked: achievement
+            .unlocked_at
+            .map(|unlocked_at| unlocked_at.format("%B %-d, %Y").to_string()),
+    }
+}
+
 #[rocket::get("/account")]
-pub async fn account(user: AuthUser) -> Result<Template, Error> {
+pub async fn account(
+    user: AuthUser,
+    user_service: &State<Arc<dyn UserService>>,
+    statistics_service: &State<Arc<dyn StatisticsService>>,
+) -> Result<Template, Error> {
+    let totp_enabled = user_service.is_totp_enabled(&user.id).await?;
+    let achievements = statistics_service
+        .get_user_achievements(&user.id)
+        .await?
+        .into_iter()
+        .map(achievement_context)
+        .collect::<Vec<_>>();
     Ok(Template::render(
         "account",
-        PageContext::new(&Some(user), context! {}),
+        PageContext::new(
+            &Some(user),
+            context! {
+                totp_enabled,
+                achievements,
+            },
+        ),
     ))
 }
 
+/// How many days the activity charts on the statistics page cover.
+const ACTIVITY_CHART_DAYS: u32 = 30;
+
+/// A bar chart of one value of the daily activity, scaled to the busiest day.
+fn activity_chart(
+    title: &str,
+    activity: &[DailyActivity],
+    value: fn(&DailyActivity) -> i64,
+) -> impl Serialize {
+    let max = activity.iter().map(&value).max().unwrap_or(0).max(1);
+    context! {
+        title,
+        first_day: activity.first().map(|day| day.day.format("%b %-d").to_string()),
+        last_day: activity.last().map(|day| day.day.format("%b %-d").to_string()),
+        bars: activity
+            .iter()
+            .map(|day| {
+                context! {
+                    label: day.day.format("%b %-d").to_string(),
+                    value: value(day),
+                    height: value(day) * 100 / max,
+                }
+            })
+            .collect::<Vec<_>>(),
+    }
+}
+
 #[rocket::get("/account/statistics")]
 pub async fn account_statistics(
     user: AuthUser,
     statistics_service: &State<Arc<dyn StatisticsService>>,
 ) -> Result<Template, Error> {
     let metrics = statistics_service.get_user_metrics(&user.id).await?;
+    let activity = statistics_service
+        .get_user_activity(&user.id, ACTIVITY_CHART_DAYS)
+        .await?;
+    let charts = [
+        activity_chart("Answers submitted per day", &activity, |day| {
+            day.answers_submitted
+        }),
+        activity_chart("Quests completed per day", &activity, |day| {
+            day.quests_completed
+        }),
+    ];
     let statistics = metrics
         .into_iter()
         .map(|metric| {
             context! {
                 name: metric.get_display_name(),
-                value: metric.value,
+                value: metric.get_display_value(),
             }
         })
         .collect::<Vec<_>>();
@@ -393,7 +958,70 @@ pub async fn account_statistics(
             &Some(user),
             context! {
                 statistics,
+                charts,
             },
         ),
     ))
 }
+
+#[rocket::get("/users/<username>")]
+pub async fn profile(
+    username: &str,
+    user: Option<AuthUser>,
+    user_service: &State<Arc<dyn UserService>>,
+    quest_service: &State<Arc<dyn QuestService>>,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+    statistics_service: &State<Arc<dyn StatisticsService>>,
+) -> Result<Result<Template, http::Status>, Error> {
+    let Ok(username) = Username::new(username) else {
+        return Ok(Err(http::Status::NotFound));
+    };
+    let Some(profile) = user_service.get_user_profile(&username).await? else {
+        return Ok(Err(http::Status::NotFound));
+    };
+
+    let authored_quests = quest_service.list_quests_by_author(&profile.id).await?;
+    let completed_quests = progression_service
+        .get_completed_quests(&profile.id)
+        .await?
+        .len();
+    let statistics = statistics_service
+        .get_user_metrics(&profile.id)
+        .await?
+        .into_iter()
+        .map(|metric| {
+            context! {
+                name: metric.get_display_name(),
+                value: metric.get_display_value(),
+            }
+        })
+        .collect::<Vec<_>>();
+    let achievements = statistics_service
+        .get_user_achievements(&profile.id)
+        .await?
+        .into_iter()
+        .filter(|achievement| achievement.unlocked_at.is_some())
+        .map(achievement_context)
+        .collect::<Vec<_>>();
+
+    Ok(Ok(Template::render(
+        "profile",
+        PageContext::new(
+            &user,
+            context! {
+                profile: context! {
+                    username: &profile.username,
+                    joined: profile.created_at.format("%B %-d, %Y").to_string(),
+                    completed_quests,
+                    authored_quests: authored_quests
+                        .iter()
+                        .filter(|quest| !quest.archived)
+                        .map(QuestContext::from)
+                        .collect::<Vec<_>>(),
+                    statistics,
+                    achievements,
+                },
+            },
+        ),
+    )))
+}
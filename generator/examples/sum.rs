@@ -0,0 +1,21 @@
+//! Asks for the sum of a list of numbers.
+//!
+//! Try it with `CODEQUEST_QUEST_ID=00000000-0000-0000-0000-000000000000 cargo run --example sum
+//! -- 00000000-0000-0000-0000-000000000001`.
+
+use codequest_generator::{Output, TextBuilder, rand::Rng};
+
+fn main() {
+    codequest_generator::run(|context| {
+        let mut rng = context.rng();
+        let count = rng.random_range(100..200);
+        let numbers = (0..count)
+            .map(|_| rng.random_range(1..1000))
+            .collect::<Vec<u32>>();
+        let input = TextBuilder::new()
+            .line(numbers.len())
+            .row(&numbers, " ")
+            .build();
+        Output::new(input, numbers.iter().sum::<u32>())
+    })
+}
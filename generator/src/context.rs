@@ -0,0 +1,63 @@
+use std::env;
+
+use uuid::Uuid;
+
+use crate::{Error, QUEST_ID_VAR, QuestRng, SECRET_VAR, rng};
+
+/// What an input is generated for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Context {
+    pub quest_id: Uuid,
+    pub user_id: Uuid,
+    /// The judge's test number, `None` when generating the user's own input.
+    pub test: Option<u32>,
+    secret: String,
+}
+
+impl Context {
+    pub fn new(quest_id: Uuid, user_id: Uuid, secret: impl Into<String>) -> Self {
+        Self {
+            quest_id,
+            user_id,
+            test: None,
+            secret: secret.into(),
+        }
+    }
+
+    pub fn with_test(mut self, test: u32) -> Self {
+        self.test = Some(test);
+        self
+    }
+
+    /// Reads the context the quest-service started the generator with. Without a secret in the
+    /// environment, seeds only depend on the quest, the user and the test.
+    pub fn from_env() -> Result<Self, Error> {
+        let mut args = env::args().skip(1);
+        let user_id = args.next().ok_or(Error::MissingUserId)?;
+        let user_id = Uuid::try_parse(&user_id).map_err(|_| Error::InvalidUserId(user_id))?;
+        let test = args
+            .next()
+            .map(|test| test.parse::<u32>().map_err(|_| Error::InvalidTest(test)))
+            .transpose()?;
+        let quest_id = env::var(QUEST_ID_VAR).map_err(|_| Error::MissingQuestId)?;
+        let quest_id = Uuid::try_parse(&quest_id).map_err(|_| Error::InvalidQuestId(quest_id))?;
+        let secret = env::var(SECRET_VAR).unwrap_or_default();
+
+        Ok(Self {
+            quest_id,
+            user_id,
+            test,
+            secret,
+        })
+    }
+
+    pub(crate) fn secret(&self) -> &str {
+        &self.secret
+    }
+
+    /// A random number generator that produces the same numbers every time it's created for the
+    /// same context, and different ones for any other context.
+    pub fn rng(&self) -> QuestRng {
+        rng::seeded(self)
+    }
+}
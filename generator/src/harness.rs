@@ -0,0 +1,524 @@
+//! Checks built generators before they're uploaded.
+//!
+//! The [`Harness`] runs the generator executable like the quest-service does, for many users, and
+//! reports every [`Problem`] it finds. It's meant for the generator crate's tests:
+//!
+//! ```ignore
+//! use codequest_generator::harness::Harness;
+//!
+//! #[test]
+//! fn generator_is_sound() {
+//!     Harness::new(env!("CARGO_BIN_EXE_my-generator"))
+//!         .with_users(200)
+//!         .run()
+//!         .assert_ok();
+//! }
+//! ```
+
+use std::{
+    collections::HashSet,
+    env, fmt,
+    io::Read,
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use rand::{Rng, SeedableRng};
+use uuid::{Builder, Uuid};
+
+use crate::{Output, QUEST_ID_VAR, QuestRng, SECRET_VAR};
+
+/// How often running generators are checked for having exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+pub struct Harness {
+    program: PathBuf,
+    sandbox: Vec<String>,
+    quest_id: Uuid,
+    secret: String,
+    users: usize,
+    tests: u32,
+    time_limit: Duration,
+    min_unique_inputs: f64,
+}
+
+impl Harness {
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        let mut rng = QuestRng::from_seed([0; 32]);
+        Self {
+            program: program.into(),
+            sandbox: Vec::new(),
+            quest_id: random_uuid(&mut rng),
+            secret: "harness".to_owned(),
+            users: 50,
+            tests: 0,
+            time_limit: Duration::from_secs(1),
+            min_unique_inputs: 0.95,
+        }
+    }
+
+    pub fn with_quest_id(mut self, quest_id: Uuid) -> Self {
+        self.quest_id = quest_id;
+        self
+    }
+
+    pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = secret.into();
+        self
+    }
+
+    /// Runs the generator through this command, e.g. `nsjail` with its options, which gets the
+    /// generator's path and arguments appended.
+    pub fn with_sandbox(mut self, sandbox: Vec<String>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// How many users the generator is run for.
+    pub fn with_users(mut self, users: usize) -> Self {
+        self.users = users;
+        self
+    }
+
+    /// How many judge test cases are generated per user, for program quests.
+    pub fn with_tests(mut self, tests: u32) -> Self {
+        self.tests = tests;
+        self
+    }
+
+    /// Runs taking longer than this are killed.
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = time_limit;
+        self
+    }
+
+    /// How many distinct inputs there have to be, relative to the number of users.
+    pub fn with_min_unique_inputs(mut self, share: f64) -> Self {
+        self.min_unique_inputs = share;
+        self
+    }
+
+    /// Runs the generator twice for every user and test.
+    pub fn run(&self) -> Report {
+        let mut report = Report::default();
+        let mut inputs = HashSet::new();
+        let mut rng = QuestRng::from_seed([1; 32]);
+        for _ in 0..self.users {
+            let user_id = random_uuid(&mut rng);
+            for test in std::iter::once(None).chain((0..self.tests).map(Some)) {
+                let Some(output) = self.check(user_id, test, &mut report) else {
+                    continue;
+                };
+                if test.is_none() {
+                    inputs.insert(output.input);
+                }
+            }
+        }
+
+        report.unique_inputs = inputs.len();
+        let required = (self.users as f64 * self.min_unique_inputs).ceil() as usize;
+        if inputs.len() < required {
+            report.problems.push(Problem::TooFewUniqueInputs {
+                unique: inputs.len(),
+                users: self.users,
+            });
+        }
+        report
+    }
+
+    fn check(&self, user_id: Uuid, test: Option<u32>, report: &mut Report) -> Option<Output> {
+        let mut outputs = Vec::with_capacity(2);
+        for _ in 0..2 {
+            report.runs += 1;
+            match self.run_once(user_id, test) {
+                Ok((output, runtime)) => {
+                    report.slowest = report.slowest.max(runtime);
+                    report.total_runtime += runtime;
+                    report.largest_input = report.largest_input.max(output.input.len());
+                    report.largest_answer = report.largest_answer.max(output.answer.len());
+                    outputs.push(output);
+                }
+                Err(problem) => {
+                    report.problems.push(problem);
+                    return None;
+                }
+            }
+        }
+        let second = outputs.pop()?;
+        let first = outputs.pop()?;
+        if first != second {
+            report
+                .problems
+                .push(Problem::NotDeterministic { user_id, test });
+            return None;
+        }
+        Some(first)
+    }
+
+    fn run_once(&self, user_id: Uuid, test: Option<u32>) -> Result<(Output, Duration), Problem> {
+        let failed = |message: String| Problem::Failed {
+            user_id,
+            test,
+            message,
+        };
+
+        let mut command = match self.sandbox.split_first() {
+            Some((sandbox, args)) => {
+                let mut command = Command::new(sandbox);
+                command.args(args).arg(&self.program);
+                command
+            }
+            None => Command::new(&self.program),
+        };
+        let started_at = Instant::now();
+        // Like the quest-service, generators only get the variables of the protocol.
+        let mut child = command
+            .arg(user_id.to_string())
+            .args(test.map(|test| test.to_string()))
+            .env_clear()
+            .envs(env::var_os("PATH").map(|path| ("PATH", path)))
+            .env(QUEST_ID_VAR, self.quest_id.to_string())
+            .env(SECRET_VAR, &self.secret)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| failed(format!("couldn't start the generator: {}", e)))?;
+
+        // Read on other threads so generators don't block on full pipes.
+        let stdout = read_to_end(child.stdout.take());
+        let stderr = read_to_end(child.stderr.take());
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if started_at.elapsed() > self.time_limit => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(Problem::TimedOut {
+                        user_id,
+                        test,
+                        time_limit: self.time_limit,
+                    });
+                }
+                Ok(None) => thread::sleep(POLL_INTERVAL),
+                Err(e) => return Err(failed(e.to_string())),
+            }
+        };
+        let runtime = started_at.elapsed();
+        let stdout = stdout.join().unwrap_or_default();
+        let stderr = stderr.join().unwrap_or_default();
+
+        if !status.success() {
+            return Err(failed(format!(
+                "{}: {}",
+                status,
+                String::from_utf8_lossy(&stderr).trim()
+            )));
+        }
+        let stdout =
+            String::from_utf8(stdout).map_err(|_| failed("the output isn't UTF-8".to_owned()))?;
+        let (input, answer) = stdout
+            .split_once('\0')
+            .ok_or_else(|| failed("the output has no NUL byte".to_owned()))?;
+        if answer.contains('\0') {
+            return Err(failed("the output has more than one NUL byte".to_owned()));
+        }
+        Ok((Output::new(input, answer), runtime))
+    }
+}
+
+fn read_to_end(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+fn random_uuid(rng: &mut QuestRng) -> Uuid {
+    Builder::from_random_bytes(rng.random()).into_uuid()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    Failed {
+        user_id: Uuid,
+        test: Option<u32>,
+        message: String,
+    },
+    TimedOut {
+        user_id: Uuid,
+        test: Option<u32>,
+        time_limit: Duration,
+    },
+    /// Two runs for the same user and test printed different outputs.
+    NotDeterministic {
+        user_id: Uuid,
+        test: Option<u32>,
+    },
+    TooFewUniqueInputs {
+        unique: usize,
+        users: usize,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let run = |f: &mut fmt::Formatter<'_>, user_id: &Uuid, test: &Option<u32>| match test {
+            Some(test) => write!(f, "user {}, test {}", user_id, test),
+            None => write!(f, "user {}", user_id),
+        };
+        match self {
+            Self::Failed {
+                user_id,
+                test,
+                message,
+            } => {
+                run(f, user_id, test)?;
+                write!(f, ": failed: {}", message)
+            }
+            Self::TimedOut {
+                user_id,
+                test,
+                time_limit,
+            } => {
+                run(f, user_id, test)?;
+                write!(f, ": took longer than {:?}", time_limit)
+            }
+            Self::NotDeterministic { user_id, test } => {
+                run(f, user_id, test)?;
+                write!(f, ": different outputs for the same user")
+            }
+            Self::TooFewUniqueInputs { unique, users } => {
+                write!(f, "only {} distinct inputs for {} users", unique, users)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub runs: usize,
+    pub unique_inputs: usize,
+    pub slowest: Duration,
+    /// The runtime of all successful runs.
+    pub total_runtime: Duration,
+    /// In bytes.
+    pub largest_input: usize,
+    /// In bytes.
+    pub largest_answer: usize,
+    pub problems: Vec<Problem>,
+}
+
+impl Report {
+    /// The mean runtime of the successful runs.
+    pub fn mean_runtime(&self) -> Duration {
+        let failed = self
+            .problems
+            .iter()
+            .filter(|problem| matches!(problem, Problem::Failed { .. } | Problem::TimedOut { .. }))
+            .count();
+        match self.runs.saturating_sub(failed) {
+            0 => Duration::ZERO,
+            successful => self.total_runtime / successful as u32,
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Panics with the report if there were problems.
+    pub fn assert_ok(&self) {
+        assert!(self.is_ok(), "{}", self);
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} runs, {} distinct inputs, runs took {:?} on average and {:?} at most, inputs have up to {} bytes",
+            self.runs,
+            self.unique_inputs,
+            self.mean_runtime(),
+            self.slowest,
+            self.largest_input
+        )?;
+        for problem in &self.problems {
+            writeln!(f, "- {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt, path::Path, sync::OnceLock};
+
+    use super::*;
+
+    /// Generators behaving as their name says, as links to one shell script.
+    const GENERATORS: &str = r#"#!/bin/sh
+case "${0##*/}" in
+    good) printf '%s\000answer %s' "$1" "$2" ;;
+    failing) echo "out of cheese" >&2; exit 3 ;;
+    no-nul) printf '%s' "$1" ;;
+    random) printf '%s\000%s' "$1" "$$" ;;
+    constant) printf 'input\000answer' ;;
+    slow) sleep 5 ;;
+esac
+"#;
+
+    fn generator(name: &str) -> PathBuf {
+        static DIR: OnceLock<PathBuf> = OnceLock::new();
+        let dir = DIR.get_or_init(|| {
+            let dir =
+                std::env::temp_dir().join(format!("codequest-harness-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let script = dir.join("generators.sh");
+            fs::write(&script, GENERATORS).unwrap();
+            fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+            for name in ["good", "failing", "no-nul", "random", "constant", "slow"] {
+                std::os::unix::fs::symlink(&script, dir.join(name)).unwrap();
+            }
+            dir
+        });
+        Path::new(dir).join(name)
+    }
+
+    #[test]
+    fn sound_generators_pass() {
+        let report = Harness::new(generator("good")).with_users(5).run();
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.runs, 10);
+        assert_eq!(report.unique_inputs, 5);
+        assert_eq!(report.largest_input, 36);
+        report.assert_ok();
+    }
+
+    #[test]
+    fn generators_run_through_the_sandbox() {
+        let report = Harness::new(generator("good"))
+            .with_sandbox(vec!["env".to_owned(), "SANDBOXED=1".to_owned()])
+            .with_users(2)
+            .run();
+        assert!(report.is_ok(), "{}", report);
+
+        let report = Harness::new(generator("good"))
+            .with_sandbox(vec!["false".to_owned()])
+            .with_users(1)
+            .run();
+        assert!(matches!(report.problems[0], Problem::Failed { .. }));
+    }
+
+    #[test]
+    fn test_cases_are_run_for_every_user() {
+        let report = Harness::new(generator("good"))
+            .with_users(3)
+            .with_tests(2)
+            .run();
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.runs, 3 * 3 * 2);
+        assert_eq!(report.unique_inputs, 3);
+    }
+
+    #[test]
+    fn failures_are_reported_with_stderr() {
+        let report = Harness::new(generator("failing")).with_users(2).run();
+        assert_eq!(report.runs, 2);
+        let failures = report
+            .problems
+            .iter()
+            .filter(|problem| {
+                matches!(problem, Problem::Failed { test: None, message, .. } if message.contains("out of cheese"))
+            })
+            .count();
+        assert_eq!(failures, 2);
+        assert!(report.problems.contains(&Problem::TooFewUniqueInputs {
+            unique: 0,
+            users: 2
+        }));
+        assert_eq!(report.mean_runtime(), Duration::ZERO);
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn outputs_without_separator_fail() {
+        let report = Harness::new(generator("no-nul")).with_users(1).run();
+        assert!(matches!(
+            &report.problems[0],
+            Problem::Failed { message, .. } if message == "the output has no NUL byte"
+        ));
+    }
+
+    #[test]
+    fn different_outputs_for_the_same_user_are_reported() {
+        let report = Harness::new(generator("random")).with_users(2).run();
+        assert_eq!(report.runs, 4);
+        assert_eq!(
+            report
+                .problems
+                .iter()
+                .filter(|problem| matches!(problem, Problem::NotDeterministic { test: None, .. }))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn shared_inputs_are_reported() {
+        let report = Harness::new(generator("constant")).with_users(4).run();
+        assert_eq!(
+            report.problems,
+            vec![Problem::TooFewUniqueInputs {
+                unique: 1,
+                users: 4
+            }]
+        );
+        let report = Harness::new(generator("constant"))
+            .with_users(4)
+            .with_min_unique_inputs(0.25)
+            .run();
+        assert!(report.is_ok(), "{}", report);
+    }
+
+    #[test]
+    fn slow_generators_are_killed() {
+        let time_limit = Duration::from_millis(100);
+        let started_at = Instant::now();
+        let report = Harness::new(generator("slow"))
+            .with_users(1)
+            .with_min_unique_inputs(0.0)
+            .with_time_limit(time_limit)
+            .run();
+        assert!(started_at.elapsed() < Duration::from_secs(5));
+        assert!(matches!(
+            report.problems.as_slice(),
+            [Problem::TimedOut { time_limit: limit, .. }] if *limit == time_limit
+        ));
+    }
+
+    #[test]
+    fn reports_list_every_problem() {
+        let report = Report {
+            runs: 3,
+            total_runtime: Duration::from_millis(300),
+            problems: vec![Problem::TooFewUniqueInputs {
+                unique: 1,
+                users: 3,
+            }],
+            ..Default::default()
+        };
+        assert_eq!(report.mean_runtime(), Duration::from_millis(100));
+        assert!(
+            report
+                .to_string()
+                .ends_with("- only 1 distinct inputs for 3 users\n")
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Quest generators written in Rust.
+//!
+//! A generator is an executable named after its quest's ID in the quest-service's generator
+//! directory. It's called with the user's ID as its first argument, and with a test number as its
+//! second one when the judge needs a test case for a program quest. The quest's ID and the
+//! generator secret are passed in the [`QUEST_ID_VAR`] and [`SECRET_VAR`] environment variables.
+//! The generator prints the input, a NUL byte and the answer to stdout.
+//!
+//! [`run`] takes care of all of that, generators only turn a [`Context`] into an [`Output`]. The
+//! context's [`Context::rng`] is seeded from the quest, the user, the test and the secret, so
+//! every user gets their own input, and always the same one:
+//!
+//! ```no_run
+//! use codequest_generator::{Output, TextBuilder, rand::Rng};
+//!
+//! fn main() {
+//!     codequest_generator::run(|context| {
+//!         let mut rng = context.rng();
+//!         let numbers = (0..100)
+//!             .map(|_| rng.random_range(1..1000))
+//!             .collect::<Vec<u32>>();
+//!         let input = TextBuilder::new().lines(&numbers).build();
+//!         Output::new(input, numbers.iter().sum::<u32>())
+//!     })
+//! }
+//! ```
+//!
+//! The [`harness`] runs a built generator for many users and checks that it follows the protocol,
+//! is deterministic, hands out different inputs and is fast enough.
+
+use std::{fmt, io};
+
+pub use rand;
+pub use uuid::Uuid;
+
+mod context;
+pub mod harness;
+mod output;
+mod rng;
+
+pub use context::Context;
+pub use output::{Output, TextBuilder, emit};
+pub use rng::QuestRng;
+
+/// The environment variable holding the quest's ID.
+pub const QUEST_ID_VAR: &str = "CODEQUEST_QUEST_ID";
+/// The environment variable holding the secret mixed into every seed, so users can't
+/// reproduce other users' inputs from the generator's source.
+pub const SECRET_VAR: &str = "CODEQUEST_GENERATOR_SECRET";
+
+#[derive(Debug)]
+pub enum Error {
+    MissingUserId,
+    InvalidUserId(String),
+    InvalidTest(String),
+    MissingQuestId,
+    InvalidQuestId(String),
+    /// The input contains the NUL byte separating it from the answer.
+    NulInInput,
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingUserId => write!(f, "missing user ID argument"),
+            Self::InvalidUserId(id) => write!(f, "invalid user ID '{}'", id),
+            Self::InvalidTest(test) => write!(f, "invalid test number '{}'", test),
+            Self::MissingQuestId => write!(f, "{} not set", QUEST_ID_VAR),
+            Self::InvalidQuestId(id) => write!(f, "invalid {} '{}'", QUEST_ID_VAR, id),
+            Self::NulInInput => write!(f, "the input contains a NUL byte"),
+            Self::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// Runs a generator: reads the [`Context`] from the arguments and the environment, and prints
+/// the [`Output`] of `generate` in the generator protocol. Exits with an error message instead
+/// if anything goes wrong.
+pub fn run(generate: impl FnOnce(&Context) -> Output) {
+    let context = match Context::from_env() {
+        Ok(context) => context,
+        Err(e) => {
+            eprintln!("generator: {}", e);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = emit(&generate(&context)) {
+        eprintln!("generator: {}", e);
+        std::process::exit(1);
+    }
+}
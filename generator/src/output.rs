@@ -0,0 +1,84 @@
+use std::{
+    fmt::{Display, Write as _},
+    io::{self, Write as _},
+};
+
+use crate::Error;
+
+/// A generated input and its answer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Output {
+    pub input: String,
+    /// Compared to the users' answers as-is, so it shouldn't end with a newline.
+    pub answer: String,
+}
+
+impl Output {
+    pub fn new(input: impl Into<String>, answer: impl Display) -> Self {
+        Self {
+            input: input.into(),
+            answer: answer.to_string(),
+        }
+    }
+}
+
+/// Prints the output in the generator protocol: the input, a NUL byte and the answer.
+pub fn emit(output: &Output) -> Result<(), Error> {
+    if output.input.contains('\0') {
+        return Err(Error::NulInInput);
+    }
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(output.input.as_bytes())?;
+    stdout.write_all(b"\0")?;
+    stdout.write_all(output.answer.as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Builds line-based text, like most inputs are.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextBuilder {
+    text: String,
+}
+
+impl TextBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn line(mut self, line: impl Display) -> Self {
+        writeln!(self.text, "{}", line).expect("writing to a String can't fail");
+        self
+    }
+
+    /// One line per item.
+    pub fn lines<T: Display>(self, lines: impl IntoIterator<Item = T>) -> Self {
+        lines.into_iter().fold(self, Self::line)
+    }
+
+    /// One line with the items separated by `separator`.
+    pub fn row<T: Display>(mut self, items: impl IntoIterator<Item = T>, separator: &str) -> Self {
+        for (i, item) in items.into_iter().enumerate() {
+            if i > 0 {
+                self.text.push_str(separator);
+            }
+            write!(self.text, "{}", item).expect("writing to a String can't fail");
+        }
+        self.text.push('\n');
+        self
+    }
+
+    /// One [`Self::row`] per row.
+    pub fn grid<R, T>(self, rows: impl IntoIterator<Item = R>, separator: &str) -> Self
+    where
+        R: IntoIterator<Item = T>,
+        T: Display,
+    {
+        rows.into_iter()
+            .fold(self, |builder, row| builder.row(row, separator))
+    }
+
+    pub fn build(self) -> String {
+        self.text
+    }
+}
@@ -0,0 +1,30 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha256};
+
+use crate::Context;
+
+/// The generator's random number generator. ChaCha20 produces the same numbers on every platform
+/// and with every version of `rand_chacha`, so inputs don't change when generators are rebuilt.
+pub type QuestRng = ChaCha20Rng;
+
+/// Bumping it changes every input, it mustn't be touched once quests are live.
+const DOMAIN: &[u8] = b"codequest-generator/v1";
+
+pub(crate) fn seeded(context: &Context) -> QuestRng {
+    let mut hasher = Sha256::new();
+    hasher.update(DOMAIN);
+    hasher.update(context.quest_id.as_bytes());
+    hasher.update(context.user_id.as_bytes());
+    // Length-prefixed so the secret and the test number can't run into each other.
+    hasher.update((context.secret().len() as u64).to_le_bytes());
+    hasher.update(context.secret().as_bytes());
+    match context.test {
+        Some(test) => {
+            hasher.update([1]);
+            hasher.update(test.to_le_bytes());
+        }
+        None => hasher.update([0]),
+    }
+    QuestRng::from_seed(hasher.finalize().into())
+}
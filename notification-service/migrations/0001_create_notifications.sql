@@ -0,0 +1,21 @@
+CREATE TYPE notification_kind AS ENUM ('quest_published', 'quest_completed', 'quest_commented', 'comment_replied');
+
+CREATE TABLE notifications (
+    id          UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id     UUID NOT NULL,
+    kind        notification_kind NOT NULL,
+    quest_id    UUID NOT NULL,
+    actor       UUID,
+    comment_id  UUID,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    read        BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX notifications_user_id_idx ON notifications (user_id, created_at DESC);
+CREATE INDEX notifications_unread_idx ON notifications (user_id) WHERE NOT read;
+CREATE INDEX notifications_quest_id_idx ON notifications (quest_id);
+
+-- The users new quests are announced to.
+CREATE TABLE users (
+    user_id  UUID PRIMARY KEY
+);
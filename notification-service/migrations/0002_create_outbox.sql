@@ -0,0 +1,12 @@
+CREATE TABLE outbox (
+    id              BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    event_id        UUID NOT NULL DEFAULT uuidv7(),
+    subject         TEXT NOT NULL,
+    payload         TEXT NOT NULL,
+    schema_version  INTEGER NOT NULL DEFAULT 1,
+    correlation_id  UUID,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT now(),
+    sent_at         TIMESTAMPTZ
+);
+
+CREATE INDEX outbox_unsent_idx ON outbox (id) WHERE sent_at IS NULL;
@@ -0,0 +1,7 @@
+ALTER TYPE notification_kind ADD VALUE 'quest_approved';
+
+-- Whether each quest is official, so approvals can be told apart from other changes.
+CREATE TABLE quests (
+    quest_id  UUID PRIMARY KEY,
+    official  BOOLEAN NOT NULL
+);
@@ -0,0 +1,795 @@
+use std::{sync::Arc, time::Duration};
+
+use codequest_common::{
+    Credentials, Error, Quest, QuestId, UserId,
+    comment::CommentId,
+    event::{
+        CommentEvent, ErasureEvent, NotificationEvent, ProgressionEvent, QuestEvent, UserEvent,
+    },
+    nats::{ConsumerHealth, NatsClient},
+    notification::{Notification, NotificationId, NotificationKind},
+    outbox::{self, OutboxRelay},
+    services::{NotificationService, QuestService},
+};
+use reqwest::{Client, StatusCode};
+use rocket::{
+    async_trait,
+    tokio::sync::{mpsc, oneshot},
+};
+use sqlx::{PgConnection, PgPool, postgres::PgPoolOptions};
+use uuid::Uuid;
+
+/// How long processed event IDs are remembered. Longer than the streams keep their events, so
+/// anything JetStream can still redeliver is recognised.
+const PROCESSED_EVENT_RETENTION: Duration = Duration::from_secs(60 * 60 * 24 * 31);
+/// Read notifications are deleted after this long.
+const READ_NOTIFICATION_RETENTION: Duration = Duration::from_secs(60 * 60 * 24 * 90);
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+const NOTIFICATION_COLUMNS: &str =
+    "id, user_id, kind, quest_id, actor, comment_id, created_at, read";
+
+/// Records that the event with `event_id` is being handled in the transaction `conn` belongs to.
+/// Returns `false` if it was handled before, in which case the caller must skip it.
+async fn mark_processed(conn: &mut PgConnection, event_id: Uuid) -> Result<bool, Error> {
+    let result =
+        sqlx::query("INSERT INTO processed_events (event_id) VALUES ($1) ON CONFLICT DO NOTHING")
+            .bind(event_id)
+            .execute(conn)
+            .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// New quests are announced to the users known from their sign-up or their progress.
+async fn remember_user(user_id: &UserId, conn: &mut PgConnection) -> Result<(), Error> {
+    sqlx::query("INSERT INTO users (user_id) VALUES ($1) ON CONFLICT DO NOTHING")
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Stores a notification and publishes it, so it's also pushed to the user live.
+async fn notify(
+    user_id: &UserId,
+    kind: NotificationKind,
+    quest_id: &QuestId,
+    actor: Option<&UserId>,
+    comment_id: Option<&CommentId>,
+    correlation_id: Uuid,
+    conn: &mut PgConnection,
+) -> Result<(), Error> {
+    let notification = sqlx::query_as::<_, Notification>(&format!(
+        "INSERT INTO notifications (user_id, kind, quest_id, actor, comment_id)
+            VALUES ($1, $2, $3, $4, $5) RETURNING {}",
+        NOTIFICATION_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(kind)
+    .bind(quest_id)
+    .bind(actor)
+    .bind(comment_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    outbox::enqueue_correlated(
+        &mut *conn,
+        &NotificationEvent::Created(notification),
+        correlation_id,
+    )
+    .await
+}
+
+/// Announces a new quest to every known user but its author, if it has one.
+async fn announce_quest(
+    quest_id: &QuestId,
+    author: Option<&UserId>,
+    correlation_id: Uuid,
+    conn: &mut PgConnection,
+) -> Result<(), Error> {
+    let notifications = sqlx::query_as::<_, Notification>(&format!(
+        "INSERT INTO notifications (user_id, kind, quest_id, actor)
+            SELECT user_id, $1, $2, $3 FROM users WHERE (user_id IS DISTINCT FROM $3)
+            RETURNING {}",
+        NOTIFICATION_COLUMNS
+    ))
+    .bind(NotificationKind::QuestPublished)
+    .bind(quest_id)
+    .bind(author)
+    .fetch_all(&mut *conn)
+    .await?;
+    for notification in notifications {
+        outbox::enqueue_correlated(
+            &mut *conn,
+            &NotificationEvent::Created(notification),
+            correlation_id,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Remembers whether the quest is official. Returns whether it was before, `None` if the quest
+/// wasn't known.
+async fn remember_quest(quest: &Quest, conn: &mut PgConnection) -> Result<Option<bool>, Error> {
+    let was_official =
+        sqlx::query_scalar::<_, bool>("SELECT official FROM quests WHERE (quest_id = $1)")
+            .bind(quest.id)
+            .fetch_optional(&mut *conn)
+            .await?;
+    sqlx::query(
+        "INSERT INTO quests (quest_id, official) VALUES ($1, $2)
+            ON CONFLICT (quest_id) DO UPDATE SET official = EXCLUDED.official",
+    )
+    .bind(quest.id)
+    .bind(quest.official)
+    .execute(&mut *conn)
+    .await?;
+    Ok(was_official)
+}
+
+async fn erase_user(user_id: &UserId, conn: &mut PgConnection) -> Result<(), Error> {
+    sqlx::query("DELETE FROM notifications WHERE (user_id = $1)")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("UPDATE notifications SET actor = NULL WHERE (actor = $1)")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query("DELETE FROM users WHERE (user_id = $1)")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// An event whose notifications go to the author of a quest, or depend on it.
+enum QuestJob {
+    Published {
+        quest_id: QuestId,
+    },
+    Modified {
+        quest_id: QuestId,
+    },
+    Completed {
+        quest_id: QuestId,
+        user_id: UserId,
+    },
+    Commented {
+        quest_id: QuestId,
+        comment_id: CommentId,
+        author: UserId,
+        parent_author: Option<UserId>,
+    },
+}
+
+impl QuestJob {
+    fn quest_id(&self) -> &QuestId {
+        match self {
+            Self::Published { quest_id }
+            | Self::Modified { quest_id }
+            | Self::Completed { quest_id, .. }
+            | Self::Commented { quest_id, .. } => quest_id,
+        }
+    }
+
+    /// Creates the job's notifications for `quest`, as it is now.
+    async fn notify(
+        &self,
+        quest: &Quest,
+        correlation_id: Uuid,
+        conn: &mut PgConnection,
+    ) -> Result<(), Error> {
+        let quest_author = quest.author;
+        match self {
+            Self::Published { quest_id } => {
+                remember_quest(quest, &mut *conn).await?;
+                announce_quest(quest_id, quest_author.as_ref(), correlation_id, conn).await?;
+            }
+            Self::Modified { quest_id } => {
+                // Quests from before their state was remembered can't be told apart.
+                let was_official = remember_quest(quest, &mut *conn).await?;
+                if let Some(quest_author) = quest_author
+                    && quest.official
+                    && was_official == Some(false)
+                {
+                    notify(
+                        &quest_author,
+                        NotificationKind::QuestApproved,
+                        quest_id,
+                        None,
+                        None,
+                        correlation_id,
+                        conn,
+                    )
+                    .await?;
+                }
+            }
+            Self::Completed { quest_id, user_id } => {
+                if let Some(quest_author) = quest_author.filter(|author| author != user_id) {
+                    notify(
+                        &quest_author,
+                        NotificationKind::QuestCompleted,
+                        quest_id,
+                        Some(user_id),
+                        None,
+                        correlation_id,
+                        conn,
+                    )
+                    .await?;
+                }
+            }
+            Self::Commented {
+                quest_id,
+                comment_id,
+                author,
+                parent_author,
+            } => {
+                let parent_author = parent_author.filter(|parent_author| parent_author != author);
+                if let Some(parent_author) = &parent_author {
+                    notify(
+                        parent_author,
+                        NotificationKind::CommentReplied,
+                        quest_id,
+                        Some(author),
+                        Some(comment_id),
+                        correlation_id,
+                        &mut *conn,
+                    )
+                    .await?;
+                }
+                // Replies to the quest's author only notify them once.
+                if let Some(quest_author) = quest_author
+                    .filter(|quest_author| quest_author != author)
+                    .filter(|quest_author| parent_author.as_ref() != Some(quest_author))
+                {
+                    notify(
+                        &quest_author,
+                        NotificationKind::QuestCommented,
+                        quest_id,
+                        Some(author),
+                        Some(comment_id),
+                        correlation_id,
+                        conn,
+                    )
+                    .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct PendingJob {
+    job: QuestJob,
+    event_id: Uuid,
+    correlation_id: Uuid,
+    result_tx: oneshot::Sender<Result<(), Error>>,
+}
+
+/// Hands `job` to the task looking up quest authors and waits for the result, so failed events
+/// are still retried.
+async fn submit(
+    jobs: &mpsc::UnboundedSender<PendingJob>,
+    job: QuestJob,
+    event_id: Uuid,
+    correlation_id: Uuid,
+) -> Result<(), Error> {
+    let (result_tx, result_rx) = oneshot::channel();
+    jobs.send(PendingJob {
+        job,
+        event_id,
+        correlation_id,
+        result_tx,
+    })
+    .map_err(|_| Error::IncoherentState)?;
+    result_rx.await.map_err(|_| Error::IncoherentState)?
+}
+
+pub struct DatabaseNotificationService {
+    pool: PgPool,
+    consumer_health: ConsumerHealth,
+}
+
+impl DatabaseNotificationService {
+    pub async fn new<S: AsRef<str>>(
+        address: S,
+        db_name: S,
+        credentials: Credentials,
+        nats_address: impl AsRef<str>,
+        quest_service: Arc<dyn QuestService>,
+    ) -> Result<Self, Error> {
+        let nats_address = nats_address.as_ref();
+
+        let pool = connect(address, db_name, credentials).await?;
+
+        let consumer_health = ConsumerHealth::new();
+
+        // Jobs needing a quest's author are handled by a separate task because the quest
+        // service can't be called from a consumer handler directly.
+        let (jobs, mut job_rx) = mpsc::unbounded_channel::<PendingJob>();
+        let _join_handle = {
+            let pool = pool.clone();
+            rocket::tokio::spawn(async move {
+                println!("notification worker started");
+                while let Some(pending) = job_rx.recv().await {
+                    let result = async {
+                        let Some(quest) = quest_service.get_quest(pending.job.quest_id()).await?
+                        else {
+                            // The quest is gone already.
+                            return Ok(());
+                        };
+                        let mut tx = pool.begin().await?;
+                        if mark_processed(&mut tx, pending.event_id).await? {
+                            pending
+                                .job
+                                .notify(&quest, pending.correlation_id, &mut tx)
+                                .await?;
+                            tx.commit().await?;
+                        }
+                        Ok(())
+                    }
+                    .await;
+                    // The consumer gave up waiting if the receiver is gone.
+                    let _ = pending.result_tx.send(result);
+                }
+            })
+        };
+
+        let _join_handle = {
+            let pool = pool.clone();
+            let jobs = jobs.clone();
+            let nats_client = NatsClient::new(nats_address).await?;
+            let consumer_health = consumer_health.clone();
+            rocket::tokio::spawn(async move {
+                println!("NATS event worker started: QuestEvents");
+                nats_client
+                    .consume::<QuestEvent>(
+                        "QUEST_EVENTS",
+                        "notification-service".to_owned(),
+                        consumer_health,
+                        async move |envelope| match envelope.event {
+                            QuestEvent::Created(quest_id) => {
+                                submit(
+                                    &jobs,
+                                    QuestJob::Published { quest_id },
+                                    envelope.id,
+                                    envelope.correlation_id(),
+                                )
+                                .await
+                            }
+                            QuestEvent::Modified(quest_id) => {
+                                submit(
+                                    &jobs,
+                                    QuestJob::Modified { quest_id },
+                                    envelope.id,
+                                    envelope.correlation_id(),
+                                )
+                                .await
+                            }
+                            QuestEvent::Deleted(quest_id) => {
+                                let mut tx = pool.begin().await?;
+                                sqlx::query("DELETE FROM notifications WHERE (quest_id = $1)")
+                                    .bind(quest_id)
+                                    .execute(&mut *tx)
+                                    .await?;
+                                sqlx::query("DELETE FROM quests WHERE (quest_id = $1)")
+                                    .bind(quest_id)
+                                    .execute(&mut *tx)
+                                    .await?;
+                                tx.commit().await?;
+                                Ok(())
+                            }
+                        },
+                    )
+                    .await;
+            })
+        };
+
+        let _join_handle = {
+            let pool = pool.clone();
+            let jobs = jobs.clone();
+            let nats_client = NatsClient::new(nats_address).await?;
+            let consumer_health = consumer_health.clone();
+            rocket::tokio::spawn(async move {
+                println!("NATS event worker started: ProgressionEvents");
+                nats_client
+                    .consume::<ProgressionEvent>(
+                        "PROGRESSION_EVENTS",
+                        "notification-service".to_owned(),
+                        consumer_health,
+                        async move |envelope| {
+                            let user_id = match &envelope.event {
+                                ProgressionEvent::QuestStarted { user_id, .. }
+                                | ProgressionEvent::AnswerSubmitted { user_id, .. }
+                                | ProgressionEvent::QuestCompleted { user_id, .. }
+                                | ProgressionEvent::HintRevealed { user_id, .. } => *user_id,
+                            };
+                            let mut conn = pool.acquire().await?;
+                            remember_user(&user_id, &mut conn).await?;
+                            if let ProgressionEvent::QuestCompleted { user_id, quest_id } =
+                                envelope.event
+                            {
+                                submit(
+                                    &jobs,
+                                    QuestJob::Completed { quest_id, user_id },
+                                    envelope.id,
+                                    envelope.correlation_id(),
+                                )
+                                .await?;
+                            }
+                            Ok(())
+                        },
+                    )
+                    .await;
+            })
+        };
+
+        let _join_handle = {
+            let pool = pool.clone();
+            let nats_client = NatsClient::new(nats_address).await?;
+            let consumer_health = consumer_health.clone();
+            rocket::tokio::spawn(async move {
+                println!("NATS event worker started: CommentEvents");
+                nats_client
+                    .consume::<CommentEvent>(
+                        "COMMENT_EVENTS",
+                        "notification-service".to_owned(),
+                        consumer_health,
+                        async move |envelope| match envelope.event {
+                            CommentEvent::Posted {
+                                comment_id,
+                                quest_id,
+                                author,
+                                parent_author,
+                                ..
+                            } => {
+                                submit(
+                                    &jobs,
+                                    QuestJob::Commented {
+                                        quest_id,
+                                        comment_id,
+                                        author,
+                                        parent_author,
+                                    },
+                                    envelope.id,
+                                    envelope.correlation_id(),
+                                )
+                                .await
+                            }
+                            // Notifications don't point to comments that are gone.
+                            CommentEvent::Deleted { comment_id, .. }
+                            | CommentEvent::Hidden {
+                                comment_id,
+                                hidden: true,
+                                ..
+                            } => {
+                                sqlx::query("DELETE FROM notifications WHERE (comment_id = $1)")
+                                    .bind(comment_id)
+                                    .execute(&pool)
+                                    .await?;
+                                Ok(())
+                            }
+                            CommentEvent::Edited { .. }
+                            | CommentEvent::Reported { .. }
+                            | CommentEvent::Hidden { hidden: false, .. } => Ok(()),
+                        },
+                    )
+                    .await;
+            })
+        };
+
+        let _join_handle = {
+            let pool = pool.clone();
+            let nats_client = NatsClient::new(nats_address).await?;
+            let consumer_health = consumer_health.clone();
+            rocket::tokio::spawn(async move {
+                println!("NATS event worker started: UserEvents");
+                nats_client
+                    .consume::<UserEvent>(
+                        "USER_EVENTS",
+                        "notification-service".to_owned(),
+                        consumer_health,
+                        async move |envelope| {
+                            let correlation_id = envelope.correlation_id();
+                            match envelope.event {
+                                UserEvent::Created(user_id) => {
+                                    let mut conn = pool.acquire().await?;
+                                    remember_user(&user_id, &mut conn).await?;
+                                }
+                                UserEvent::Deleted(user_id) => {
+                                    let mut tx = pool.begin().await?;
+                                    if !mark_processed(&mut tx, envelope.id).await? {
+                                        return Ok(());
+                                    }
+                                    erase_user(&user_id, &mut tx).await?;
+                                    outbox::enqueue_correlated(
+                                        &mut *tx,
+                                        &ErasureEvent::Completed {
+                                            user_id,
+                                            service: "notification-service".to_owned(),
+                                        },
+                                        correlation_id,
+                                    )
+                                    .await?;
+                                    tx.commit().await?;
+                                }
+                                UserEvent::LockedOut { .. } | UserEvent::Renamed { .. } => (),
+                            }
+                            Ok(())
+                        },
+                    )
+                    .await;
+            })
+        };
+
+        let _join_handle = {
+            let pool = pool.clone();
+            rocket::tokio::spawn(async move {
+                loop {
+                    if let Err(e) = sqlx::query(
+                        "DELETE FROM processed_events WHERE processed_at < now() - make_interval(secs => $1)",
+                    )
+                    .bind(PROCESSED_EVENT_RETENTION.as_secs_f64())
+                    .execute(&pool)
+                    .await
+                    {
+                        eprintln!("failed to purge processed events: {}", e);
+                    }
+                    if let Err(e) = sqlx::query(
+                        "DELETE FROM notifications WHERE read AND created_at < now() - make_interval(secs => $1)",
+                    )
+                    .bind(READ_NOTIFICATION_RETENTION.as_secs_f64())
+                    .execute(&pool)
+                    .await
+                    {
+                        eprintln!("failed to purge read notifications: {}", e);
+                    }
+                    rocket::tokio::time::sleep(PURGE_INTERVAL).await;
+                }
+            })
+        };
+
+        OutboxRelay::new(pool.clone(), nats_address, "notification-service").spawn();
+
+        Ok(Self {
+            pool,
+            consumer_health,
+        })
+    }
+
+    /// The state of the service's NATS consumers, for its `/health` route.
+    pub fn consumer_health(&self) -> ConsumerHealth {
+        self.consumer_health.clone()
+    }
+}
+
+async fn connect<S: AsRef<str>>(
+    address: S,
+    db_name: S,
+    credentials: Credentials,
+) -> Result<PgPool, Error> {
+    let pool = PgPoolOptions::new()
+        .max_connections(20)
+        .connect(
+            format!(
+                "postgres://{}:{}@{}/{}",
+                credentials.username,
+                credentials.password,
+                address.as_ref(),
+                db_name.as_ref()
+            )
+            .as_str(),
+        )
+        .await?;
+
+    sqlx::migrate!().run(&pool).await?;
+
+    Ok(pool)
+}
+
+#[async_trait]
+impl NotificationService for DatabaseNotificationService {
+    async fn list_notifications(
+        &self,
+        user_id: &UserId,
+        limit: u32,
+    ) -> Result<Box<[Notification]>, Error> {
+        let notifications = sqlx::query_as::<_, Notification>(&format!(
+            "SELECT {} FROM notifications WHERE (user_id = $1) ORDER BY created_at DESC LIMIT $2",
+            NOTIFICATION_COLUMNS
+        ))
+        .bind(user_id)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(notifications.into_boxed_slice())
+    }
+
+    async fn export_notifications(&self, user_id: &UserId) -> Result<Box<[Notification]>, Error> {
+        let notifications = sqlx::query_as::<_, Notification>(&format!(
+            "SELECT {} FROM notifications WHERE (user_id = $1) ORDER BY created_at DESC",
+            NOTIFICATION_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(notifications.into_boxed_slice())
+    }
+
+    async fn count_unread(&self, user_id: &UserId) -> Result<u64, Error> {
+        let count = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM notifications WHERE (user_id = $1 AND NOT read)",
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count as u64)
+    }
+
+    async fn mark_read(
+        &self,
+        user_id: &UserId,
+        id: &NotificationId,
+    ) -> Result<Option<Notification>, Error> {
+        let mut tx = self.pool.begin().await?;
+        let Some(mut notification) = sqlx::query_as::<_, Notification>(&format!(
+            "SELECT {} FROM notifications WHERE (id = $1 AND user_id = $2) FOR UPDATE",
+            NOTIFICATION_COLUMNS
+        ))
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(None);
+        };
+        if !notification.read {
+            sqlx::query("UPDATE notifications SET read = TRUE WHERE (id = $1)")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            outbox::enqueue(
+                &mut *tx,
+                &NotificationEvent::Read {
+                    user_id: *user_id,
+                    notification_id: Some(*id),
+                },
+            )
+            .await?;
+            notification.read = true;
+        }
+        tx.commit().await?;
+        Ok(Some(notification))
+    }
+
+    async fn mark_all_read(&self, user_id: &UserId) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+        let res =
+            sqlx::query("UPDATE notifications SET read = TRUE WHERE (user_id = $1 AND NOT read)")
+                .bind(user_id)
+                .execute(&mut *tx)
+                .await?;
+        if res.rows_affected() > 0 {
+            outbox::enqueue(
+                &mut *tx,
+                &NotificationEvent::Read {
+                    user_id: *user_id,
+                    notification_id: None,
+                },
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+pub struct BackendNotificationService {
+    address: String,
+    client: Client,
+}
+
+impl BackendNotificationService {
+    pub fn new<S: AsRef<str>>(address: S) -> Self {
+        Self {
+            address: address.as_ref().to_owned(),
+            client: Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationService for BackendNotificationService {
+    async fn list_notifications(
+        &self,
+        user_id: &UserId,
+        limit: u32,
+    ) -> Result<Box<[Notification]>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}?limit={}", &self.address, user_id, limit))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Box<[Notification]>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn export_notifications(&self, user_id: &UserId) -> Result<Box<[Notification]>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}/export", &self.address, user_id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Box<[Notification]>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn count_unread(&self, user_id: &UserId) -> Result<u64, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}/unread", &self.address, user_id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<u64>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn mark_read(
+        &self,
+        user_id: &UserId,
+        id: &NotificationId,
+    ) -> Result<Option<Notification>, Error> {
+        let response = self
+            .client
+            .post(format!("{}/{}/{}/read", &self.address, user_id, id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Notification>()
+                .await
+                .map(Some)
+                .map_err(|_| Error::InvalidResponse),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn mark_all_read(&self, user_id: &UserId) -> Result<(), Error> {
+        let response = self
+            .client
+            .post(format!("{}/{}/read", &self.address, user_id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(()),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+}
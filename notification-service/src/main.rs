@@ -0,0 +1,153 @@
+use std::{env, sync::Arc};
+
+use codequest_common::{
+    Credentials, Error, UserId, load_secret_key,
+    nats::ConsumerHealth,
+    notification::{Notification, NotificationId},
+    services::NotificationService,
+};
+use codequest_notification_service::DatabaseNotificationService;
+use codequest_quest_service::BackendQuestService;
+use dotenv::dotenv;
+use rocket::{State, catchers, response::status, routes, serde::json::Json};
+
+mod defaults {
+    pub const SECRET_KEY_FILE: &str = "./secrets/secret_key";
+    pub const PORT: u16 = 8000;
+    pub const LIMIT: u32 = 50;
+    pub const MAX_LIMIT: u32 = 200;
+}
+
+#[rocket::get("/<user_id>?<limit>")]
+async fn list_notifications(
+    user_id: UserId,
+    limit: Option<u32>,
+    notification_service: &State<Arc<dyn NotificationService>>,
+) -> Result<Json<Box<[Notification]>>, Error> {
+    let limit = limit
+        .unwrap_or(defaults::LIMIT)
+        .clamp(1, defaults::MAX_LIMIT);
+    notification_service
+        .list_notifications(&user_id, limit)
+        .await
+        .map(Json)
+}
+
+#[rocket::get("/<user_id>/export")]
+async fn export_notifications(
+    user_id: UserId,
+    notification_service: &State<Arc<dyn NotificationService>>,
+) -> Result<Json<Box<[Notification]>>, Error> {
+    notification_service
+        .export_notifications(&user_id)
+        .await
+        .map(Json)
+}
+
+#[rocket::get("/<user_id>/unread")]
+async fn count_unread(
+    user_id: UserId,
+    notification_service: &State<Arc<dyn NotificationService>>,
+) -> Result<Json<u64>, Error> {
+    notification_service.count_unread(&user_id).await.map(Json)
+}
+
+#[rocket::post("/<user_id>/<id>/read")]
+async fn mark_read(
+    user_id: UserId,
+    id: NotificationId,
+    notification_service: &State<Arc<dyn NotificationService>>,
+) -> Result<Result<Json<Notification>, status::NotFound<()>>, Error> {
+    Ok(notification_service
+        .mark_read(&user_id, &id)
+        .await?
+        .map(Json)
+        .ok_or(status::NotFound(())))
+}
+
+#[rocket::post("/<user_id>/read")]
+async fn mark_all_read(
+    user_id: UserId,
+    notification_service: &State<Arc<dyn NotificationService>>,
+) -> Result<status::NoContent, Error> {
+    notification_service.mark_all_read(&user_id).await?;
+    Ok(status::NoContent)
+}
+
+#[rocket::get("/health")]
+fn health(consumer_health: &State<ConsumerHealth>) -> ConsumerHealth {
+    consumer_health.inner().clone()
+}
+
+#[rocket::catch(default)]
+fn catch_all() -> &'static str {
+    ""
+}
+
+#[rocket::main]
+async fn main() -> Result<(), rocket::Error> {
+    dotenv().ok();
+
+    let db_credentials = {
+        let username = env::var("DB_USERNAME_NOTIFICATION_SERVICE")
+            .expect("DB_USERNAME_NOTIFICATION_SERVICE not set");
+        let password = env::var("DB_PASSWORD_NOTIFICATION_SERVICE")
+            .expect("DB_PASSWORD_NOTIFICATION_SERVICE not set");
+        Credentials::new(username, password)
+    };
+    let db_name = env::var("POSTGRES_DB").expect("POSTGRES_DB not set");
+    let db_address = env::var("DB_ADDRESS").expect("DB_ADDRESS not set");
+
+    let nats_address = env::var("NATS_ADDRESS").expect("NATS_ADDRESS not set");
+
+    let secret_key = load_secret_key(
+        env::var("SECRET_KEY_FILE").unwrap_or_else(|_| defaults::SECRET_KEY_FILE.to_owned()),
+    )
+    .expect("failed to load secret key");
+
+    let port = env::var("NOTIFICATION_SERVICE_PORT")
+        .map(|port| {
+            port.parse::<u16>()
+                .unwrap_or_else(|_| panic!("invalid NOTIFICATION_SERVICE_PORT: '{}'", port))
+        })
+        .unwrap_or(defaults::PORT);
+
+    let rocket_config = rocket::Config::figment()
+        .merge(("secret_key", secret_key))
+        .merge(("port", port));
+
+    let quest_service_address =
+        env::var("QUEST_SERVICE_ADDRESS").expect("QUEST_SERVICE_ADDRESS not set");
+
+    let quest_service = BackendQuestService::new(quest_service_address);
+
+    let notification_service = DatabaseNotificationService::new(
+        &db_address,
+        &db_name,
+        db_credentials,
+        nats_address,
+        Arc::new(quest_service),
+    )
+    .await
+    .expect("failed to start DatabaseNotificationService");
+
+    rocket::custom(&rocket_config)
+        .register("/", catchers![catch_all])
+        .mount(
+            "/notifications",
+            routes![
+                list_notifications,
+                export_notifications,
+                count_unread,
+                mark_read,
+                mark_all_read
+            ],
+        )
+        .mount("/", routes![health])
+        .manage(notification_service.consumer_health())
+        .manage(Arc::new(notification_service) as Arc<dyn NotificationService>)
+        .launch()
+        .await?;
+
+    Ok(())
+}
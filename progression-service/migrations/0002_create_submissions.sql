@@ -0,0 +1,10 @@
+CREATE TABLE submissions (
+    id            UUID PRIMARY KEY DEFAULT uuidv7(),
+    user_id       UUID NOT NULL,
+    quest_id      UUID NOT NULL,
+    answer        TEXT NOT NULL,
+    correct       BOOLEAN NOT NULL,
+    submitted_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX submissions_user_id_idx ON submissions (user_id);
@@ -0,0 +1,6 @@
+CREATE TABLE quest_starts (
+    user_id     UUID NOT NULL,
+    quest_id    UUID NOT NULL,
+    started_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, quest_id)
+);
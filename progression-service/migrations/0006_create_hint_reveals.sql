@@ -0,0 +1,8 @@
+CREATE TABLE hint_reveals (
+    user_id      UUID NOT NULL,
+    quest_id     UUID NOT NULL,
+    hint_index   INTEGER NOT NULL,
+    penalty      INTEGER NOT NULL,
+    revealed_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, quest_id, hint_index)
+);
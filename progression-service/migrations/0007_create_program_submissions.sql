@@ -0,0 +1,25 @@
+CREATE TYPE verdict AS ENUM ('AC', 'WA', 'TLE', 'RE', 'CE');
+
+CREATE TABLE program_submissions (
+    id                  UUID PRIMARY KEY DEFAULT uuidv7(),
+    user_id             UUID NOT NULL,
+    quest_id            UUID NOT NULL,
+    language            TEXT NOT NULL,
+    source              TEXT NOT NULL,
+    verdict             verdict,
+    compiler_output     TEXT,
+    submitted_at        TIMESTAMPTZ NOT NULL DEFAULT now(),
+    judging_started_at  TIMESTAMPTZ,
+    judged_at           TIMESTAMPTZ
+);
+
+CREATE INDEX program_submissions_user_id_idx ON program_submissions (user_id, quest_id);
+CREATE INDEX program_submissions_queue_idx ON program_submissions (submitted_at) WHERE verdict IS NULL;
+
+CREATE TABLE program_test_results (
+    submission_id  UUID NOT NULL REFERENCES program_submissions (id) ON DELETE CASCADE,
+    test_index     INTEGER NOT NULL,
+    verdict        verdict NOT NULL,
+    time_ms        INTEGER NOT NULL,
+    PRIMARY KEY (submission_id, test_index)
+);
@@ -0,0 +1,2 @@
+-- Submission limits count a user's recent programs.
+CREATE INDEX program_submissions_user_id_submitted_at_idx ON program_submissions (user_id, submitted_at);
@@ -0,0 +1,3 @@
+-- Submissions that can't be judged get a final verdict after a few attempts.
+ALTER TYPE verdict ADD VALUE 'JE';
+ALTER TABLE program_submissions ADD COLUMN judge_attempts INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,575 @@
+//! Runs submitted programs against test cases generated by the quest's generator.
+//!
+//! Submissions are queued in the `program_submissions` table. Each worker claims the oldest
+//! unjudged submission, compiles it if its language needs that, runs it on the test cases until
+//! one fails and stores the verdict together with the completion of the quest, like
+//! [`submit_answer`] does for pasted answers. Submissions that fail to be judged, e.g. because
+//! the quest's generator is broken, are retried a few times and then get a judge error.
+//!
+//! The compile and run commands of each language are taken verbatim from the judge's config
+//! file and always run inside the configured sandbox command, such as `nsjail`, with an empty
+//! environment. The sandbox is what limits resources and keeps programs off the network and the
+//! file system, so without one no worker starts and programs can't be submitted.
+//!
+//! [`submit_answer`]: codequest_common::services::ProgressionService::submit_answer
+
+use std::{
+    collections::BTreeMap,
+    fs::File as StdFile,
+    io,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use codequest_common::{
+    Error, QuestId, UserId,
+    event::ProgressionEvent,
+    judge::{Language, SubmissionId, TestCase, Verdict},
+    outbox,
+    services::QuestService,
+};
+use rocket::serde::json::serde_json;
+use serde::Deserialize;
+use sqlx::PgPool;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt as _, AsyncWriteExt as _},
+    process::Command,
+};
+
+use crate::complete_quest;
+
+/// How long an idle worker waits before looking for new submissions.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How often judging a submission may fail, e.g. because the quest's generator is broken, before
+/// it gets [`Verdict::JudgeError`].
+const MAX_JUDGE_ATTEMPTS: i32 = 3;
+/// Output beyond this is cut off. A program that keeps writing blocks and runs into the time
+/// limit.
+const MAX_OUTPUT_BYTES: u64 = 1024 * 1024;
+const MAX_COMPILER_OUTPUT_LENGTH: usize = 16 * 1024;
+/// The only environment variable sandboxed commands get.
+const SANDBOX_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+/// Replaced by the submission's directory in the sandbox command.
+const WORK_DIR_PLACEHOLDER: &str = "{work_dir}";
+
+/// How a language's programs are compiled and run. Commands run in a fresh directory containing
+/// only the source file.
+#[derive(Debug, Clone, Deserialize)]
+struct LanguageRunner {
+    name: String,
+    source_file: String,
+    /// Languages without a compile step are only run.
+    #[serde(default)]
+    compile: Option<Vec<String>>,
+    run: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JudgeConfig {
+    #[serde(default)]
+    languages: BTreeMap<String, LanguageRunner>,
+    /// Command every compile and run command is appended to, e.g. `nsjail` with its options.
+    /// `{work_dir}` in it is replaced by the submission's directory.
+    #[serde(default)]
+    sandbox: Option<Vec<String>>,
+    /// Every submission runs on this many test cases.
+    #[serde(default = "defaults::test_count")]
+    test_count: u32,
+    #[serde(default = "defaults::time_limit_ms")]
+    time_limit_ms: u64,
+    #[serde(default = "defaults::compile_time_limit_ms")]
+    compile_time_limit_ms: u64,
+    #[serde(default = "defaults::workers")]
+    workers: usize,
+    #[serde(default = "defaults::work_dir")]
+    work_dir: PathBuf,
+}
+
+mod defaults {
+    use std::{env, path::PathBuf};
+
+    pub fn test_count() -> u32 {
+        5
+    }
+
+    pub fn time_limit_ms() -> u64 {
+        2000
+    }
+
+    pub fn compile_time_limit_ms() -> u64 {
+        30000
+    }
+
+    pub fn workers() -> usize {
+        2
+    }
+
+    pub fn work_dir() -> PathBuf {
+        env::temp_dir().join("codequest-judge")
+    }
+}
+
+impl JudgeConfig {
+    /// A config without languages, programs can't be submitted then.
+    pub fn disabled() -> Self {
+        serde_json::from_str("{}").expect("the judge config has defaults for every field")
+    }
+
+    /// Reads the config from a JSON file. A missing file disables the judge.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        match StdFile::open(path) {
+            Ok(file) => Ok(serde_json::from_reader(file)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::disabled()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Whether programs are judged, which needs both languages and a sandbox.
+    fn is_enabled(&self) -> bool {
+        !self.languages.is_empty() && self.sandbox.as_ref().is_some_and(|s| !s.is_empty())
+    }
+
+    pub fn languages(&self) -> Box<[Language]> {
+        if !self.is_enabled() {
+            return Box::new([]);
+        }
+        self.languages
+            .iter()
+            .map(|(id, runner)| Language {
+                id: id.clone(),
+                name: runner.name.clone(),
+            })
+            .collect()
+    }
+
+    pub fn has_language(&self, id: &str) -> bool {
+        self.is_enabled() && self.languages.contains_key(id)
+    }
+}
+
+struct ClaimedSubmission {
+    id: SubmissionId,
+    user_id: UserId,
+    quest_id: QuestId,
+    language: String,
+    source: String,
+}
+
+struct Judgement {
+    verdict: Verdict,
+    compiler_output: Option<String>,
+    tests: Vec<(Verdict, Duration)>,
+}
+
+impl Judgement {
+    fn compilation_error(compiler_output: String) -> Self {
+        Self {
+            verdict: Verdict::CompilationError,
+            compiler_output: Some(compiler_output),
+            tests: Vec::new(),
+        }
+    }
+
+    fn judge_error() -> Self {
+        Self {
+            verdict: Verdict::JudgeError,
+            compiler_output: Some(
+                "The submission couldn't be judged. Please try again later or tell the quest's author."
+                    .to_owned(),
+            ),
+            tests: Vec::new(),
+        }
+    }
+}
+
+pub(crate) struct Judge {
+    pool: PgPool,
+    quest_service: Arc<dyn QuestService>,
+    config: Arc<JudgeConfig>,
+}
+
+impl Judge {
+    pub(crate) fn new(
+        pool: PgPool,
+        quest_service: Arc<dyn QuestService>,
+        config: Arc<JudgeConfig>,
+    ) -> Self {
+        Self {
+            pool,
+            quest_service,
+            config,
+        }
+    }
+
+    /// Starts the configured number of workers, unless no language or no sandbox is configured.
+    pub(crate) fn spawn(self) {
+        if self.config.languages.is_empty() {
+            println!("judge disabled: no languages configured");
+            return;
+        }
+        if !self.config.is_enabled() {
+            eprintln!("judge disabled: refusing to run programs without a sandbox command");
+            return;
+        }
+
+        let judge = Arc::new(self);
+        for worker in 0..judge.config.workers {
+            let judge = judge.clone();
+            tokio::spawn(async move {
+                println!("judge worker {} started", worker);
+                loop {
+                    match judge.judge_next().await {
+                        Ok(true) => (),
+                        Ok(false) => tokio::time::sleep(POLL_INTERVAL).await,
+                        Err(e) => {
+                            eprintln!("judge worker {}: {}", worker, e);
+                            tokio::time::sleep(POLL_INTERVAL).await;
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    /// Judges the oldest unjudged submission. Returns `false` if there was none.
+    async fn judge_next(&self) -> Result<bool, Error> {
+        // Submissions whose worker died while judging them are picked up again after a while.
+        let Some((id, user_id, quest_id, language, source, attempts)) =
+            sqlx::query_as::<_, (SubmissionId, UserId, QuestId, String, String, i32)>(
+                "UPDATE program_submissions
+                    SET judging_started_at = now(), judge_attempts = judge_attempts + 1
+                    WHERE id = (
+                        SELECT id FROM program_submissions
+                        WHERE (verdict IS NULL AND (judging_started_at IS NULL OR judging_started_at < now() - INTERVAL '10 minutes'))
+                        ORDER BY submitted_at
+                        LIMIT 1
+                        FOR UPDATE SKIP LOCKED
+                    )
+                    RETURNING id, user_id, quest_id, language, source, judge_attempts",
+            )
+            .fetch_optional(&self.pool)
+            .await?
+        else {
+            return Ok(false);
+        };
+        let submission = ClaimedSubmission {
+            id,
+            user_id,
+            quest_id,
+            language,
+            source,
+        };
+
+        let work_dir = self.config.work_dir.join(submission.id.to_string());
+        let judgement = self.judge(&submission, &work_dir).await;
+        if let Err(e) = tokio::fs::remove_dir_all(&work_dir).await {
+            eprintln!("failed to remove {}: {}", work_dir.display(), e);
+        }
+        let judgement = match judgement {
+            Ok(judgement) => judgement,
+            Err(e) if attempts >= MAX_JUDGE_ATTEMPTS => {
+                eprintln!(
+                    "giving up on judging submission {} after {} attempts: {}",
+                    submission.id, attempts, e
+                );
+                Judgement::judge_error()
+            }
+            // The submission is claimed again once the claim expires.
+            Err(e) => return Err(e),
+        };
+        self.store(&submission, judgement).await?;
+        Ok(true)
+    }
+
+    async fn judge(
+        &self,
+        submission: &ClaimedSubmission,
+        work_dir: &Path,
+    ) -> Result<Judgement, Error> {
+        let sandbox = self.config.sandbox.as_deref().unwrap_or_default();
+        let Some(runner) = self.config.languages.get(&submission.language) else {
+            return Ok(Judgement::compilation_error(format!(
+                "The language '{}' isn't supported anymore.",
+                submission.language
+            )));
+        };
+
+        tokio::fs::create_dir_all(work_dir).await?;
+        tokio::fs::write(work_dir.join(&runner.source_file), &submission.source).await?;
+
+        if let Some(compile) = &runner.compile {
+            let time_limit = Duration::from_millis(self.config.compile_time_limit_ms);
+            match run(sandbox, compile, work_dir, "", time_limit).await? {
+                RunOutcome::Finished { success: true, .. } => (),
+                RunOutcome::Finished { stdout, stderr, .. } => {
+                    let mut output = stderr;
+                    output.push_str(&stdout);
+                    output.truncate(output.floor_char_boundary(MAX_COMPILER_OUTPUT_LENGTH));
+                    return Ok(Judgement::compilation_error(output));
+                }
+                RunOutcome::TimedOut => {
+                    return Ok(Judgement::compilation_error(
+                        "Compilation took too long.".to_owned(),
+                    ));
+                }
+            }
+        }
+
+        let time_limit = Duration::from_millis(self.config.time_limit_ms);
+        let mut tests = Vec::new();
+        for index in 0..self.config.test_count {
+            let Some(TestCase { input, answer }) = self
+                .quest_service
+                .get_test_case(&submission.quest_id, &submission.user_id, index)
+                .await?
+            else {
+                return Err(Error::IncoherentState);
+            };
+            let (verdict, elapsed) =
+                match run(sandbox, &runner.run, work_dir, &input, time_limit).await? {
+                    RunOutcome::Finished {
+                        success: false,
+                        elapsed,
+                        ..
+                    } => (Verdict::RuntimeError, elapsed),
+                    RunOutcome::Finished {
+                        stdout, elapsed, ..
+                    } => match outputs_match(&stdout, &answer) {
+                        true => (Verdict::Accepted, elapsed),
+                        false => (Verdict::WrongAnswer, elapsed),
+                    },
+                    RunOutcome::TimedOut => (Verdict::TimeLimitExceeded, time_limit),
+                };
+            tests.push((verdict, elapsed));
+            // Like most judges, stop at the first failed test.
+            if verdict != Verdict::Accepted {
+                break;
+            }
+        }
+
+        Ok(Judgement {
+            verdict: tests
+                .iter()
+                .map(|(verdict, _)| *verdict)
+                .find(|verdict| *verdict != Verdict::Accepted)
+                .unwrap_or(Verdict::Accepted),
+            compiler_output: None,
+            tests,
+        })
+    }
+
+    async fn store(
+        &self,
+        submission: &ClaimedSubmission,
+        judgement: Judgement,
+    ) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+        let res = sqlx::query(
+            "UPDATE program_submissions SET verdict = $2, compiler_output = $3, judged_at = now()
+                WHERE (id = $1 AND verdict IS NULL)",
+        )
+        .bind(submission.id)
+        .bind(judgement.verdict)
+        .bind(judgement.compiler_output)
+        .execute(&mut *tx)
+        .await?;
+        // Another worker got there first.
+        if res.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        sqlx::query(
+            "INSERT INTO program_test_results (submission_id, test_index, verdict, time_ms)
+                SELECT $1, test_index - 1, verdict, time_ms
+                FROM UNNEST($2::verdict[], $3::INTEGER[]) WITH ORDINALITY AS tests (verdict, time_ms, test_index)",
+        )
+        .bind(submission.id)
+        .bind(
+            judgement
+                .tests
+                .iter()
+                .map(|(verdict, _)| *verdict)
+                .collect::<Vec<_>>(),
+        )
+        .bind(
+            judgement
+                .tests
+                .iter()
+                .map(|(_, elapsed)| elapsed.as_millis().min(i32::MAX as u128) as i32)
+                .collect::<Vec<_>>(),
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let correct = judgement.verdict == Verdict::Accepted;
+        outbox::enqueue(
+            &mut *tx,
+            &ProgressionEvent::AnswerSubmitted {
+                user_id: submission.user_id,
+                quest_id: Some(submission.quest_id),
+                correct,
+            },
+        )
+        .await?;
+        if correct {
+            complete_quest(&submission.user_id, &submission.quest_id, &mut tx).await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+enum RunOutcome {
+    Finished {
+        success: bool,
+        stdout: String,
+        stderr: String,
+        elapsed: Duration,
+    },
+    TimedOut,
+}
+
+/// Appends `command` to the `sandbox` command, with its placeholders replaced for `dir`.
+fn sandboxed(sandbox: &[String], dir: &Path, command: &[String]) -> Vec<String> {
+    let dir = dir.to_string_lossy();
+    sandbox
+        .iter()
+        .map(|arg| arg.replace(WORK_DIR_PLACEHOLDER, &dir))
+        .chain(command.iter().cloned())
+        .collect()
+}
+
+/// Runs `command` inside `sandbox` in `dir` with `input` on stdin and kills it once `time_limit`
+/// is exceeded.
+async fn run(
+    sandbox: &[String],
+    command: &[String],
+    dir: &Path,
+    input: &str,
+    time_limit: Duration,
+) -> Result<RunOutcome, Error> {
+    if sandbox.is_empty() {
+        return Err(Error::IncoherentState);
+    }
+    let command = sandboxed(sandbox, dir, command);
+    let (program, args) = command.split_first().ok_or(Error::IncoherentState)?;
+    let mut child = Command::new(program)
+        .args(args)
+        .env_clear()
+        .env("PATH", SANDBOX_PATH)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let mut stdin = child.stdin.take().ok_or(Error::IncoherentState)?;
+    let stdout = child.stdout.take().ok_or(Error::IncoherentState)?;
+    let stderr = child.stderr.take().ok_or(Error::IncoherentState)?;
+    let input = input.to_owned();
+    // Programs that don't read their whole input close the pipe early, that's not an error.
+    tokio::spawn(async move {
+        let _ = stdin.write_all(input.as_bytes()).await;
+    });
+
+    let start = Instant::now();
+    let result = tokio::time::timeout(time_limit, async {
+        let (status, stdout, stderr) =
+            tokio::join!(child.wait(), read_capped(stdout), read_capped(stderr));
+        Ok::<_, io::Error>((status?, stdout?, stderr?))
+    })
+    .await;
+    let elapsed = start.elapsed();
+
+    match result {
+        Ok(result) => {
+            let (status, stdout, stderr) = result?;
+            Ok(RunOutcome::Finished {
+                success: status.success(),
+                stdout,
+                stderr,
+                elapsed,
+            })
+        }
+        Err(_) => {
+            child.kill().await?;
+            Ok(RunOutcome::TimedOut)
+        }
+    }
+}
+
+async fn read_capped(reader: impl AsyncRead + Unpin) -> io::Result<String> {
+    let mut buffer = Vec::new();
+    reader
+        .take(MAX_OUTPUT_BYTES)
+        .read_to_end(&mut buffer)
+        .await?;
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+/// Compares line by line, ignoring trailing whitespace and trailing empty lines.
+fn outputs_match(output: &str, answer: &str) -> bool {
+    fn lines(text: &str) -> Vec<&str> {
+        let mut lines = text.lines().map(str::trim_end).collect::<Vec<_>>();
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        lines
+    }
+    lines(output) == lines(answer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| (*arg).to_owned()).collect()
+    }
+
+    #[test]
+    fn commands_run_inside_the_sandbox() {
+        let sandbox = strings(&["nsjail", "--bindmount", "{work_dir}:/judge", "--"]);
+        let command = sandboxed(&sandbox, Path::new("/tmp/judge/1"), &strings(&["./main"]));
+        assert_eq!(
+            command,
+            strings(&[
+                "nsjail",
+                "--bindmount",
+                "/tmp/judge/1:/judge",
+                "--",
+                "./main"
+            ])
+        );
+    }
+
+    #[test]
+    fn outputs_match_ignoring_trailing_whitespace() {
+        assert!(outputs_match("1 2\n3\n", "1 2\n3"));
+        assert!(outputs_match("1 2  \r\n3\t\n\n\n", "1 2\n3\n"));
+        assert!(outputs_match("", "\n"));
+    }
+
+    #[test]
+    fn outputs_differ_in_content_and_leading_whitespace() {
+        assert!(!outputs_match("1 2\n4\n", "1 2\n3\n"));
+        assert!(!outputs_match(" 1 2\n3\n", "1 2\n3\n"));
+        assert!(!outputs_match("1\n\n2\n", "1\n2\n"));
+        assert!(!outputs_match("1\n", "1\n2\n"));
+    }
+
+    #[test]
+    fn the_judge_is_disabled_without_a_sandbox() {
+        let config = serde_json::from_str::<JudgeConfig>(
+            r#"{"languages": {"python": {"name": "Python 3", "source_file": "main.py", "run": ["python3", "main.py"]}}}"#,
+        )
+        .unwrap();
+        assert!(!config.is_enabled());
+        assert!(!config.has_language("python"));
+        assert!(config.languages().is_empty());
+    }
+}
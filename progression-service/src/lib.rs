@@ -4,12 +4,16 @@ use std::{
     io,
     path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
 
 use codequest_common::{
-    Credentials, Error, QuestId, UserId,
-    event::{ProgressionEvent, UserEvent},
-    nats::NatsClient,
+    AnswerMode, Credentials, Error, QuestId, UserId,
+    event::{ErasureEvent, ProgressionEvent, UserEvent},
+    judge::{Language, ProgramSubmission, ProgramSubmissionDetails, SubmissionId, TestResult},
+    nats::{ConsumerHealth, NatsClient},
+    outbox::{self, OutboxRelay},
+    progression::{CompletedQuest, ProgressionExport, RevealedHint, StartedQuest, Submission},
     services::{ProgressionService, QuestService},
 };
 use reqwest::{Client, StatusCode};
@@ -18,10 +22,27 @@ use rocket::{
     serde::json::{self, serde_json},
     tokio::{fs::File as TokioFile, io::AsyncWriteExt as _, sync::RwLock},
 };
-use sqlx::{PgPool, postgres::PgPoolOptions};
+use sqlx::{
+    FromRow as _, PgConnection, PgPool, Row as _,
+    postgres::{PgPoolOptions, PgRow},
+};
+
+use crate::judge::Judge;
+pub use crate::judge::JudgeConfig;
+
+mod judge;
+
+/// Longest source code accepted for program submissions, in bytes.
+const MAX_SOURCE_LENGTH: usize = 64 * 1024;
+/// How many of a user's programs may wait for or be in judging at the same time.
+const MAX_PENDING_SUBMISSIONS: i64 = 2;
+/// How many programs a user may submit within [`SUBMISSION_WINDOW`].
+const MAX_RECENT_SUBMISSIONS: i64 = 20;
+const SUBMISSION_WINDOW: Duration = Duration::from_secs(10 * 60);
 
 pub struct InMemoryProgressionService {
     user_progress: RwLock<HashMap<UserId, Vec<QuestId>>>,
+    revealed_hints: RwLock<HashMap<(UserId, QuestId), u32>>,
     quest_service: Arc<dyn QuestService>,
 }
 
@@ -30,6 +51,7 @@ impl InMemoryProgressionService {
         let user_progress = RwLock::new(HashMap::new());
         Self {
             user_progress,
+            revealed_hints: RwLock::new(HashMap::new()),
             quest_service,
         }
     }
@@ -40,6 +62,7 @@ impl InMemoryProgressionService {
     ) -> Self {
         Self {
             user_progress: RwLock::new(user_progress),
+            revealed_hints: RwLock::new(HashMap::new()),
             quest_service,
         }
     }
@@ -47,6 +70,20 @@ impl InMemoryProgressionService {
 
 #[async_trait]
 impl ProgressionService for InMemoryProgressionService {
+    async fn get_completed_quests(&self, user_id: &UserId) -> Result<Box<[QuestId]>, Error> {
+        Ok(self
+            .user_progress
+            .read()
+            .await
+            .get(user_id)
+            .map(|completed_quests| completed_quests.clone().into_boxed_slice())
+            .unwrap_or_default())
+    }
+
+    async fn export_user_data(&self, _user_id: &UserId) -> Result<ProgressionExport, Error> {
+        Err(Error::Unsupported)
+    }
+
     async fn has_user_completed_quest(
         &self,
         user_id: &UserId,
@@ -63,6 +100,10 @@ impl ProgressionService for InMemoryProgressionService {
         })
     }
 
+    async fn start_quest(&self, _user_id: &UserId, _quest_id: &QuestId) -> Result<(), Error> {
+        Ok(())
+    }
+
     async fn submit_answer(
         &self,
         user_id: &UserId,
@@ -87,6 +128,61 @@ impl ProgressionService for InMemoryProgressionService {
 
         return Ok(res);
     }
+
+    async fn get_revealed_hints(&self, user_id: &UserId, quest_id: &QuestId) -> Result<u32, Error> {
+        Ok(self
+            .revealed_hints
+            .read()
+            .await
+            .get(&(*user_id, *quest_id))
+            .copied()
+            .unwrap_or(0))
+    }
+
+    async fn reveal_hint(
+        &self,
+        user_id: &UserId,
+        quest_id: &QuestId,
+        hint_index: u32,
+        _penalty: u32,
+    ) -> Result<bool, Error> {
+        let mut revealed_hints = self.revealed_hints.write().await;
+        let revealed = revealed_hints.entry((*user_id, *quest_id)).or_insert(0);
+        if *revealed != hint_index {
+            return Ok(false);
+        }
+        *revealed += 1;
+        Ok(true)
+    }
+
+    async fn list_languages(&self) -> Result<Box<[Language]>, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn submit_program(
+        &self,
+        _user_id: &UserId,
+        _quest_id: &QuestId,
+        _language: &str,
+        _source: &str,
+    ) -> Result<Option<SubmissionId>, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn get_program_submission(
+        &self,
+        _id: &SubmissionId,
+    ) -> Result<Option<ProgramSubmissionDetails>, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn list_program_submissions(
+        &self,
+        _user_id: &UserId,
+        _quest_id: &QuestId,
+    ) -> Result<Box<[ProgramSubmission]>, Error> {
+        Err(Error::Unsupported)
+    }
 }
 
 pub struct FileProgressionService {
@@ -145,6 +241,18 @@ impl FileProgressionService {
 
 #[async_trait]
 impl ProgressionService for FileProgressionService {
+    async fn get_completed_quests(&self, user_id: &UserId) -> Result<Box<[QuestId]>, Error> {
+        self.in_memory_progression_service
+            .get_completed_quests(user_id)
+            .await
+    }
+
+    async fn export_user_data(&self, user_id: &UserId) -> Result<ProgressionExport, Error> {
+        self.in_memory_progression_service
+            .export_user_data(user_id)
+            .await
+    }
+
     async fn has_user_completed_quest(
         &self,
         user_id: &UserId,
@@ -155,6 +263,12 @@ impl ProgressionService for FileProgressionService {
             .await
     }
 
+    async fn start_quest(&self, user_id: &UserId, quest_id: &QuestId) -> Result<(), Error> {
+        self.in_memory_progression_service
+            .start_quest(user_id, quest_id)
+            .await
+    }
+
     async fn submit_answer(
         &self,
         user_id: &UserId,
@@ -175,11 +289,67 @@ impl ProgressionService for FileProgressionService {
         }
         return Ok(res);
     }
+
+    // Revealed hints aren't written to the file.
+    async fn get_revealed_hints(&self, user_id: &UserId, quest_id: &QuestId) -> Result<u32, Error> {
+        self.in_memory_progression_service
+            .get_revealed_hints(user_id, quest_id)
+            .await
+    }
+
+    async fn reveal_hint(
+        &self,
+        user_id: &UserId,
+        quest_id: &QuestId,
+        hint_index: u32,
+        penalty: u32,
+    ) -> Result<bool, Error> {
+        self.in_memory_progression_service
+            .reveal_hint(user_id, quest_id, hint_index, penalty)
+            .await
+    }
+
+    async fn list_languages(&self) -> Result<Box<[Language]>, Error> {
+        self.in_memory_progression_service.list_languages().await
+    }
+
+    async fn submit_program(
+        &self,
+        user_id: &UserId,
+        quest_id: &QuestId,
+        language: &str,
+        source: &str,
+    ) -> Result<Option<SubmissionId>, Error> {
+        self.in_memory_progression_service
+            .submit_program(user_id, quest_id, language, source)
+            .await
+    }
+
+    async fn get_program_submission(
+        &self,
+        id: &SubmissionId,
+    ) -> Result<Option<ProgramSubmissionDetails>, Error> {
+        self.in_memory_progression_service
+            .get_program_submission(id)
+            .await
+    }
+
+    async fn list_program_submissions(
+        &self,
+        user_id: &UserId,
+        quest_id: &QuestId,
+    ) -> Result<Box<[ProgramSubmission]>, Error> {
+        self.in_memory_progression_service
+            .list_program_submissions(user_id, quest_id)
+            .await
+    }
 }
 
 pub struct DatabaseProgressionService {
     pool: PgPool,
     quest_service: Arc<dyn QuestService>,
+    judge_config: Arc<JudgeConfig>,
+    consumer_health: ConsumerHealth,
 }
 
 impl DatabaseProgressionService {
@@ -189,6 +359,7 @@ impl DatabaseProgressionService {
         db_name: S,
         credentials: Credentials,
         nats_address: impl AsRef<str>,
+        judge_config: JudgeConfig,
     ) -> Result<Self, Error> {
         let pool = PgPoolOptions::new()
             .max_connections(20)
@@ -203,46 +374,195 @@ impl DatabaseProgressionService {
                 .as_str(),
             )
             .await?;
+        sqlx::migrate!().run(&pool).await?;
+
+        let consumer_health = ConsumerHealth::new();
+
         let pool2 = pool.clone();
 
-        let nats_client = NatsClient::new(nats_address).await?;
+        let nats_client = NatsClient::new(nats_address.as_ref()).await?;
+
+        let consumer_health2 = consumer_health.clone();
 
         let _join_handle = rocket::tokio::spawn(async move {
             println!("NATS garbage collector started");
             let pool = pool2;
-            let _x = nats_client
+            nats_client
                 .consume::<UserEvent>(
                     "USER_EVENTS",
                     "progression-service".to_owned(),
-                    async move |event| {
-                        match event {
+                    consumer_health2,
+                    async move |envelope| {
+                        let correlation_id = envelope.correlation_id();
+                        match envelope.event {
                             UserEvent::Deleted(user_id) => {
-                                let _query_result =
-                                    sqlx::query("DELETE FROM progression WHERE (user_id = $1)")
-                                        .bind(&user_id)
-                                        .execute(&pool)
-                                        .await?;
+                                let mut transaction = pool.begin().await?;
+                                sqlx::query("DELETE FROM progression WHERE (user_id = $1)")
+                                    .bind(user_id)
+                                    .execute(&mut *transaction)
+                                    .await?;
+                                sqlx::query("DELETE FROM submissions WHERE (user_id = $1)")
+                                    .bind(user_id)
+                                    .execute(&mut *transaction)
+                                    .await?;
+                                sqlx::query("DELETE FROM quest_starts WHERE (user_id = $1)")
+                                    .bind(user_id)
+                                    .execute(&mut *transaction)
+                                    .await?;
+                                sqlx::query("DELETE FROM hint_reveals WHERE (user_id = $1)")
+                                    .bind(user_id)
+                                    .execute(&mut *transaction)
+                                    .await?;
+                                sqlx::query("DELETE FROM program_submissions WHERE (user_id = $1)")
+                                    .bind(user_id)
+                                    .execute(&mut *transaction)
+                                    .await?;
+                                outbox::enqueue_correlated(
+                                    &mut *transaction,
+                                    &ErasureEvent::Completed {
+                                        user_id,
+                                        service: "progression-service".to_owned(),
+                                    },
+                                    correlation_id,
+                                )
+                                .await?;
+                                transaction.commit().await?;
                             }
-                            UserEvent::Created(_) => (),
+                            UserEvent::Created(_)
+                            | UserEvent::LockedOut { .. }
+                            | UserEvent::Renamed { .. } => (),
                         }
                         Ok(())
                     },
                 )
-                .await
-                .expect("NATS garbage collector crashed");
+                .await;
         });
 
-        sqlx::migrate!().run(&pool).await?;
+        OutboxRelay::new(pool.clone(), nats_address.as_ref(), "progression-service").spawn();
+
+        let judge_config = Arc::new(judge_config);
+        Judge::new(pool.clone(), quest_service.clone(), judge_config.clone()).spawn();
 
         Ok(Self {
             pool,
             quest_service,
+            judge_config,
+            consumer_health,
         })
     }
+
+    /// The state of the service's NATS consumers, for its `/health` route.
+    pub fn consumer_health(&self) -> ConsumerHealth {
+        self.consumer_health.clone()
+    }
+
+    /// Completes a `program_submissions` row that includes the source and compiler output with
+    /// its test results.
+    async fn program_submission_details(
+        &self,
+        row: PgRow,
+    ) -> Result<ProgramSubmissionDetails, Error> {
+        let submission = ProgramSubmission::from_row(&row)?;
+        let tests = sqlx::query_as::<_, TestResult>(
+            "SELECT test_index, verdict, time_ms FROM program_test_results WHERE (submission_id = $1) ORDER BY test_index",
+        )
+        .bind(submission.id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(ProgramSubmissionDetails {
+            submission,
+            source: row.try_get("source")?,
+            compiler_output: row.try_get("compiler_output")?,
+            tests,
+        })
+    }
+}
+
+/// Marks the quest as completed by the user and publishes [`ProgressionEvent::QuestCompleted`].
+/// Returns `false` if the user had already completed it.
+async fn complete_quest(
+    user_id: &UserId,
+    quest_id: &QuestId,
+    conn: &mut PgConnection,
+) -> Result<bool, Error> {
+    let res = sqlx::query(
+        "INSERT INTO progression (quest_id, user_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+    )
+    .bind(quest_id)
+    .bind(user_id)
+    .execute(&mut *conn)
+    .await?;
+    if res.rows_affected() == 0 {
+        return Ok(false);
+    }
+    outbox::enqueue(
+        &mut *conn,
+        &ProgressionEvent::QuestCompleted {
+            user_id: *user_id,
+            quest_id: *quest_id,
+        },
+    )
+    .await?;
+    Ok(true)
 }
 
 #[async_trait]
 impl ProgressionService for DatabaseProgressionService {
+    async fn get_completed_quests(&self, user_id: &UserId) -> Result<Box<[QuestId]>, Error> {
+        Ok(sqlx::query_scalar::<_, QuestId>(
+            "SELECT quest_id FROM progression WHERE (user_id = $1) ORDER BY completed_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?
+        .into_boxed_slice())
+    }
+
+    async fn export_user_data(&self, user_id: &UserId) -> Result<ProgressionExport, Error> {
+        let started_quests = sqlx::query_as::<_, StartedQuest>(
+            "SELECT quest_id, started_at FROM quest_starts WHERE (user_id = $1) ORDER BY started_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let completed_quests = sqlx::query_as::<_, CompletedQuest>(
+            "SELECT quest_id, completed_at FROM progression WHERE (user_id = $1) ORDER BY completed_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let submissions = sqlx::query_as::<_, Submission>(
+            "SELECT quest_id, answer, correct, submitted_at FROM submissions WHERE (user_id = $1) ORDER BY submitted_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let revealed_hints = sqlx::query_as::<_, RevealedHint>(
+            "SELECT quest_id, hint_index, penalty, revealed_at FROM hint_reveals WHERE (user_id = $1) ORDER BY revealed_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut program_submissions = Vec::new();
+        for row in sqlx::query(
+            "SELECT id, user_id, quest_id, language, verdict, submitted_at, judged_at, source, compiler_output
+                FROM program_submissions WHERE (user_id = $1) ORDER BY submitted_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?
+        {
+            program_submissions.push(self.program_submission_details(row).await?);
+        }
+        Ok(ProgressionExport {
+            started_quests,
+            completed_quests,
+            submissions,
+            revealed_hints,
+            program_submissions,
+        })
+    }
+
     async fn has_user_completed_quest(
         &self,
         user_id: &UserId,
@@ -258,6 +578,34 @@ impl ProgressionService for DatabaseProgressionService {
         .unwrap())
     }
 
+    async fn start_quest(&self, user_id: &UserId, quest_id: &QuestId) -> Result<(), Error> {
+        if self.has_user_completed_quest(user_id, quest_id).await? {
+            return Ok(());
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            "INSERT INTO quest_starts (user_id, quest_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        )
+        .bind(user_id)
+        .bind(quest_id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 1 {
+            outbox::enqueue(
+                &mut *tx,
+                &ProgressionEvent::QuestStarted {
+                    user_id: *user_id,
+                    quest_id: *quest_id,
+                },
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
     async fn submit_answer(
         &self,
         user_id: &UserId,
@@ -271,24 +619,173 @@ impl ProgressionService for DatabaseProgressionService {
             .quest_service
             .verify_answer(quest_id, user_id, answer)
             .await?;
-        if Some(true) == res {
-            match sqlx::query("INSERT INTO progression (quest_id, user_id) VALUES ($1, $2)")
-                .bind(&quest_id)
-                .bind(&user_id)
-                .execute(&self.pool)
-                .await
-            {
-                Ok(_) => (),
-                Err(sqlx::Error::Database(db_error))
-                    if db_error.constraint() == Some("progression_pkey") =>
-                {
-                    return Ok(None);
-                }
-                Err(e) => return Err(e.into()),
-            }
+        let Some(correct) = res else {
+            return Ok(None);
+        };
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO submissions (user_id, quest_id, answer, correct) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(user_id)
+        .bind(quest_id)
+        .bind(answer)
+        .bind(correct)
+        .execute(&mut *tx)
+        .await?;
+        outbox::enqueue(
+            &mut *tx,
+            &ProgressionEvent::AnswerSubmitted {
+                user_id: *user_id,
+                quest_id: Some(*quest_id),
+                correct,
+            },
+        )
+        .await?;
+        // Completed concurrently, the submission is dropped with the transaction.
+        if correct && !complete_quest(user_id, quest_id, &mut tx).await? {
+            return Ok(None);
         }
+        tx.commit().await?;
 
-        return Ok(res);
+        Ok(res)
+    }
+
+    async fn get_revealed_hints(&self, user_id: &UserId, quest_id: &QuestId) -> Result<u32, Error> {
+        let revealed = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM hint_reveals WHERE (user_id = $1 AND quest_id = $2)",
+        )
+        .bind(user_id)
+        .bind(quest_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(revealed as u32)
+    }
+
+    async fn reveal_hint(
+        &self,
+        user_id: &UserId,
+        quest_id: &QuestId,
+        hint_index: u32,
+        penalty: u32,
+    ) -> Result<bool, Error> {
+        let mut tx = self.pool.begin().await?;
+        // Only the next hint in order can be revealed.
+        let result = sqlx::query(
+            "INSERT INTO hint_reveals (user_id, quest_id, hint_index, penalty)
+                SELECT $1, $2, $3, $4
+                WHERE (SELECT COUNT(*) FROM hint_reveals WHERE (user_id = $1 AND quest_id = $2)) = $3
+                ON CONFLICT DO NOTHING",
+        )
+        .bind(user_id)
+        .bind(quest_id)
+        .bind(hint_index as i32)
+        .bind(penalty as i32)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+        outbox::enqueue(
+            &mut *tx,
+            &ProgressionEvent::HintRevealed {
+                user_id: *user_id,
+                quest_id: *quest_id,
+                hint_index,
+                penalty,
+            },
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    async fn list_languages(&self) -> Result<Box<[Language]>, Error> {
+        Ok(self.judge_config.languages())
+    }
+
+    async fn submit_program(
+        &self,
+        user_id: &UserId,
+        quest_id: &QuestId,
+        language: &str,
+        source: &str,
+    ) -> Result<Option<SubmissionId>, Error> {
+        let Some(quest) = self.quest_service.get_quest(quest_id).await? else {
+            return Ok(None);
+        };
+        if quest.answer_mode != AnswerMode::Program {
+            return Ok(None);
+        }
+        if !self.judge_config.has_language(language) || source.len() > MAX_SOURCE_LENGTH {
+            return Err(Error::BadRequest);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        // Serialises the user's submissions so concurrent ones can't all pass the limits.
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1::TEXT, 0))")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        let (pending, recent) = sqlx::query_as::<_, (i64, i64)>(
+            "SELECT COUNT(*) FILTER (WHERE verdict IS NULL),
+                    COUNT(*) FILTER (WHERE submitted_at > now() - make_interval(secs => $2))
+                FROM program_submissions WHERE (user_id = $1)",
+        )
+        .bind(user_id)
+        .bind(SUBMISSION_WINDOW.as_secs_f64())
+        .fetch_one(&mut *tx)
+        .await?;
+        if pending >= MAX_PENDING_SUBMISSIONS || recent >= MAX_RECENT_SUBMISSIONS {
+            return Err(Error::TooManyRequests);
+        }
+
+        // The judge picks the submission up from the table.
+        let id = sqlx::query_scalar::<_, SubmissionId>(
+            "INSERT INTO program_submissions (user_id, quest_id, language, source) VALUES ($1, $2, $3, $4) RETURNING id",
+        )
+        .bind(user_id)
+        .bind(quest_id)
+        .bind(language)
+        .bind(source)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(Some(id))
+    }
+
+    async fn get_program_submission(
+        &self,
+        id: &SubmissionId,
+    ) -> Result<Option<ProgramSubmissionDetails>, Error> {
+        let Some(row) = sqlx::query(
+            "SELECT id, user_id, quest_id, language, verdict, submitted_at, judged_at, source, compiler_output
+                FROM program_submissions WHERE (id = $1)",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(self.program_submission_details(row).await?))
+    }
+
+    async fn list_program_submissions(
+        &self,
+        user_id: &UserId,
+        quest_id: &QuestId,
+    ) -> Result<Box<[ProgramSubmission]>, Error> {
+        Ok(sqlx::query_as::<_, ProgramSubmission>(
+            "SELECT id, user_id, quest_id, language, verdict, submitted_at, judged_at
+                FROM program_submissions WHERE (user_id = $1 AND quest_id = $2) ORDER BY submitted_at DESC",
+        )
+        .bind(user_id)
+        .bind(quest_id)
+        .fetch_all(&self.pool)
+        .await?
+        .into_boxed_slice())
     }
 }
 
@@ -308,6 +805,40 @@ impl BackendProgressionService {
 
 #[async_trait]
 impl ProgressionService for BackendProgressionService {
+    async fn get_completed_quests(&self, user_id: &UserId) -> Result<Box<[QuestId]>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}", &self.address, user_id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Box<[QuestId]>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn export_user_data(&self, user_id: &UserId) -> Result<ProgressionExport, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}/export", &self.address, user_id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<ProgressionExport>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
     async fn has_user_completed_quest(
         &self,
         user_id: &UserId,
@@ -331,6 +862,20 @@ impl ProgressionService for BackendProgressionService {
         }
     }
 
+    async fn start_quest(&self, user_id: &UserId, quest_id: &QuestId) -> Result<(), Error> {
+        let response = self
+            .client
+            .post(format!("{}/{}/{}/start", &self.address, user_id, quest_id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => Ok(()),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
     async fn submit_answer(
         &self,
         user_id: &UserId,
@@ -355,67 +900,142 @@ impl ProgressionService for BackendProgressionService {
                 Err(_) => Err(Error::InvalidResponse),
             },
             StatusCode::NOT_FOUND => Ok(None),
+            StatusCode::SERVICE_UNAVAILABLE => Err(Error::QuestContextPending),
             _ => Err(Error::InvalidResponse),
         }
     }
-}
 
-pub struct ProgressionServiceNatsWrapper {
-    progression_service: Arc<dyn ProgressionService>,
-    nats_client: NatsClient,
-}
+    async fn get_revealed_hints(&self, user_id: &UserId, quest_id: &QuestId) -> Result<u32, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}/{}/hints", &self.address, user_id, quest_id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
 
-impl ProgressionServiceNatsWrapper {
-    pub async fn new(
-        progression_service: Arc<dyn ProgressionService>,
-        nats_address: impl AsRef<str>,
-    ) -> Result<Self, Error> {
-        let nats_client = NatsClient::new(nats_address).await?;
-        Ok(Self {
-            progression_service,
-            nats_client,
-        })
+        match response.status() {
+            StatusCode::OK => match response.text().await {
+                Ok(revealed) => revealed.parse::<u32>().map_err(|_| Error::InvalidResponse),
+                Err(_) => Err(Error::InvalidResponse),
+            },
+            _ => Err(Error::InvalidResponse),
+        }
     }
-}
 
-#[async_trait]
-impl ProgressionService for ProgressionServiceNatsWrapper {
-    async fn has_user_completed_quest(
+    async fn reveal_hint(
         &self,
         user_id: &UserId,
         quest_id: &QuestId,
+        hint_index: u32,
+        penalty: u32,
     ) -> Result<bool, Error> {
-        self.progression_service
-            .has_user_completed_quest(user_id, quest_id)
+        let response = self
+            .client
+            .post(format!(
+                "{}/{}/{}/hints/{}?penalty={}",
+                &self.address, user_id, quest_id, hint_index, penalty
+            ))
+            .send()
             .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => match response.text().await {
+                Ok(revealed) => revealed.parse::<bool>().map_err(|_| Error::InvalidResponse),
+                Err(_) => Err(Error::InvalidResponse),
+            },
+            _ => Err(Error::InvalidResponse),
+        }
     }
 
-    async fn submit_answer(
+    async fn list_languages(&self) -> Result<Box<[Language]>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/languages", &self.address))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Box<[Language]>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn submit_program(
         &self,
         user_id: &UserId,
         quest_id: &QuestId,
-        answer: &str,
-    ) -> Result<Option<bool>, Error> {
-        let res = self
-            .progression_service
-            .submit_answer(user_id, quest_id, answer)
-            .await?;
-        if let Some(correct) = res {
-            self.nats_client
-                .emit(ProgressionEvent::AnswerSubmitted {
-                    user_id: user_id.clone(),
-                    correct,
-                })
-                .await?;
-            if correct {
-                self.nats_client
-                    .emit(ProgressionEvent::QuestCompleted {
-                        user_id: user_id.clone(),
-                        quest_id: quest_id.clone(),
-                    })
-                    .await?
-            }
+        language: &str,
+        source: &str,
+    ) -> Result<Option<SubmissionId>, Error> {
+        let response = self
+            .client
+            .post(format!(
+                "{}/{}/{}/programs",
+                &self.address, user_id, quest_id
+            ))
+            .query(&[("language", language)])
+            .body(source.to_owned())
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => Ok(Some(SubmissionId::try_parse(response.text().await?)?)),
+            StatusCode::NOT_FOUND => Ok(None),
+            StatusCode::BAD_REQUEST => Err(Error::BadRequest),
+            StatusCode::TOO_MANY_REQUESTS => Err(Error::TooManyRequests),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn get_program_submission(
+        &self,
+        id: &SubmissionId,
+    ) -> Result<Option<ProgramSubmissionDetails>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/programs/{}", &self.address, id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<ProgramSubmissionDetails>()
+                .await
+                .map(Some)
+                .map_err(|_| Error::InvalidResponse),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn list_program_submissions(
+        &self,
+        user_id: &UserId,
+        quest_id: &QuestId,
+    ) -> Result<Box<[ProgramSubmission]>, Error> {
+        let response = self
+            .client
+            .get(format!(
+                "{}/{}/{}/programs",
+                &self.address, user_id, quest_id
+            ))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Box<[ProgramSubmission]>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
         }
-        return Ok(res);
     }
 }
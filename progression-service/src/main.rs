@@ -1,23 +1,54 @@
 use std::{env, sync::Arc};
 
 use codequest_common::{
-    Credentials, Error, QuestId, UserId, load_secret_key,
+    Credentials, Error, QuestId, UserId,
+    judge::{Language, ProgramSubmission, ProgramSubmissionDetails, SubmissionId},
+    load_secret_key,
+    nats::ConsumerHealth,
+    progression::ProgressionExport,
     services::{ProgressionService, QuestService},
 };
-use codequest_progression_service::{DatabaseProgressionService, ProgressionServiceNatsWrapper};
+use codequest_progression_service::{DatabaseProgressionService, JudgeConfig};
 use codequest_quest_service::BackendQuestService;
 use dotenv::dotenv;
 use rocket::{
     State, catchers,
-    response::{content::RawText, status},
+    response::{
+        content::{RawJson, RawText},
+        status,
+    },
     routes,
+    serde::json::Json,
 };
 
 mod defaults {
     pub const SECRET_KEY_FILE: &'static str = "./secrets/secret_key";
+    pub const JUDGE_CONFIG_FILE: &str = "./judge/config.json";
     pub const PORT: u16 = 8000;
 }
 
+#[rocket::get("/<user_id>")]
+async fn get_completed_quests(
+    user_id: UserId,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<Json<Box<[QuestId]>>, Error> {
+    progression_service
+        .get_completed_quests(&user_id)
+        .await
+        .map(Json)
+}
+
+#[rocket::get("/<user_id>/export")]
+async fn export_user_data(
+    user_id: UserId,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<Json<ProgressionExport>, Error> {
+    progression_service
+        .export_user_data(&user_id)
+        .await
+        .map(Json)
+}
+
 #[rocket::get("/<user_id>/<quest_id>")]
 async fn has_user_completed_quest(
     user_id: UserId,
@@ -30,6 +61,15 @@ async fn has_user_completed_quest(
         .map(|res| res.to_string())
 }
 
+#[rocket::post("/<user_id>/<quest_id>/start")]
+async fn start_quest(
+    user_id: UserId,
+    quest_id: QuestId,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<(), Error> {
+    progression_service.start_quest(&user_id, &quest_id).await
+}
+
 #[rocket::post("/<user_id>/<quest_id>/answer", data = "<answer>")]
 async fn submit_answer(
     quest_id: QuestId,
@@ -44,6 +84,84 @@ async fn submit_answer(
         .map(|answer_was_correct| answer_was_correct.to_string()))
 }
 
+#[rocket::get("/<user_id>/<quest_id>/hints")]
+async fn get_revealed_hints(
+    user_id: UserId,
+    quest_id: QuestId,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<String, Error> {
+    progression_service
+        .get_revealed_hints(&user_id, &quest_id)
+        .await
+        .map(|revealed| revealed.to_string())
+}
+
+#[rocket::post("/<user_id>/<quest_id>/hints/<hint_index>?<penalty>")]
+async fn reveal_hint(
+    user_id: UserId,
+    quest_id: QuestId,
+    hint_index: u32,
+    penalty: u32,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<String, Error> {
+    progression_service
+        .reveal_hint(&user_id, &quest_id, hint_index, penalty)
+        .await
+        .map(|revealed| revealed.to_string())
+}
+
+#[rocket::get("/languages")]
+async fn list_languages(
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<Json<Box<[Language]>>, Error> {
+    progression_service.list_languages().await.map(Json)
+}
+
+#[rocket::post("/<user_id>/<quest_id>/programs?<language>", data = "<source>")]
+async fn submit_program(
+    user_id: UserId,
+    quest_id: QuestId,
+    language: &str,
+    source: &str,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<Result<String, status::NotFound<RawText<&'static str>>>, Error> {
+    Ok(progression_service
+        .submit_program(&user_id, &quest_id, language, source)
+        .await?
+        .map(|submission_id| submission_id.to_string())
+        .ok_or(status::NotFound(RawText(""))))
+}
+
+#[rocket::get("/<user_id>/<quest_id>/programs")]
+async fn list_program_submissions(
+    user_id: UserId,
+    quest_id: QuestId,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<Json<Box<[ProgramSubmission]>>, Error> {
+    progression_service
+        .list_program_submissions(&user_id, &quest_id)
+        .await
+        .map(Json)
+}
+
+#[rocket::get("/programs/<id>")]
+async fn get_program_submission(
+    id: SubmissionId,
+    progression_service: &State<Arc<dyn ProgressionService>>,
+) -> Result<Result<Json<ProgramSubmissionDetails>, status::NotFound<RawJson<&'static str>>>, Error>
+{
+    Ok(progression_service
+        .get_program_submission(&id)
+        .await?
+        .map(Json)
+        .ok_or(status::NotFound(RawJson(""))))
+}
+
+#[rocket::get("/health")]
+fn health(consumer_health: &State<ConsumerHealth>) -> ConsumerHealth {
+    consumer_health.inner().clone()
+}
+
 #[rocket::catch(default)]
 fn catch_all() -> &'static str {
     ""
@@ -87,26 +205,42 @@ async fn main() -> Result<(), rocket::Error> {
     let quest_service =
         Arc::new(BackendQuestService::new(quest_service_address)) as Arc<dyn QuestService>;
 
+    let judge_config = JudgeConfig::load(
+        env::var("JUDGE_CONFIG_FILE").unwrap_or_else(|_| defaults::JUDGE_CONFIG_FILE.to_owned()),
+    )
+    .expect("failed to load judge config");
+
     let progression_service = DatabaseProgressionService::new(
         quest_service,
         &db_address,
         &db_name,
         db_credentials,
-        nats_address.clone(),
+        nats_address,
+        judge_config,
     )
     .await
     .expect("failed to start DatabaseProgressionService");
-    let progression_service =
-        ProgressionServiceNatsWrapper::new(Arc::new(progression_service), nats_address)
-            .await
-            .expect("failed to start nats wrapper");
 
     rocket::custom(&rocket_config)
         .register("/", catchers![catch_all])
         .mount(
             "/progression",
-            routes![has_user_completed_quest, submit_answer],
+            routes![
+                get_completed_quests,
+                export_user_data,
+                has_user_completed_quest,
+                start_quest,
+                submit_answer,
+                get_revealed_hints,
+                reveal_hint,
+                list_languages,
+                submit_program,
+                list_program_submissions,
+                get_program_submission,
+            ],
         )
+        .mount("/", routes![health])
+        .manage(progression_service.consumer_health())
         .manage(Arc::new(progression_service) as Arc<dyn ProgressionService>)
         .launch()
         .await?;
@@ -0,0 +1,2 @@
+ALTER TABLE quests
+ADD archived BOOLEAN NOT NULL DEFAULT false;
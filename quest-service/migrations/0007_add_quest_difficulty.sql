@@ -0,0 +1,2 @@
+ALTER TABLE quests
+ADD difficulty INTEGER;
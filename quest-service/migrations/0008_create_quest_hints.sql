@@ -0,0 +1,7 @@
+CREATE TABLE quest_hints (
+    quest_id  UUID NOT NULL REFERENCES quests (id) ON DELETE CASCADE,
+    position  INTEGER NOT NULL,
+    text      TEXT NOT NULL,
+    penalty   INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (quest_id, position)
+);
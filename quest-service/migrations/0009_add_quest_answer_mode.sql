@@ -0,0 +1,4 @@
+CREATE TYPE answer_mode AS ENUM ('answer', 'program');
+
+ALTER TABLE quests
+ADD answer_mode answer_mode NOT NULL DEFAULT 'answer';
@@ -0,0 +1,24 @@
+-- Generated contexts. `input` and `answer` are NULL for quests without a generator.
+CREATE TABLE quest_contexts (
+    quest_id      UUID NOT NULL REFERENCES quests (id) ON DELETE CASCADE,
+    user_id       UUID NOT NULL,
+    input         TEXT,
+    answer        TEXT,
+    generated_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (quest_id, user_id)
+);
+
+CREATE INDEX quest_contexts_user_id_idx ON quest_contexts (user_id);
+
+-- Contexts waiting to be generated.
+CREATE TABLE generation_jobs (
+    quest_id      UUID NOT NULL REFERENCES quests (id) ON DELETE CASCADE,
+    user_id       UUID NOT NULL,
+    requested_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    started_at    TIMESTAMPTZ,
+    attempts      INTEGER NOT NULL DEFAULT 0,
+    error         TEXT,
+    PRIMARY KEY (quest_id, user_id)
+);
+
+CREATE INDEX generation_jobs_requested_at_idx ON generation_jobs (requested_at) WHERE (error IS NULL);
@@ -0,0 +1,25 @@
+-- Users whose contexts get pre-generated. Filled from signups and from users requesting contexts.
+CREATE TABLE known_users (
+    user_id     UUID PRIMARY KEY,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+INSERT INTO known_users (user_id) SELECT DISTINCT user_id FROM quest_contexts;
+
+-- Background generation runs, for reporting their progress.
+CREATE TABLE prewarm_batches (
+    id          BIGINT GENERATED ALWAYS AS IDENTITY PRIMARY KEY,
+    -- Exactly one of them is set, depending on whether a quest or a user is pre-warmed.
+    quest_id    UUID REFERENCES quests (id) ON DELETE CASCADE,
+    user_id     UUID,
+    total       INTEGER NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX prewarm_batches_quest_id_idx ON prewarm_batches (quest_id, created_at);
+
+ALTER TABLE generation_jobs
+    ADD COLUMN background  BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN batch_id    BIGINT REFERENCES prewarm_batches (id) ON DELETE SET NULL;
+
+CREATE INDEX generation_jobs_batch_id_idx ON generation_jobs (batch_id);
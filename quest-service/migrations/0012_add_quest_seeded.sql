@@ -0,0 +1,4 @@
+-- Whether the quest was seeded from the official quests directory, which archives it once its
+-- package is removed from there.
+ALTER TABLE quests
+ADD seeded BOOLEAN NOT NULL DEFAULT false;
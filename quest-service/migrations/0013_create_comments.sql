@@ -0,0 +1,29 @@
+CREATE TYPE comment_thread AS ENUM ('discussion', 'solutions');
+
+-- Deleted comments keep their row, with an empty body and no author, so their replies stay in place.
+CREATE TABLE comments (
+    id          UUID PRIMARY KEY,
+    quest_id    UUID NOT NULL REFERENCES quests (id) ON DELETE CASCADE,
+    thread      comment_thread NOT NULL,
+    parent_id   UUID REFERENCES comments (id) ON DELETE CASCADE,
+    author      UUID,
+    body        TEXT NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    edited_at   TIMESTAMPTZ,
+    deleted     BOOLEAN NOT NULL DEFAULT false,
+    hidden      BOOLEAN NOT NULL DEFAULT false
+);
+
+CREATE INDEX comments_quest_id_idx ON comments (quest_id, thread, created_at);
+CREATE INDEX comments_author_idx ON comments (author);
+
+-- Open reports, dropped once a moderator handled the comment.
+CREATE TABLE comment_reports (
+    comment_id  UUID NOT NULL REFERENCES comments (id) ON DELETE CASCADE,
+    reporter    UUID NOT NULL,
+    reason      TEXT NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (comment_id, reporter)
+);
+
+CREATE INDEX comment_reports_reporter_idx ON comment_reports (reporter);
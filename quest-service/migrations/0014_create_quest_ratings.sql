@@ -0,0 +1,20 @@
+CREATE TABLE quest_ratings (
+    quest_id  UUID NOT NULL REFERENCES quests (id) ON DELETE CASCADE,
+    user_id   UUID NOT NULL,
+    rating    SMALLINT NOT NULL CHECK (rating BETWEEN 1 AND 5),
+    -- For the quest's author.
+    feedback  TEXT,
+    rated_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (quest_id, user_id)
+);
+
+CREATE INDEX quest_ratings_user_id_idx ON quest_ratings (user_id);
+
+CREATE TABLE quest_favourites (
+    user_id     UUID NOT NULL,
+    quest_id    UUID NOT NULL REFERENCES quests (id) ON DELETE CASCADE,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, quest_id)
+);
+
+CREATE INDEX quest_favourites_quest_id_idx ON quest_favourites (quest_id);
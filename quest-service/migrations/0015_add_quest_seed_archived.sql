@@ -0,0 +1,4 @@
+-- Whether seeding archived the quest because its package was removed, so seeding only restores
+-- quests it archived itself and leaves the ones archived by an admin alone.
+ALTER TABLE quests
+ADD seed_archived BOOLEAN NOT NULL DEFAULT false;
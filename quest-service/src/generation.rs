@@ -0,0 +1,412 @@
+//! Runs quest generators through a job queue instead of inside the requests that need them.
+//!
+//! Requests for a missing context queue a job in the `generation_jobs` table, keyed by quest and
+//! user, so concurrent requests for the same context share one job even across instances. A fixed
+//! number of workers runs the jobs and stores the results in `quest_contexts`, which doubles as a
+//! persistent cache. Requests wait for their job for a while and fail with
+//! [`Error::QuestContextPending`] afterwards, which tells clients to retry shortly.
+//!
+//! Contexts are also pre-generated in the background when quests are created and users sign up,
+//! see [`GenerationQueue::prewarm_quest`] and [`GenerationQueue::prewarm_user`]. Background jobs
+//! only run on some of the workers and after all requested ones, so they don't delay users.
+
+use std::{sync::Arc, time::Duration};
+
+use codequest_common::{Error, PrewarmProgress, QuestId, UserId, judge::TestCase};
+use rocket::async_trait;
+use sqlx::PgPool;
+use tokio::{sync::Notify, time::Instant};
+
+use crate::quest_context::{QuestContext, QuestContextProvider};
+
+/// How often idle workers check for jobs queued by other instances.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How often waiting requests check whether a job queued by another instance finished.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone)]
+pub struct GenerationConfig {
+    /// How many generators run at the same time.
+    pub workers: usize,
+    /// How long requests wait for their context before failing with
+    /// [`Error::QuestContextPending`].
+    pub wait_timeout: Duration,
+    /// Generators running longer than this are killed.
+    pub generator_timeout: Duration,
+    /// How many of the workers also run background jobs.
+    pub background_workers: usize,
+    /// Command generators run through, e.g. `nsjail` with its options. Authors can only upload
+    /// and test generators when there is one.
+    pub sandbox: Option<Vec<String>>,
+}
+
+impl Default for GenerationConfig {
+    fn default() -> Self {
+        Self {
+            workers: 4,
+            wait_timeout: Duration::from_secs(5),
+            generator_timeout: Duration::from_secs(30),
+            background_workers: 1,
+            sandbox: None,
+        }
+    }
+}
+
+pub struct GenerationQueue {
+    pool: PgPool,
+    generator: Arc<dyn QuestContextProvider>,
+    config: GenerationConfig,
+    /// Wakes up a worker when a job is queued.
+    queued: Notify,
+    /// Wakes up the waiting requests when a job is done.
+    finished: Notify,
+}
+
+impl GenerationQueue {
+    /// Creates the queue and starts its workers.
+    pub fn spawn(
+        pool: PgPool,
+        generator: Arc<dyn QuestContextProvider>,
+        config: GenerationConfig,
+    ) -> Arc<Self> {
+        let queue = Arc::new(Self {
+            pool,
+            generator,
+            config,
+            queued: Notify::new(),
+            finished: Notify::new(),
+        });
+        for worker in 0..queue.config.workers {
+            let queue = queue.clone();
+            let background = worker < queue.config.background_workers;
+            tokio::spawn(async move {
+                println!("generator worker {} started", worker);
+                loop {
+                    match queue.run_next_job(background).await {
+                        Ok(true) => (),
+                        Ok(false) => {
+                            tokio::select! {
+                                _ = queue.queued.notified() => (),
+                                _ = tokio::time::sleep(POLL_INTERVAL) => (),
+                            }
+                        }
+                        Err(e) => {
+                            eprintln!("generator worker {}: {}", worker, e);
+                            tokio::time::sleep(POLL_INTERVAL).await;
+                        }
+                    }
+                }
+            });
+        }
+        queue
+    }
+
+    /// Queues a job for the context unless one is queued already. A failed job is queued again,
+    /// a background job is moved ahead of the other background jobs.
+    async fn enqueue(&self, quest_id: &QuestId, user_id: &UserId) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+        // Users who signed up before contexts were pre-generated are only known once they show up.
+        sqlx::query("INSERT INTO known_users (user_id) VALUES ($1) ON CONFLICT DO NOTHING")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "INSERT INTO generation_jobs (quest_id, user_id) VALUES ($1, $2)
+                ON CONFLICT (quest_id, user_id) DO UPDATE SET error = NULL, attempts = 0, background = false, requested_at = now()
+                WHERE (generation_jobs.error IS NOT NULL OR generation_jobs.background)",
+        )
+        .bind(quest_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        self.queued.notify_one();
+        Ok(())
+    }
+
+    /// Queues background jobs for the quest's missing contexts of every known user and returns
+    /// how many were queued.
+    pub async fn prewarm_quest(&self, quest_id: &QuestId) -> Result<u64, Error> {
+        let mut tx = self.pool.begin().await?;
+        let batch_id = sqlx::query_scalar::<_, i64>(
+            "INSERT INTO prewarm_batches (quest_id, total) VALUES ($1, 0) RETURNING id",
+        )
+        .bind(quest_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        let queued = sqlx::query(
+            "INSERT INTO generation_jobs (quest_id, user_id, background, batch_id)
+                SELECT $1, user_id, true, $2 FROM known_users
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM quest_contexts
+                    WHERE (quest_contexts.quest_id = $1 AND quest_contexts.user_id = known_users.user_id)
+                )
+                ON CONFLICT DO NOTHING",
+        )
+        .bind(quest_id)
+        .bind(batch_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        self.finish_batch(batch_id, queued, tx).await?;
+        println!(
+            "queued {} contexts of quest {} for pre-generation",
+            queued, quest_id
+        );
+        Ok(queued)
+    }
+
+    /// Queues background jobs for the user's missing contexts of every quest that isn't archived
+    /// and returns how many were queued.
+    pub async fn prewarm_user(&self, user_id: &UserId) -> Result<u64, Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("INSERT INTO known_users (user_id) VALUES ($1) ON CONFLICT DO NOTHING")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        let batch_id = sqlx::query_scalar::<_, i64>(
+            "INSERT INTO prewarm_batches (user_id, total) VALUES ($1, 0) RETURNING id",
+        )
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        let queued = sqlx::query(
+            "INSERT INTO generation_jobs (quest_id, user_id, background, batch_id)
+                SELECT id, $1, true, $2 FROM quests
+                WHERE (NOT archived AND NOT EXISTS (
+                    SELECT 1 FROM quest_contexts
+                    WHERE (quest_contexts.quest_id = quests.id AND quest_contexts.user_id = $1)
+                ))
+                ON CONFLICT DO NOTHING",
+        )
+        .bind(user_id)
+        .bind(batch_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        self.finish_batch(batch_id, queued, tx).await?;
+        println!(
+            "queued {} contexts of user {} for pre-generation",
+            queued, user_id
+        );
+        Ok(queued)
+    }
+
+    async fn finish_batch(
+        &self,
+        batch_id: i64,
+        queued: u64,
+        mut tx: sqlx::Transaction<'_, sqlx::Postgres>,
+    ) -> Result<(), Error> {
+        sqlx::query("UPDATE prewarm_batches SET total = $2 WHERE (id = $1)")
+            .bind(batch_id)
+            .bind(queued as i32)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        self.queued.notify_waiters();
+        Ok(())
+    }
+
+    /// The progress of the latest background generation of each quest.
+    pub async fn list_progress(&self) -> Result<Box<[PrewarmProgress]>, Error> {
+        Ok(sqlx::query_as::<_, PrewarmProgress>(
+            "SELECT DISTINCT ON (b.quest_id)
+                    b.quest_id, b.total, b.created_at AS started_at,
+                    COUNT(j.quest_id) AS remaining,
+                    COUNT(j.quest_id) FILTER (WHERE j.error IS NOT NULL) AS failed
+                FROM prewarm_batches b
+                LEFT JOIN generation_jobs j ON (j.batch_id = b.id)
+                WHERE (b.quest_id IS NOT NULL)
+                GROUP BY b.id
+                ORDER BY b.quest_id, b.created_at DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_boxed_slice())
+    }
+
+    /// The stored context, `Some(None)` if the quest has no generator.
+    async fn load(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+    ) -> Result<Option<Option<QuestContext>>, Error> {
+        Ok(sqlx::query_as::<_, (Option<String>, Option<String>)>(
+            "SELECT input, answer FROM quest_contexts WHERE (quest_id = $1 AND user_id = $2)",
+        )
+        .bind(quest_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|context| match context {
+            (Some(input), Some(answer)) => Some(QuestContext::new(input, answer)),
+            _ => None,
+        }))
+    }
+
+    async fn job_error(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+    ) -> Result<Option<String>, Error> {
+        Ok(sqlx::query_scalar::<_, Option<String>>(
+            "SELECT error FROM generation_jobs WHERE (quest_id = $1 AND user_id = $2)",
+        )
+        .bind(quest_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .flatten())
+    }
+
+    /// Runs the oldest queued job, background jobs only if `background` is set and no other job
+    /// is waiting. Returns `false` if there was none.
+    async fn run_next_job(&self, background: bool) -> Result<bool, Error> {
+        // Jobs whose worker died while running them are picked up again after a while.
+        let Some((quest_id, user_id)) = sqlx::query_as::<_, (QuestId, UserId)>(
+            "UPDATE generation_jobs SET started_at = now(), attempts = attempts + 1
+                WHERE (quest_id, user_id) = (
+                    SELECT quest_id, user_id FROM generation_jobs
+                    WHERE (
+                        error IS NULL
+                        AND (started_at IS NULL OR started_at < now() - INTERVAL '5 minutes')
+                        AND (NOT background OR $1)
+                    )
+                    ORDER BY background, requested_at
+                    LIMIT 1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING quest_id, user_id",
+        )
+        .bind(background)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(false);
+        };
+
+        let result = match tokio::time::timeout(
+            self.config.generator_timeout,
+            self.generator.get_context(&quest_id, &user_id),
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(_) => Err(Error::QuestContextGenerationFailed(format!(
+                "the generator took longer than {:?}",
+                self.config.generator_timeout
+            ))),
+        };
+
+        match result {
+            Ok(context) => {
+                let (input, answer) = context
+                    .map(TestCase::from)
+                    .map(|context| (Some(context.input), Some(context.answer)))
+                    .unwrap_or_default();
+                let mut tx = self.pool.begin().await?;
+                sqlx::query(
+                    "INSERT INTO quest_contexts (quest_id, user_id, input, answer) VALUES ($1, $2, $3, $4)
+                        ON CONFLICT (quest_id, user_id) DO UPDATE SET input = $3, answer = $4, generated_at = now()",
+                )
+                .bind(quest_id)
+                .bind(user_id)
+                .bind(input)
+                .bind(answer)
+                .execute(&mut *tx)
+                .await?;
+                sqlx::query("DELETE FROM generation_jobs WHERE (quest_id = $1 AND user_id = $2)")
+                    .bind(quest_id)
+                    .bind(user_id)
+                    .execute(&mut *tx)
+                    .await?;
+                tx.commit().await?;
+            }
+            Err(e) => {
+                eprintln!(
+                    "generating the context of quest {} for user {} failed: {}",
+                    quest_id, user_id, e
+                );
+                sqlx::query(
+                    "UPDATE generation_jobs SET error = $3, started_at = NULL WHERE (quest_id = $1 AND user_id = $2)",
+                )
+                .bind(quest_id)
+                .bind(user_id)
+                .bind(e.to_string())
+                .execute(&self.pool)
+                .await?;
+            }
+        }
+        self.finished.notify_waiters();
+        Ok(true)
+    }
+}
+
+#[async_trait]
+impl QuestContextProvider for GenerationQueue {
+    async fn get_context(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+    ) -> Result<Option<QuestContext>, Error> {
+        if let Some(context) = self.load(quest_id, user_id).await? {
+            return Ok(context);
+        }
+
+        self.enqueue(quest_id, user_id).await?;
+        let deadline = Instant::now() + self.config.wait_timeout;
+        loop {
+            let finished = self.finished.notified();
+            if let Some(context) = self.load(quest_id, user_id).await? {
+                return Ok(context);
+            }
+            if let Some(error) = self.job_error(quest_id, user_id).await? {
+                return Err(Error::QuestContextGenerationFailed(error));
+            }
+            if Instant::now() >= deadline {
+                return Err(Error::QuestContextPending);
+            }
+            tokio::select! {
+                _ = finished => (),
+                _ = tokio::time::sleep(WAIT_POLL_INTERVAL) => (),
+                _ = tokio::time::sleep_until(deadline) => (),
+            }
+        }
+    }
+
+    // Test cases are only needed by the judge, which doesn't mind waiting, but not forever.
+    async fn get_test_case(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+        index: u32,
+    ) -> Result<Option<QuestContext>, Error> {
+        match tokio::time::timeout(
+            self.config.generator_timeout,
+            self.generator.get_test_case(quest_id, user_id, index),
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(_) => Err(Error::QuestContextGenerationFailed(format!(
+                "the generator took longer than {:?}",
+                self.config.generator_timeout
+            ))),
+        }
+    }
+
+    async fn clear_quest(&self, quest_id: &QuestId) -> Result<(), Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM quest_contexts WHERE (quest_id = $1)")
+            .bind(quest_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM generation_jobs WHERE (quest_id = $1 AND started_at IS NULL)")
+            .bind(quest_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        self.generator.clear_quest(quest_id).await
+    }
+}
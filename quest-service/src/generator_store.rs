@@ -0,0 +1,65 @@
+use std::{
+    fs::Permissions,
+    io::{self, ErrorKind},
+    os::unix::fs::PermissionsExt as _,
+    path::PathBuf,
+};
+
+use codequest_common::QuestId;
+use tokio::fs;
+
+/// The generator executables, named after their quests' IDs, that
+/// [`QuestContextGenerator`](crate::quest_context::QuestContextGenerator) runs.
+#[derive(Debug, Clone)]
+pub struct GeneratorStore {
+    dir: PathBuf,
+}
+
+impl GeneratorStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Where the quest's generator is installed, whether it has one or not.
+    pub fn path(&self, quest_id: &QuestId) -> PathBuf {
+        self.dir.join(quest_id.to_string())
+    }
+
+    pub async fn contains(&self, quest_id: &QuestId) -> io::Result<bool> {
+        fs::try_exists(self.path(quest_id)).await
+    }
+
+    /// The quest's generator, `None` if it has none.
+    pub async fn read(&self, quest_id: &QuestId) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.dir.join(quest_id.to_string())).await {
+            Ok(generator) => Ok(Some(generator)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Installs the quest's generator, replacing the previous one.
+    pub async fn write(&self, quest_id: &QuestId, generator: &[u8]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir).await?;
+        // Written next to the generator and moved in place, so it's never run half-written.
+        let tmp_path = self.dir.join(format!(".{}.tmp", quest_id));
+        fs::write(&tmp_path, generator).await?;
+        fs::set_permissions(&tmp_path, Permissions::from_mode(0o755)).await?;
+        fs::rename(&tmp_path, self.dir.join(quest_id.to_string())).await
+    }
+
+    pub async fn remove(&self, quest_id: &QuestId) -> io::Result<()> {
+        match fs::remove_file(self.dir.join(quest_id.to_string())).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes `generator`, or removes the quest's generator if it's `None`.
+    pub async fn replace(&self, quest_id: &QuestId, generator: Option<&[u8]>) -> io::Result<()> {
+        match generator {
+            Some(generator) => self.write(quest_id, generator).await,
+            None => self.remove(quest_id).await,
+        }
+    }
+}
@@ -3,20 +3,46 @@ use std::{
     io,
     path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
 
 use codequest_common::{
-    Credentials, Error, PartialQuestData, Quest, QuestData, QuestEntry, QuestId, UserId,
-    event::QuestEvent, nats::NatsClient, services::QuestService,
+    AnswerMode, Credentials, Error, GeneratorTestReport, Hint, PartialQuestData, PrewarmProgress,
+    Quest, QuestData, QuestEntry, QuestFilter, QuestId, QuestSort, UserId,
+    comment::{
+        Comment, CommentExport, CommentId, CommentReport, CommentThread, FiledReport, NewComment,
+        ReportedComment, clean_comment_body, clean_report_reason,
+    },
+    escape_like,
+    event::{CommentEvent, ErasureEvent, QuestEvent, StatisticsEvent, UserEvent},
+    judge::TestCase,
+    nats::{ConsumerHealth, NatsClient},
+    outbox::{self, OutboxRelay},
+    package::{QuestPackage, validate_generator},
+    rating::{Favourite, QuestRating, RatingData, UserRating},
+    services::QuestService,
 };
+use codequest_generator::{Uuid, harness::Harness};
 use reqwest::{Client, StatusCode};
 use rocket::{async_trait, serde::json};
-use sqlx::{PgPool, QueryBuilder, postgres::PgPoolOptions};
-use tokio::{fs::File as TokioFile, io::AsyncWriteExt as _, sync::RwLock};
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder, postgres::PgPoolOptions};
+use tokio::{
+    fs::File as TokioFile,
+    io::AsyncWriteExt as _,
+    sync::{RwLock, Semaphore},
+    task,
+};
 
-use crate::quest_context::QuestContextProvider;
+use crate::{
+    generation::{GenerationConfig, GenerationQueue},
+    generator_store::GeneratorStore,
+    quest_context::{InMemoryQuestContextCache, QuestContextProvider},
+};
 
+pub mod generation;
+pub mod generator_store;
 pub mod quest_context;
+pub mod seeding;
 
 pub struct ConstQuestService {
     quests: Box<[Quest]>,
@@ -82,6 +108,10 @@ impl QuestService for ConstQuestService {
         Ok(self.quests.iter().find(|quest| quest.id == *id).is_some())
     }
 
+    async fn get_hints(&self, id: &QuestId) -> Result<Option<Box<[Hint]>>, Error> {
+        Ok(self.quest_exists(id).await?.then(Default::default))
+    }
+
     async fn get_input(
         &self,
         quest_id: &QuestId,
@@ -105,6 +135,15 @@ impl QuestService for ConstQuestService {
         })
     }
 
+    async fn get_test_case(
+        &self,
+        _quest_id: &QuestId,
+        _user_id: &UserId,
+        _index: u32,
+    ) -> Result<Option<TestCase>, Error> {
+        Err(Error::Unsupported)
+    }
+
     async fn create_quest(&self, _quest: QuestData) -> Result<QuestId, Error> {
         Err(Error::Unsupported)
     }
@@ -116,22 +155,33 @@ impl QuestService for ConstQuestService {
     async fn modify_quest(&self, _id: &QuestId, _data: PartialQuestData) -> Result<bool, Error> {
         Err(Error::Unsupported)
     }
+
+    async fn delete_quest(&self, _id: &QuestId) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn clear_quest_contexts(&self, _id: &QuestId) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 pub struct InMemoryQuestService {
     quests: RwLock<HashMap<QuestId, Quest>>,
+    hints: RwLock<HashMap<QuestId, Box<[Hint]>>>,
 }
 
 impl InMemoryQuestService {
     pub fn new() -> io::Result<Self> {
         Ok(Self {
             quests: RwLock::new(HashMap::new()),
+            hints: RwLock::new(HashMap::new()),
         })
     }
 
     pub fn with(quests: HashMap<QuestId, Quest>) -> Self {
         Self {
             quests: RwLock::new(quests),
+            hints: RwLock::new(HashMap::new()),
         }
     }
 }
@@ -144,15 +194,38 @@ impl QuestService for InMemoryQuestService {
             .read()
             .await
             .values()
+            .filter(|quest| !quest.archived)
             .map(|quest| quest.to_entry())
             .collect::<Vec<QuestEntry>>()
             .into_boxed_slice())
     }
 
+    async fn search_quests(&self, filter: &QuestFilter) -> Result<Box<[QuestEntry]>, Error> {
+        let mut quests = self
+            .quests
+            .read()
+            .await
+            .values()
+            .map(|quest| quest.to_entry())
+            .filter(|quest| filter.matches(quest))
+            .collect::<Vec<QuestEntry>>();
+        filter.sort(&mut quests);
+        Ok(quests.into_boxed_slice())
+    }
+
     async fn get_quest(&self, id: &QuestId) -> Result<Option<Quest>, Error> {
         Ok(self.quests.read().await.get(id).cloned())
     }
 
+    async fn get_hints(&self, id: &QuestId) -> Result<Option<Box<[Hint]>>, Error> {
+        if !self.quest_exists(id).await? {
+            return Ok(None);
+        }
+        Ok(Some(
+            self.hints.read().await.get(id).cloned().unwrap_or_default(),
+        ))
+    }
+
     async fn get_quest_author(&self, id: &QuestId) -> Result<Option<Option<UserId>>, Error> {
         Ok(self
             .quests
@@ -185,11 +258,24 @@ impl QuestService for InMemoryQuestService {
         })
     }
 
+    async fn get_test_case(
+        &self,
+        _quest_id: &QuestId,
+        _user_id: &UserId,
+        _index: u32,
+    ) -> Result<Option<TestCase>, Error> {
+        Err(Error::Unsupported)
+    }
+
     async fn create_quest(&self, quest: QuestData) -> Result<QuestId, Error> {
-        let quest = Quest::new(quest.name, quest.author, quest.official, quest.text);
+        let hints = quest.hints.into_boxed_slice();
+        let answer_mode = quest.answer_mode;
+        let mut quest = Quest::new(quest.name, quest.author, quest.official, quest.text);
+        quest.answer_mode = answer_mode;
         let id = quest.id;
         let old_value = self.quests.write().await.insert(id, quest);
         assert!(old_value.is_none());
+        self.hints.write().await.insert(id, hints);
         Ok(id)
     }
 
@@ -202,6 +288,11 @@ impl QuestService for InMemoryQuestService {
         quest.author = data.author;
         quest.official = data.official;
         quest.text = data.text;
+        quest.answer_mode = data.answer_mode;
+        self.hints
+            .write()
+            .await
+            .insert(*id, data.hints.into_boxed_slice());
         Ok(true)
     }
 
@@ -223,11 +314,32 @@ impl QuestService for InMemoryQuestService {
         if let Some(official) = data.official {
             quest.official = official;
         }
+        if let Some(archived) = data.archived {
+            quest.archived = archived;
+        }
         if let Some(text) = data.text {
             quest.text = text;
         }
+        if let Some(answer_mode) = data.answer_mode {
+            quest.answer_mode = answer_mode;
+        }
+        if let Some(hints) = data.hints {
+            self.hints
+                .write()
+                .await
+                .insert(*id, hints.into_boxed_slice());
+        }
         Ok(true)
     }
+
+    async fn delete_quest(&self, id: &QuestId) -> Result<bool, Error> {
+        self.hints.write().await.remove(id);
+        Ok(self.quests.write().await.remove(id).is_some())
+    }
+
+    async fn clear_quest_contexts(&self, _id: &QuestId) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 pub struct FileQuestService {
@@ -265,10 +377,19 @@ impl QuestService for FileQuestService {
         self.in_memory_quest_service.list_quests().await
     }
 
+    async fn search_quests(&self, filter: &QuestFilter) -> Result<Box<[QuestEntry]>, Error> {
+        self.in_memory_quest_service.search_quests(filter).await
+    }
+
     async fn get_quest(&self, id: &QuestId) -> Result<Option<Quest>, Error> {
         self.in_memory_quest_service.get_quest(id).await
     }
 
+    /// Hints aren't written to the file, so they are lost on restart.
+    async fn get_hints(&self, id: &QuestId) -> Result<Option<Box<[Hint]>>, Error> {
+        self.in_memory_quest_service.get_hints(id).await
+    }
+
     async fn get_quest_author(&self, id: &QuestId) -> Result<Option<Option<UserId>>, Error> {
         self.in_memory_quest_service.get_quest_author(id).await
     }
@@ -293,6 +414,17 @@ impl QuestService for FileQuestService {
             .await
     }
 
+    async fn get_test_case(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+        index: u32,
+    ) -> Result<Option<TestCase>, Error> {
+        self.in_memory_quest_service
+            .get_test_case(quest_id, user_id, index)
+            .await
+    }
+
     async fn create_quest(&self, quest: QuestData) -> Result<QuestId, Error> {
         let quest_id = self.in_memory_quest_service.create_quest(quest).await?;
         if let Err(e) = self.save().await {
@@ -320,11 +452,46 @@ impl QuestService for FileQuestService {
         }
         return Ok(quest_modified);
     }
+
+    async fn delete_quest(&self, id: &QuestId) -> Result<bool, Error> {
+        let quest_deleted = self.in_memory_quest_service.delete_quest(id).await?;
+        if quest_deleted && let Err(e) = self.save().await {
+            eprintln!("FileQuestService: failed to write quests to file: {}", e);
+        }
+        Ok(quest_deleted)
+    }
+
+    async fn clear_quest_contexts(&self, id: &QuestId) -> Result<(), Error> {
+        self.in_memory_quest_service.clear_quest_contexts(id).await
+    }
 }
 
+/// Selects [`QuestEntry`]s with their aggregated ratings.
+const QUEST_ENTRY_SELECT: &str = "SELECT id, name, author, official, archived, difficulty,
+        coalesce(rating_count, 0) AS rating_count, coalesce(rating_sum, 0) AS rating_sum
+    FROM quests LEFT JOIN (
+        SELECT quest_id, count(*) AS rating_count, sum(rating) AS rating_sum FROM quest_ratings GROUP BY quest_id
+    ) AS ratings ON (ratings.quest_id = quests.id)";
+
+const COMMENT_COLUMNS: &str =
+    "id, quest_id, thread, parent_id, author, body, created_at, edited_at, deleted, hidden";
+
+/// Generator tests are allowed to run at once, each of them runs the generator many times.
+const CONCURRENT_GENERATOR_TESTS: usize = 2;
+pub const MAX_GENERATOR_TEST_USERS: usize = 100;
+/// Judge test cases generated per user when testing the generator of a program quest.
+const GENERATOR_TEST_CASES: u32 = 3;
+const GENERATOR_TEST_TIME_LIMIT: Duration = Duration::from_secs(5);
+
 pub struct DatabaseQuestService {
     pool: PgPool,
     context_provider: Arc<dyn QuestContextProvider>,
+    generation_queue: Arc<GenerationQueue>,
+    generator_store: GeneratorStore,
+    generator_tests: Semaphore,
+    /// Generators uploaded by authors are only run and tested through a sandbox.
+    generator_sandbox: Option<Vec<String>>,
+    consumer_health: ConsumerHealth,
 }
 
 impl DatabaseQuestService {
@@ -332,7 +499,10 @@ impl DatabaseQuestService {
         address: S,
         db_name: S,
         credentials: Credentials,
-        context_provider: Arc<dyn QuestContextProvider>,
+        generator: Arc<dyn QuestContextProvider>,
+        generation_config: GenerationConfig,
+        generator_store: GeneratorStore,
+        nats_address: impl AsRef<str>,
     ) -> Result<Self, Error> {
         let pool = PgPoolOptions::new()
             .max_connections(20)
@@ -350,27 +520,243 @@ impl DatabaseQuestService {
 
         sqlx::migrate!().run(&pool).await?;
 
+        let generator_sandbox = generation_config.sandbox.clone();
+        let generation_queue = GenerationQueue::spawn(pool.clone(), generator, generation_config);
+        let context_provider = Arc::new(InMemoryQuestContextCache::new(generation_queue.clone()));
+
+        let consumer_health = ConsumerHealth::new();
+
+        let _join_handle = {
+            let pool = pool.clone();
+            let generation_queue = generation_queue.clone();
+            let nats_client = NatsClient::new(nats_address.as_ref()).await?;
+            let consumer_health = consumer_health.clone();
+            tokio::spawn(async move {
+                println!("NATS garbage collector started");
+                nats_client
+                    .consume::<UserEvent>(
+                        "USER_EVENTS",
+                        "quest-service".to_owned(),
+                        consumer_health,
+                        async move |envelope| {
+                            let correlation_id = envelope.correlation_id();
+                            match envelope.event {
+                                UserEvent::Deleted(user_id) => {
+                                    let mut tx = pool.begin().await?;
+                                    sqlx::query(
+                                        "UPDATE quests SET author = NULL WHERE (author = $1)",
+                                    )
+                                    .bind(user_id)
+                                    .execute(&mut *tx)
+                                    .await?;
+                                    sqlx::query("DELETE FROM quest_contexts WHERE (user_id = $1)")
+                                        .bind(user_id)
+                                        .execute(&mut *tx)
+                                        .await?;
+                                    sqlx::query("DELETE FROM generation_jobs WHERE (user_id = $1)")
+                                        .bind(user_id)
+                                        .execute(&mut *tx)
+                                        .await?;
+                                    sqlx::query("DELETE FROM prewarm_batches WHERE (user_id = $1)")
+                                        .bind(user_id)
+                                        .execute(&mut *tx)
+                                        .await?;
+                                    sqlx::query("DELETE FROM known_users WHERE (user_id = $1)")
+                                        .bind(user_id)
+                                        .execute(&mut *tx)
+                                        .await?;
+                                    sqlx::query(
+                                        "UPDATE comments SET author = NULL, body = '', deleted = true WHERE (author = $1)",
+                                    )
+                                    .bind(user_id)
+                                    .execute(&mut *tx)
+                                    .await?;
+                                    sqlx::query(
+                                        "DELETE FROM comment_reports WHERE (reporter = $1)",
+                                    )
+                                    .bind(user_id)
+                                    .execute(&mut *tx)
+                                    .await?;
+                                    sqlx::query("DELETE FROM quest_ratings WHERE (user_id = $1)")
+                                        .bind(user_id)
+                                        .execute(&mut *tx)
+                                        .await?;
+                                    sqlx::query(
+                                        "DELETE FROM quest_favourites WHERE (user_id = $1)",
+                                    )
+                                    .bind(user_id)
+                                    .execute(&mut *tx)
+                                    .await?;
+                                    outbox::enqueue_correlated(
+                                        &mut *tx,
+                                        &ErasureEvent::Completed {
+                                            user_id,
+                                            service: "quest-service".to_owned(),
+                                        },
+                                        correlation_id,
+                                    )
+                                    .await?;
+                                    tx.commit().await?;
+                                }
+                                UserEvent::Created(user_id) => {
+                                    generation_queue.prewarm_user(&user_id).await?;
+                                }
+                                UserEvent::LockedOut { .. } | UserEvent::Renamed { .. } => (),
+                            }
+                            Ok(())
+                        },
+                    )
+                    .await;
+            })
+        };
+
+        let _join_handle = {
+            let pool = pool.clone();
+            let nats_client = NatsClient::new(nats_address.as_ref()).await?;
+            let consumer_health = consumer_health.clone();
+            tokio::spawn(async move {
+                println!("NATS event worker started: StatisticsEvents");
+                nats_client
+                    .consume::<StatisticsEvent>(
+                        "STATISTICS_EVENTS",
+                        "quest-service".to_owned(),
+                        consumer_health,
+                        async move |envelope| {
+                            match envelope.event {
+                                StatisticsEvent::QuestRated {
+                                    quest_id,
+                                    difficulty,
+                                } => {
+                                    sqlx::query(
+                                        "UPDATE quests SET difficulty = $2 WHERE (id = $1)",
+                                    )
+                                    .bind(quest_id)
+                                    .bind(difficulty)
+                                    .execute(&pool)
+                                    .await?;
+                                }
+                            }
+                            Ok(())
+                        },
+                    )
+                    .await;
+            })
+        };
+
+        let _join_handle = {
+            let generation_queue = generation_queue.clone();
+            let nats_client = NatsClient::new(nats_address.as_ref()).await?;
+            let consumer_health = consumer_health.clone();
+            tokio::spawn(async move {
+                println!("NATS event worker started: QuestEvents");
+                nats_client
+                    .consume::<QuestEvent>(
+                        "QUEST_EVENTS",
+                        "quest-service".to_owned(),
+                        consumer_health,
+                        async move |envelope| {
+                            match envelope.event {
+                                QuestEvent::Created(quest_id) => {
+                                    generation_queue.prewarm_quest(&quest_id).await?;
+                                }
+                                QuestEvent::Modified(_) | QuestEvent::Deleted(_) => (),
+                            }
+                            Ok(())
+                        },
+                    )
+                    .await;
+            })
+        };
+
+        OutboxRelay::new(pool.clone(), nats_address.as_ref(), "quest-service").spawn();
+
         Ok(Self {
             pool,
             context_provider,
+            generation_queue,
+            generator_store,
+            generator_tests: Semaphore::new(CONCURRENT_GENERATOR_TESTS),
+            generator_sandbox,
+            consumer_health,
         })
     }
+
+    /// The state of the service's NATS consumers, for its `/health` route.
+    pub fn consumer_health(&self) -> ConsumerHealth {
+        self.consumer_health.clone()
+    }
 }
 
 #[async_trait]
 impl QuestService for DatabaseQuestService {
     async fn list_quests(&self) -> Result<Box<[QuestEntry]>, Error> {
         Ok(
-            sqlx::query_as::<_, QuestEntry>("SELECT id, name, author, official FROM quests")
+            sqlx::query_as::<_, QuestEntry>(&format!("{} WHERE NOT archived", QUEST_ENTRY_SELECT))
+                .fetch_all(&self.pool)
+                .await?
+                .into_boxed_slice(),
+        )
+    }
+
+    async fn list_quests_by_author(&self, author: &UserId) -> Result<Box<[QuestEntry]>, Error> {
+        Ok(
+            sqlx::query_as::<_, QuestEntry>(&format!("{} WHERE (author = $1)", QUEST_ENTRY_SELECT))
+                .bind(author)
                 .fetch_all(&self.pool)
                 .await?
                 .into_boxed_slice(),
         )
     }
 
+    async fn search_quests(&self, filter: &QuestFilter) -> Result<Box<[QuestEntry]>, Error> {
+        let mut query_builder = QueryBuilder::new(QUEST_ENTRY_SELECT);
+        query_builder.push(" WHERE TRUE");
+        if let Some(name) = &filter.name {
+            query_builder
+                .push(" AND name ILIKE ")
+                .push_bind(format!("%{}%", escape_like(name)))
+                .push(" ESCAPE '\\'");
+        }
+        if let Some(official) = filter.official {
+            query_builder.push(" AND official = ").push_bind(official);
+        }
+        if let Some(archived) = filter.archived {
+            query_builder.push(" AND archived = ").push_bind(archived);
+        }
+        if let Some(min_difficulty) = filter.min_difficulty {
+            query_builder
+                .push(" AND difficulty >= ")
+                .push_bind(min_difficulty);
+        }
+        if let Some(max_difficulty) = filter.max_difficulty {
+            query_builder
+                .push(" AND difficulty <= ")
+                .push_bind(max_difficulty);
+        }
+        if let Some(user_id) = filter.favourites_of {
+            query_builder
+                .push(" AND id IN (SELECT quest_id FROM quest_favourites WHERE user_id = ")
+                .push_bind(user_id)
+                .push(")");
+        }
+        query_builder.push(match filter.sort_by.unwrap_or(QuestSort::Name) {
+            QuestSort::Name => " ORDER BY name",
+            QuestSort::Difficulty => " ORDER BY difficulty NULLS LAST, name",
+            QuestSort::Rating => {
+                " ORDER BY rating_sum::DOUBLE PRECISION / rating_count DESC NULLS LAST, name"
+            }
+        });
+
+        Ok(query_builder
+            .build_query_as::<QuestEntry>()
+            .fetch_all(&self.pool)
+            .await?
+            .into_boxed_slice())
+    }
+
     async fn get_quest(&self, id: &QuestId) -> Result<Option<Quest>, Error> {
         Ok(sqlx::query_as::<_, Quest>(
-            "SELECT id, name, description, author, official FROM quests WHERE id = $1",
+            "SELECT id, name, description, author, official, archived, answer_mode FROM quests WHERE id = $1",
         )
         .bind(&id)
         .fetch_optional(&self.pool)
@@ -396,6 +782,26 @@ impl QuestService for DatabaseQuestService {
         )
     }
 
+    async fn get_hints(&self, id: &QuestId) -> Result<Option<Box<[Hint]>>, Error> {
+        if !self.quest_exists(id).await? {
+            return Ok(None);
+        }
+        Ok(Some(
+            sqlx::query_as::<_, (String, i32)>(
+                "SELECT text, penalty FROM quest_hints WHERE (quest_id = $1) ORDER BY position",
+            )
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|(text, penalty)| Hint {
+                text,
+                penalty: penalty as u32,
+            })
+            .collect(),
+        ))
+    }
+
     async fn get_input(
         &self,
         quest_id: &QuestId,
@@ -412,34 +818,59 @@ impl QuestService for DatabaseQuestService {
         self.context_provider.get_answer(quest_id, user_id).await
     }
 
+    async fn get_test_case(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+        index: u32,
+    ) -> Result<Option<TestCase>, Error> {
+        Ok(self
+            .context_provider
+            .get_test_case(quest_id, user_id, index)
+            .await?
+            .map(TestCase::from))
+    }
+
     async fn create_quest(&self, quest: QuestData) -> Result<QuestId, Error> {
+        let mut tx = self.pool.begin().await?;
         let id = sqlx::query_scalar::<_, QuestId>(
-            "INSERT INTO quests (name, description, author, official) VALUES ($1, $2, $3, $4) RETURNING id",
+            "INSERT INTO quests (name, description, author, official, answer_mode) VALUES ($1, $2, $3, $4, $5) RETURNING id",
         )
         .bind(quest.name)
         .bind(quest.text)
         .bind(quest.author)
         .bind(quest.official)
-        .fetch_one(&self.pool)
+        .bind(quest.answer_mode)
+        .fetch_one(&mut *tx)
         .await?;
+        replace_hints(&id, &quest.hints, &mut tx).await?;
+        outbox::enqueue(&mut *tx, &QuestEvent::Created(id)).await?;
+        tx.commit().await?;
 
         Ok(id)
     }
 
     async fn update_quest(&self, id: &QuestId, data: QuestData) -> Result<bool, Error> {
+        let mut tx = self.pool.begin().await?;
         let res = sqlx::query(
-            "UPDATE quests SET name = $2, author = $3, official = $4, description = $5 WHERE (id = $1)",
+            "UPDATE quests SET name = $2, author = $3, official = $4, description = $5, answer_mode = $6 WHERE (id = $1)",
         )
         .bind(id)
         .bind(data.name)
         .bind(data.author)
         .bind(data.official)
         .bind(data.text)
-        .execute(&self.pool)
+        .bind(data.answer_mode)
+        .execute(&mut *tx)
         .await?;
         match res.rows_affected() {
             0 => Ok(false),
-            1 => Ok(true),
+            1 => {
+                replace_hints(id, &data.hints, &mut tx).await?;
+                outbox::enqueue(&mut *tx, &QuestEvent::Modified(*id)).await?;
+                tx.commit().await?;
+                Ok(true)
+            }
             x => unreachable!(
                 "SQL 'UPDATE quests' query is constrained by primary key (id) but multiple rows ({}) were affected",
                 x
@@ -447,198 +878,1080 @@ impl QuestService for DatabaseQuestService {
         }
     }
 
-    async fn modify_quest(&self, id: &QuestId, data: PartialQuestData) -> Result<bool, Error> {
+    async fn modify_quest(&self, id: &QuestId, mut data: PartialQuestData) -> Result<bool, Error> {
         if data.is_empty() {
             return Err(Error::BadRequest);
         }
 
-        let mut query_builder = QueryBuilder::new("UPDATE quests SET ");
-        let mut separated = query_builder.separated(", ");
-        if let Some(name) = data.name {
-            separated.push("name = ").push_bind_unseparated(name);
-        }
-        if let Some(author) = data.author {
-            separated.push("author = ").push_bind_unseparated(author);
-        }
-        if let Some(official) = data.official {
-            separated
-                .push("official = ")
-                .push_bind_unseparated(official);
-        }
-        if let Some(text) = data.text {
-            separated.push("description = ").push_bind_unseparated(text);
-        }
-        query_builder.push(" WHERE id = ").push_bind(id);
-        let query = query_builder.build();
-
-        let res = query.execute(&self.pool).await?;
+        let mut tx = self.pool.begin().await?;
+        let hints = data.hints.take();
+        // Hints live in their own table, so there may be no column left to update.
+        let res = if data.is_empty() {
+            sqlx::query("SELECT 1 FROM quests WHERE (id = $1) FOR UPDATE")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?
+        } else {
+            let mut query_builder = quest_update_query(id, data);
+            query_builder.build().execute(&mut *tx).await?
+        };
         match res.rows_affected() {
             0 => Ok(false),
-            1 => Ok(true),
+            1 => {
+                if let Some(hints) = hints {
+                    replace_hints(id, &hints, &mut tx).await?;
+                }
+                outbox::enqueue(&mut *tx, &QuestEvent::Modified(*id)).await?;
+                tx.commit().await?;
+                Ok(true)
+            }
             x => unreachable!(
                 "SQL 'UPDATE quests' query is constrained by primary key (id) but multiple rows ({}) were affected",
                 x
             ),
         }
     }
-}
-
-pub struct BackendQuestService {
-    address: String,
-    client: Client,
-}
 
-impl BackendQuestService {
-    pub fn new<S: AsRef<str>>(address: S) -> Self {
-        Self {
-            address: address.as_ref().to_owned(),
-            client: Client::new(),
+    async fn delete_quest(&self, id: &QuestId) -> Result<bool, Error> {
+        let mut tx = self.pool.begin().await?;
+        let res = sqlx::query("DELETE FROM quests WHERE (id = $1)")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        match res.rows_affected() {
+            0 => Ok(false),
+            1 => {
+                outbox::enqueue(&mut *tx, &QuestEvent::Deleted(*id)).await?;
+                tx.commit().await?;
+                self.context_provider.clear_quest(id).await?;
+                Ok(true)
+            }
+            x => unreachable!(
+                "SQL 'DELETE FROM quests' query is constrained by primary key (id) but multiple rows ({}) were affected",
+                x
+            ),
         }
     }
-}
 
-#[async_trait]
-impl QuestService for BackendQuestService {
-    async fn list_quests(&self) -> Result<Box<[QuestEntry]>, Error> {
-        let response = self
-            .client
-            .get(&self.address)
-            .send()
-            .await
-            .map_err(|_| Error::ServerUnreachable)?;
-        match response.status() {
-            StatusCode::OK => response
-                .json::<Box<[QuestEntry]>>()
-                .await
-                .map_err(|_| Error::InvalidResponse),
-            _ => Err(Error::InvalidResponse),
-        }
+    async fn clear_quest_contexts(&self, id: &QuestId) -> Result<(), Error> {
+        self.context_provider.clear_quest(id).await
     }
 
-    async fn get_quest(&self, id: &QuestId) -> Result<Option<Quest>, Error> {
-        let response = self
-            .client
-            .get(format!("{}/{}", &self.address, id))
-            .send()
-            .await
-            .map_err(|_| Error::ServerUnreachable)?;
-        match response.status() {
-            StatusCode::OK => match response.json().await {
-                Ok(quest) => Ok(Some(quest)),
-                Err(_) => Err(Error::ServerUnreachable),
-            },
-            StatusCode::NOT_FOUND => Ok(None),
-            _ => Err(Error::InvalidResponse),
+    async fn regenerate_quest_contexts(&self, id: &QuestId) -> Result<bool, Error> {
+        if !self.quest_exists(id).await? {
+            return Ok(false);
         }
+        self.context_provider.clear_quest(id).await?;
+        self.generation_queue.prewarm_quest(id).await?;
+        Ok(true)
     }
 
-    async fn get_quest_author(&self, id: &QuestId) -> Result<Option<Option<UserId>>, Error> {
-        let response = self
-            .client
-            .get(format!("{}/{}/author", &self.address, id))
-            .send()
-            .await
-            .map_err(|_| Error::ServerUnreachable)?;
-        match response.status() {
-            StatusCode::OK => match response.json().await {
-                Ok(author) => Ok(Some(author)),
-                Err(_) => Err(Error::ServerUnreachable),
-            },
-            StatusCode::NOT_FOUND => Ok(None),
-            _ => Err(Error::InvalidResponse),
-        }
+    async fn list_prewarm_progress(&self) -> Result<Box<[PrewarmProgress]>, Error> {
+        self.generation_queue.list_progress().await
     }
 
-    async fn get_input(
+    async fn export_quest(&self, id: &QuestId) -> Result<Option<QuestPackage>, Error> {
+        let Some(quest) = self.get_quest(id).await? else {
+            return Ok(None);
+        };
+        let hints = self.get_hints(id).await?.unwrap_or_default().into_vec();
+        let generator = self.generator_store.read(id).await?;
+        Ok(Some(QuestPackage::new(quest, hints, generator)))
+    }
+
+    async fn test_generator(
         &self,
-        quest_id: &QuestId,
-        user_id: &UserId,
-    ) -> Result<Option<String>, Error> {
-        let response = self
-            .client
-            .get(format!("{}/{}/input/{}", &self.address, quest_id, user_id))
-            .send()
+        id: &QuestId,
+        users: usize,
+    ) -> Result<Option<GeneratorTestReport>, Error> {
+        let Some(sandbox) = self.generator_sandbox.clone() else {
+            return Err(Error::Unsupported);
+        };
+        let Some(quest) = self.get_quest(id).await? else {
+            return Ok(None);
+        };
+        let users = users.clamp(1, MAX_GENERATOR_TEST_USERS);
+        if !self.generator_store.contains(id).await? {
+            return Ok(Some(GeneratorTestReport {
+                users,
+                runs: 0,
+                unique_inputs: 0,
+                mean_runtime_ms: 0.0,
+                slowest_runtime_ms: 0.0,
+                largest_input_bytes: 0,
+                largest_answer_bytes: 0,
+                problems: vec!["the quest has no generator".to_owned()],
+            }));
+        }
+
+        let mut harness = Harness::new(self.generator_store.path(id))
+            .with_sandbox(sandbox)
+            .with_quest_id(Uuid::parse_str(&id.to_string())?)
+            .with_users(users)
+            .with_time_limit(GENERATOR_TEST_TIME_LIMIT);
+        if quest.answer_mode == AnswerMode::Program {
+            harness = harness.with_tests(GENERATOR_TEST_CASES);
+        }
+        let _permit = self
+            .generator_tests
+            .acquire()
             .await
-            .map_err(|_| Error::ServerUnreachable)?;
+            .map_err(|_| Error::IncoherentState)?;
+        // The harness blocks on the generator processes.
+        let report = task::spawn_blocking(move || harness.run())
+            .await
+            .map_err(|_| Error::IncoherentState)?;
+        Ok(Some(GeneratorTestReport {
+            users,
+            runs: report.runs,
+            unique_inputs: report.unique_inputs,
+            mean_runtime_ms: report.mean_runtime().as_secs_f64() * 1000.0,
+            slowest_runtime_ms: report.slowest.as_secs_f64() * 1000.0,
+            largest_input_bytes: report.largest_input,
+            largest_answer_bytes: report.largest_answer,
+            problems: report
+                .problems
+                .iter()
+                .map(|problem| problem.to_string())
+                .collect(),
+        }))
+    }
 
-        match response.status() {
-            StatusCode::OK => match response.text().await {
-                Ok(input) => Ok(Some(input)),
-                Err(_) => Err(Error::InvalidResponse),
-            },
-            StatusCode::NOT_FOUND => Ok(None),
-            _ => Err(Error::InvalidResponse),
+    async fn upload_generator(&self, id: &QuestId, generator: Vec<u8>) -> Result<bool, Error> {
+        if self.generator_sandbox.is_none() {
+            return Err(Error::Unsupported);
+        }
+        if !self.quest_exists(id).await? {
+            return Ok(false);
         }
+        validate_generator(&generator)?;
+        self.generator_store.write(id, &generator).await?;
+        self.context_provider.clear_quest(id).await?;
+        self.generation_queue.prewarm_quest(id).await?;
+        Ok(true)
     }
 
-    async fn get_answer(
+    async fn list_comments(
         &self,
         quest_id: &QuestId,
-        user_id: &UserId,
-    ) -> Result<Option<String>, Error> {
-        let response = self
-            .client
-            .get(format!("{}/{}/answer/{}", &self.address, quest_id, user_id))
-            .send()
-            .await
-            .map_err(|_| Error::ServerUnreachable)?;
+        thread: CommentThread,
+    ) -> Result<Box<[Comment]>, Error> {
+        Ok(sqlx::query_as::<_, Comment>(&format!(
+            "SELECT {} FROM comments WHERE (quest_id = $1 AND thread = $2) ORDER BY created_at",
+            COMMENT_COLUMNS
+        ))
+        .bind(quest_id)
+        .bind(thread)
+        .fetch_all(&self.pool)
+        .await?
+        .into_boxed_slice())
+    }
 
-        match response.status() {
-            StatusCode::OK => match response.text().await {
-                Ok(answer) => Ok(Some(answer)),
-                Err(_) => Err(Error::InvalidResponse),
-            },
-            StatusCode::NOT_FOUND => Ok(None),
-            _ => Err(Error::InvalidResponse),
-        }
+    async fn get_comment(&self, id: &CommentId) -> Result<Option<Comment>, Error> {
+        Ok(sqlx::query_as::<_, Comment>(&format!(
+            "SELECT {} FROM comments WHERE (id = $1)",
+            COMMENT_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?)
     }
 
-    async fn verify_answer(
+    async fn post_comment(
         &self,
         quest_id: &QuestId,
-        user_id: &UserId,
-        answer: &str,
-    ) -> Result<Option<bool>, Error> {
-        let response = self
-            .client
-            .post(format!("{}/{}/answer/{}", &self.address, quest_id, user_id))
-            .body(answer.to_owned())
-            .send()
-            .await
-            .map_err(|_| Error::ServerUnreachable)?;
-
-        match response.status() {
-            StatusCode::OK => match response.text().await {
-                Ok(input) => Ok(Some(
-                    input.parse::<bool>().map_err(|_| Error::InvalidResponse)?,
-                )),
-                Err(_) => Err(Error::InvalidResponse),
-            },
-            StatusCode::NOT_FOUND => Ok(None),
-            _ => Err(Error::InvalidResponse),
+        comment: NewComment,
+    ) -> Result<Option<CommentId>, Error> {
+        let body = clean_comment_body(&comment.body)?;
+        let mut tx = self.pool.begin().await?;
+        let quest_exists =
+            sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM quests WHERE (id = $1))")
+                .bind(quest_id)
+                .fetch_one(&mut *tx)
+                .await?;
+        if !quest_exists {
+            return Ok(None);
         }
-    }
+        // Replies go to their parent's thread, so solutions can't leak into the discussion.
+        let (thread, parent_author) = match &comment.parent_id {
+            Some(parent_id) => sqlx::query_as::<_, (CommentThread, Option<UserId>)>(
+                "SELECT thread, author FROM comments WHERE (id = $1 AND quest_id = $2)",
+            )
+            .bind(parent_id)
+            .bind(quest_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(Error::BadRequest)?,
+            None => (comment.thread, None),
+        };
 
-    async fn create_quest(&self, quest: QuestData) -> Result<QuestId, Error> {
-        let response = self
+        let id = CommentId::new();
+        sqlx::query(
+            "INSERT INTO comments (id, quest_id, thread, parent_id, author, body) VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(id)
+        .bind(quest_id)
+        .bind(thread)
+        .bind(comment.parent_id)
+        .bind(comment.author)
+        .bind(body)
+        .execute(&mut *tx)
+        .await?;
+        outbox::enqueue(
+            &mut *tx,
+            &CommentEvent::Posted {
+                comment_id: id,
+                quest_id: *quest_id,
+                thread,
+                author: comment.author,
+                parent_author,
+            },
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(Some(id))
+    }
+
+    async fn edit_comment(&self, id: &CommentId, body: String) -> Result<bool, Error> {
+        let body = clean_comment_body(&body)?;
+        let mut tx = self.pool.begin().await?;
+        let Some(quest_id) = sqlx::query_scalar::<_, QuestId>(
+            "UPDATE comments SET body = $2, edited_at = now() WHERE (id = $1 AND NOT deleted) RETURNING quest_id",
+        )
+        .bind(id)
+        .bind(body)
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(false);
+        };
+        outbox::enqueue(
+            &mut *tx,
+            &CommentEvent::Edited {
+                comment_id: *id,
+                quest_id,
+            },
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn delete_comment(&self, id: &CommentId) -> Result<bool, Error> {
+        let mut tx = self.pool.begin().await?;
+        let Some(quest_id) = sqlx::query_scalar::<_, QuestId>(
+            "UPDATE comments SET author = NULL, body = '', deleted = true WHERE (id = $1 AND NOT deleted) RETURNING quest_id",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(false);
+        };
+        // Nothing is left to moderate.
+        sqlx::query("DELETE FROM comment_reports WHERE (comment_id = $1)")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        outbox::enqueue(
+            &mut *tx,
+            &CommentEvent::Deleted {
+                comment_id: *id,
+                quest_id,
+            },
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn report_comment(
+        &self,
+        id: &CommentId,
+        reporter: &UserId,
+        reason: String,
+    ) -> Result<bool, Error> {
+        let reason = clean_report_reason(&reason)?;
+        let mut tx = self.pool.begin().await?;
+        let Some(quest_id) = sqlx::query_scalar::<_, QuestId>(
+            "SELECT quest_id FROM comments WHERE (id = $1 AND NOT deleted)",
+        )
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(false);
+        };
+        sqlx::query(
+            "INSERT INTO comment_reports (comment_id, reporter, reason) VALUES ($1, $2, $3)
+                ON CONFLICT (comment_id, reporter) DO UPDATE SET reason = $3, created_at = now()",
+        )
+        .bind(id)
+        .bind(reporter)
+        .bind(reason)
+        .execute(&mut *tx)
+        .await?;
+        outbox::enqueue(
+            &mut *tx,
+            &CommentEvent::Reported {
+                comment_id: *id,
+                quest_id,
+                reporter: *reporter,
+            },
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn set_comment_hidden(&self, id: &CommentId, hidden: bool) -> Result<bool, Error> {
+        let mut tx = self.pool.begin().await?;
+        let Some(quest_id) = sqlx::query_scalar::<_, QuestId>(
+            "UPDATE comments SET hidden = $2 WHERE (id = $1) RETURNING quest_id",
+        )
+        .bind(id)
+        .bind(hidden)
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(false);
+        };
+        sqlx::query("DELETE FROM comment_reports WHERE (comment_id = $1)")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        outbox::enqueue(
+            &mut *tx,
+            &CommentEvent::Hidden {
+                comment_id: *id,
+                quest_id,
+                hidden,
+            },
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn dismiss_comment_reports(&self, id: &CommentId) -> Result<bool, Error> {
+        let mut tx = self.pool.begin().await?;
+        let exists =
+            sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM comments WHERE (id = $1))")
+                .bind(id)
+                .fetch_one(&mut *tx)
+                .await?;
+        sqlx::query("DELETE FROM comment_reports WHERE (comment_id = $1)")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(exists)
+    }
+
+    async fn list_reported_comments(&self) -> Result<Box<[ReportedComment]>, Error> {
+        Ok(sqlx::query_as::<_, ReportedComment>(
+            "SELECT comments.id, quest_id, thread, parent_id, author, body, comments.created_at, edited_at, deleted, hidden,
+                    array_agg(reason ORDER BY comment_reports.created_at) AS reasons,
+                    max(comment_reports.created_at) AS last_reported_at
+                FROM comments JOIN comment_reports ON (comment_reports.comment_id = comments.id)
+                GROUP BY comments.id
+                ORDER BY last_reported_at DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_boxed_slice())
+    }
+
+    async fn export_comments(&self, user_id: &UserId) -> Result<CommentExport, Error> {
+        let comments = sqlx::query_as::<_, Comment>(&format!(
+            "SELECT {} FROM comments WHERE (author = $1) ORDER BY created_at",
+            COMMENT_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let reports = sqlx::query_as::<_, FiledReport>(
+            "SELECT comment_id, reason, created_at FROM comment_reports WHERE (reporter = $1) ORDER BY created_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(CommentExport { comments, reports })
+    }
+
+    async fn rate_quest(&self, quest_id: &QuestId, rating: RatingData) -> Result<bool, Error> {
+        let rating = rating.clean()?;
+        let res = sqlx::query(
+            "INSERT INTO quest_ratings (quest_id, user_id, rating, feedback)
+                SELECT id, $2, $3, $4 FROM quests WHERE (id = $1)
+                ON CONFLICT (quest_id, user_id) DO UPDATE SET rating = $3, feedback = $4, rated_at = now()",
+        )
+        .bind(quest_id)
+        .bind(rating.user_id)
+        .bind(rating.rating)
+        .bind(rating.feedback)
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected() > 0)
+    }
+
+    async fn get_rating(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+    ) -> Result<Option<QuestRating>, Error> {
+        Ok(sqlx::query_as::<_, QuestRating>(
+            "SELECT user_id, rating, feedback, rated_at FROM quest_ratings WHERE (quest_id = $1 AND user_id = $2)",
+        )
+        .bind(quest_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn list_ratings(&self, quest_id: &QuestId) -> Result<Box<[QuestRating]>, Error> {
+        Ok(sqlx::query_as::<_, QuestRating>(
+            "SELECT user_id, rating, feedback, rated_at FROM quest_ratings WHERE (quest_id = $1) ORDER BY rated_at DESC",
+        )
+        .bind(quest_id)
+        .fetch_all(&self.pool)
+        .await?
+        .into_boxed_slice())
+    }
+
+    async fn list_user_ratings(&self, user_id: &UserId) -> Result<Box<[UserRating]>, Error> {
+        Ok(sqlx::query_as::<_, UserRating>(
+            "SELECT quest_id, rating, feedback, rated_at FROM quest_ratings WHERE (user_id = $1) ORDER BY rated_at DESC",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?
+        .into_boxed_slice())
+    }
+
+    async fn set_favourite(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+        favourite: bool,
+    ) -> Result<bool, Error> {
+        if !self.quest_exists(quest_id).await? {
+            return Ok(false);
+        }
+        let query = match favourite {
+            true => {
+                "INSERT INTO quest_favourites (user_id, quest_id) VALUES ($1, $2) ON CONFLICT DO NOTHING"
+            }
+            false => "DELETE FROM quest_favourites WHERE (user_id = $1 AND quest_id = $2)",
+        };
+        sqlx::query(query)
+            .bind(user_id)
+            .bind(quest_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(true)
+    }
+
+    async fn is_favourite(&self, quest_id: &QuestId, user_id: &UserId) -> Result<bool, Error> {
+        Ok(sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM quest_favourites WHERE (user_id = $1 AND quest_id = $2))",
+        )
+        .bind(user_id)
+        .bind(quest_id)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn list_favourites(&self, user_id: &UserId) -> Result<Box<[Favourite]>, Error> {
+        Ok(sqlx::query_as::<_, Favourite>(
+            "SELECT quest_id, created_at FROM quest_favourites WHERE (user_id = $1) ORDER BY created_at DESC",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?
+        .into_boxed_slice())
+    }
+
+    async fn import_quest(&self, package: QuestPackage) -> Result<QuestId, Error> {
+        package.validate()?;
+        let id = package.manifest.id;
+        let previous_generator = self.generator_store.read(&id).await?;
+        let generator_changed = previous_generator != package.generator;
+        // Installed first, so contexts generated for the new quest come from the new generator.
+        if generator_changed {
+            self.generator_store
+                .replace(&id, package.generator.as_deref())
+                .await?;
+        }
+
+        let data = package.to_quest_data();
+        let result = async {
+            let mut tx = self.pool.begin().await?;
+            // Authors belong to instances, imported quests keep theirs or have none.
+            let created = sqlx::query_scalar::<_, bool>(
+                "INSERT INTO quests (id, name, description, official, answer_mode) VALUES ($1, $2, $3, $4, $5)
+                    ON CONFLICT (id) DO UPDATE SET name = $2, description = $3, official = $4, answer_mode = $5
+                    RETURNING (xmax = 0)",
+            )
+            .bind(id)
+            .bind(data.name)
+            .bind(data.text)
+            .bind(data.official)
+            .bind(data.answer_mode)
+            .fetch_one(&mut *tx)
+            .await?;
+            replace_hints(&id, &data.hints, &mut tx).await?;
+            let event = match created {
+                true => QuestEvent::Created(id),
+                false => QuestEvent::Modified(id),
+            };
+            outbox::enqueue(&mut *tx, &event).await?;
+            tx.commit().await?;
+            Ok::<_, Error>(created)
+        }
+        .await;
+        let created = match result {
+            Ok(created) => created,
+            // The quest is unchanged, so its generator must be too.
+            Err(e) if generator_changed => {
+                self.generator_store
+                    .replace(&id, previous_generator.as_deref())
+                    .await?;
+                return Err(e);
+            }
+            Err(e) => return Err(e),
+        };
+
+        if !created && generator_changed {
+            self.context_provider.clear_quest(&id).await?;
+        }
+        Ok(id)
+    }
+}
+
+/// Builds the `UPDATE` for the quest columns set in `data`, which mustn't be empty.
+fn quest_update_query(id: &QuestId, data: PartialQuestData) -> QueryBuilder<'static, Postgres> {
+    let mut query_builder = QueryBuilder::new("UPDATE quests SET ");
+    let mut separated = query_builder.separated(", ");
+    if let Some(name) = data.name {
+        separated.push("name = ").push_bind_unseparated(name);
+    }
+    if let Some(author) = data.author {
+        separated.push("author = ").push_bind_unseparated(author);
+    }
+    if let Some(official) = data.official {
+        separated
+            .push("official = ")
+            .push_bind_unseparated(official);
+    }
+    if let Some(archived) = data.archived {
+        separated
+            .push("archived = ")
+            .push_bind_unseparated(archived);
+        // Archiving or restoring a quest by hand overrides what seeding did.
+        separated.push("seed_archived = false");
+    }
+    if let Some(text) = data.text {
+        separated.push("description = ").push_bind_unseparated(text);
+    }
+    if let Some(answer_mode) = data.answer_mode {
+        separated
+            .push("answer_mode = ")
+            .push_bind_unseparated(answer_mode);
+    }
+    query_builder.push(" WHERE id = ").push_bind(*id);
+    query_builder
+}
+
+/// Replaces the quest's hints with `hints`, in order.
+async fn replace_hints(id: &QuestId, hints: &[Hint], conn: &mut PgConnection) -> Result<(), Error> {
+    sqlx::query("DELETE FROM quest_hints WHERE (quest_id = $1)")
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(
+        "INSERT INTO quest_hints (quest_id, position, text, penalty)
+            SELECT $1, position - 1, text, penalty
+            FROM UNNEST($2::TEXT[], $3::INTEGER[]) WITH ORDINALITY AS hints (text, penalty, position)",
+    )
+    .bind(id)
+    .bind(hints.iter().map(|hint| hint.text.clone()).collect::<Vec<_>>())
+    .bind(
+        hints
+            .iter()
+            .map(|hint| hint.penalty as i32)
+            .collect::<Vec<_>>(),
+    )
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+pub struct BackendQuestService {
+    address: String,
+    client: Client,
+}
+
+impl BackendQuestService {
+    pub fn new<S: AsRef<str>>(address: S) -> Self {
+        Self {
+            address: address.as_ref().to_owned(),
+            client: Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl QuestService for BackendQuestService {
+    async fn list_quests(&self) -> Result<Box<[QuestEntry]>, Error> {
+        let response = self
+            .client
+            .get(&self.address)
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Box<[QuestEntry]>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn list_quests_by_author(&self, author: &UserId) -> Result<Box<[QuestEntry]>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/by-author/{}", &self.address, author))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Box<[QuestEntry]>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn search_quests(&self, filter: &QuestFilter) -> Result<Box<[QuestEntry]>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/search", &self.address))
+            .query(filter)
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Box<[QuestEntry]>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn get_quest(&self, id: &QuestId) -> Result<Option<Quest>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}", &self.address, id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+        match response.status() {
+            StatusCode::OK => match response.json().await {
+                Ok(quest) => Ok(Some(quest)),
+                Err(_) => Err(Error::ServerUnreachable),
+            },
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn get_hints(&self, id: &QuestId) -> Result<Option<Box<[Hint]>>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}/hints", &self.address, id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Box<[Hint]>>()
+                .await
+                .map(Some)
+                .map_err(|_| Error::InvalidResponse),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn get_quest_author(&self, id: &QuestId) -> Result<Option<Option<UserId>>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}/author", &self.address, id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+        match response.status() {
+            StatusCode::OK => match response.json().await {
+                Ok(author) => Ok(Some(author)),
+                Err(_) => Err(Error::ServerUnreachable),
+            },
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn get_input(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+    ) -> Result<Option<String>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}/input/{}", &self.address, quest_id, user_id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => match response.text().await {
+                Ok(input) => Ok(Some(input)),
+                Err(_) => Err(Error::InvalidResponse),
+            },
+            StatusCode::NOT_FOUND => Ok(None),
+            StatusCode::SERVICE_UNAVAILABLE => Err(Error::QuestContextPending),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn get_answer(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+    ) -> Result<Option<String>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}/answer/{}", &self.address, quest_id, user_id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => match response.text().await {
+                Ok(answer) => Ok(Some(answer)),
+                Err(_) => Err(Error::InvalidResponse),
+            },
+            StatusCode::NOT_FOUND => Ok(None),
+            StatusCode::SERVICE_UNAVAILABLE => Err(Error::QuestContextPending),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn verify_answer(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+        answer: &str,
+    ) -> Result<Option<bool>, Error> {
+        let response = self
+            .client
+            .post(format!("{}/{}/answer/{}", &self.address, quest_id, user_id))
+            .body(answer.to_owned())
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => match response.text().await {
+                Ok(input) => Ok(Some(
+                    input.parse::<bool>().map_err(|_| Error::InvalidResponse)?,
+                )),
+                Err(_) => Err(Error::InvalidResponse),
+            },
+            StatusCode::NOT_FOUND => Ok(None),
+            StatusCode::SERVICE_UNAVAILABLE => Err(Error::QuestContextPending),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn get_test_case(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+        index: u32,
+    ) -> Result<Option<TestCase>, Error> {
+        let response = self
+            .client
+            .get(format!(
+                "{}/{}/tests/{}/{}",
+                &self.address, quest_id, user_id, index
+            ))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<TestCase>()
+                .await
+                .map(Some)
+                .map_err(|_| Error::InvalidResponse),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn create_quest(&self, quest: QuestData) -> Result<QuestId, Error> {
+        let response = self
+            .client
+            .post(format!("{}", &self.address))
+            .json(&quest)
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => Ok(QuestId::try_parse(response.text().await?)?),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn update_quest(&self, id: &QuestId, data: QuestData) -> Result<bool, Error> {
+        let response = self
+            .client
+            .put(format!("{}/{}", &self.address, id))
+            .json(&data)
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn modify_quest(&self, id: &QuestId, data: PartialQuestData) -> Result<bool, Error> {
+        let response = self
+            .client
+            .patch(format!("{}/{}", &self.address, id))
+            .json(&data)
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            StatusCode::BAD_REQUEST => Err(Error::BadRequest),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn delete_quest(&self, id: &QuestId) -> Result<bool, Error> {
+        let response = self
+            .client
+            .delete(format!("{}/{}", &self.address, id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn clear_quest_contexts(&self, id: &QuestId) -> Result<(), Error> {
+        let response = self
+            .client
+            .delete(format!("{}/{}/contexts", &self.address, id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(()),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn regenerate_quest_contexts(&self, id: &QuestId) -> Result<bool, Error> {
+        let response = self
+            .client
+            .post(format!("{}/{}/contexts/regenerate", &self.address, id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn list_prewarm_progress(&self) -> Result<Box<[PrewarmProgress]>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/contexts/prewarm", &self.address))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Box<[PrewarmProgress]>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn export_quest(&self, id: &QuestId) -> Result<Option<QuestPackage>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}/package", &self.address, id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<QuestPackage>()
+                .await
+                .map(Some)
+                .map_err(|_| Error::InvalidResponse),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn test_generator(
+        &self,
+        id: &QuestId,
+        users: usize,
+    ) -> Result<Option<GeneratorTestReport>, Error> {
+        let response = self
+            .client
+            .post(format!(
+                "{}/{}/generator/test?users={}",
+                &self.address, id, users
+            ))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<GeneratorTestReport>()
+                .await
+                .map(Some)
+                .map_err(|_| Error::InvalidResponse),
+            StatusCode::NOT_FOUND => Ok(None),
+            StatusCode::NOT_IMPLEMENTED => Err(Error::Unsupported),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn upload_generator(&self, id: &QuestId, generator: Vec<u8>) -> Result<bool, Error> {
+        let response = self
             .client
-            .post(format!("{}", &self.address))
-            .json(&quest)
+            .put(format!("{}/{}/generator", &self.address, id))
+            .body(generator)
             .send()
             .await
             .map_err(|_| Error::ServerUnreachable)?;
 
         match response.status() {
-            StatusCode::OK => Ok(QuestId::try_parse(response.text().await?)?),
+            StatusCode::OK => match response.text().await {
+                Ok(uploaded) => uploaded.parse::<bool>().map_err(|_| Error::InvalidResponse),
+                Err(_) => Err(Error::InvalidResponse),
+            },
+            StatusCode::BAD_REQUEST => Err(Error::InvalidQuestPackage(
+                response.text().await.map_err(|_| Error::InvalidResponse)?,
+            )),
+            StatusCode::NOT_IMPLEMENTED => Err(Error::Unsupported),
             _ => Err(Error::InvalidResponse),
         }
     }
 
-    async fn update_quest(&self, id: &QuestId, data: QuestData) -> Result<bool, Error> {
+    async fn list_comments(
+        &self,
+        quest_id: &QuestId,
+        thread: CommentThread,
+    ) -> Result<Box<[Comment]>, Error> {
         let response = self
             .client
-            .put(format!("{}/{}", &self.address, id))
-            .json(&data)
+            .get(format!("{}/{}/comments", &self.address, quest_id))
+            .query(&[("thread", thread)])
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Box<[Comment]>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn get_comment(&self, id: &CommentId) -> Result<Option<Comment>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/comments/{}", &self.address, id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Comment>()
+                .await
+                .map(Some)
+                .map_err(|_| Error::InvalidResponse),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn post_comment(
+        &self,
+        quest_id: &QuestId,
+        comment: NewComment,
+    ) -> Result<Option<CommentId>, Error> {
+        let response = self
+            .client
+            .post(format!("{}/{}/comments", &self.address, quest_id))
+            .json(&comment)
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<CommentId>()
+                .await
+                .map(Some)
+                .map_err(|_| Error::InvalidResponse),
+            StatusCode::NOT_FOUND => Ok(None),
+            StatusCode::BAD_REQUEST => Err(Error::BadRequest),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn edit_comment(&self, id: &CommentId, body: String) -> Result<bool, Error> {
+        let response = self
+            .client
+            .put(format!("{}/comments/{}", &self.address, id))
+            .json(&body)
             .send()
             .await
             .map_err(|_| Error::ServerUnreachable)?;
@@ -646,15 +1959,39 @@ impl QuestService for BackendQuestService {
         match response.status() {
             StatusCode::NO_CONTENT => Ok(true),
             StatusCode::NOT_FOUND => Ok(false),
+            StatusCode::BAD_REQUEST => Err(Error::BadRequest),
             _ => Err(Error::InvalidResponse),
         }
     }
 
-    async fn modify_quest(&self, id: &QuestId, data: PartialQuestData) -> Result<bool, Error> {
+    async fn delete_comment(&self, id: &CommentId) -> Result<bool, Error> {
         let response = self
             .client
-            .patch(format!("{}/{}", &self.address, id))
-            .json(&data)
+            .delete(format!("{}/comments/{}", &self.address, id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn report_comment(
+        &self,
+        id: &CommentId,
+        reporter: &UserId,
+        reason: String,
+    ) -> Result<bool, Error> {
+        let response = self
+            .client
+            .post(format!("{}/comments/{}/reports", &self.address, id))
+            .json(&CommentReport {
+                reporter: *reporter,
+                reason,
+            })
             .send()
             .await
             .map_err(|_| Error::ServerUnreachable)?;
@@ -666,94 +2003,228 @@ impl QuestService for BackendQuestService {
             _ => Err(Error::InvalidResponse),
         }
     }
-}
 
-pub struct QuestServiceNatsWrapper {
-    quest_service: Arc<dyn QuestService>,
-    nats_client: NatsClient,
-}
+    async fn set_comment_hidden(&self, id: &CommentId, hidden: bool) -> Result<bool, Error> {
+        let response = self
+            .client
+            .put(format!("{}/comments/{}/hidden", &self.address, id))
+            .json(&hidden)
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
 
-impl QuestServiceNatsWrapper {
-    pub async fn new(
-        quest_service: Arc<dyn QuestService>,
-        nats_address: impl AsRef<str>,
-    ) -> Result<Self, Error> {
-        let nats_client = NatsClient::new(nats_address).await?;
-        Ok(Self {
-            quest_service,
-            nats_client,
-        })
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            _ => Err(Error::InvalidResponse),
+        }
     }
-}
 
-#[async_trait]
-impl QuestService for QuestServiceNatsWrapper {
-    async fn list_quests(&self) -> Result<Box<[QuestEntry]>, Error> {
-        self.quest_service.list_quests().await
+    async fn dismiss_comment_reports(&self, id: &CommentId) -> Result<bool, Error> {
+        let response = self
+            .client
+            .delete(format!("{}/comments/{}/reports", &self.address, id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            _ => Err(Error::InvalidResponse),
+        }
     }
 
-    async fn get_quest(&self, id: &QuestId) -> Result<Option<Quest>, Error> {
-        self.quest_service.get_quest(id).await
+    async fn list_reported_comments(&self) -> Result<Box<[ReportedComment]>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/comments/reported", &self.address))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Box<[ReportedComment]>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
     }
 
-    async fn get_quest_author(&self, id: &QuestId) -> Result<Option<Option<UserId>>, Error> {
-        self.quest_service.get_quest_author(id).await
+    async fn export_comments(&self, user_id: &UserId) -> Result<CommentExport, Error> {
+        let response = self
+            .client
+            .get(format!("{}/comments/by-author/{}", &self.address, user_id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<CommentExport>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
     }
 
-    async fn quest_exists(&self, id: &QuestId) -> Result<bool, Error> {
-        self.quest_service.quest_exists(id).await
+    async fn rate_quest(&self, quest_id: &QuestId, rating: RatingData) -> Result<bool, Error> {
+        let response = self
+            .client
+            .put(format!("{}/{}/ratings", &self.address, quest_id))
+            .json(&rating)
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            StatusCode::BAD_REQUEST => Err(Error::BadRequest),
+            _ => Err(Error::InvalidResponse),
+        }
     }
 
-    async fn get_input(
+    async fn get_rating(
         &self,
         quest_id: &QuestId,
         user_id: &UserId,
-    ) -> Result<Option<String>, Error> {
-        self.quest_service.get_input(quest_id, user_id).await
+    ) -> Result<Option<QuestRating>, Error> {
+        let response = self
+            .client
+            .get(format!(
+                "{}/{}/ratings/{}",
+                &self.address, quest_id, user_id
+            ))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<QuestRating>()
+                .await
+                .map(Some)
+                .map_err(|_| Error::InvalidResponse),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(Error::InvalidResponse),
+        }
     }
 
-    async fn get_answer(
-        &self,
-        quest_id: &QuestId,
-        user_id: &UserId,
-    ) -> Result<Option<String>, Error> {
-        self.quest_service.get_answer(quest_id, user_id).await
+    async fn list_ratings(&self, quest_id: &QuestId) -> Result<Box<[QuestRating]>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}/ratings", &self.address, quest_id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Box<[QuestRating]>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
     }
 
-    async fn verify_answer(
+    async fn list_user_ratings(&self, user_id: &UserId) -> Result<Box<[UserRating]>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/ratings/by-user/{}", &self.address, user_id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Box<[UserRating]>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn set_favourite(
         &self,
         quest_id: &QuestId,
         user_id: &UserId,
-        answer: &str,
-    ) -> Result<Option<bool>, Error> {
-        self.quest_service
-            .verify_answer(quest_id, user_id, answer)
+        favourite: bool,
+    ) -> Result<bool, Error> {
+        let response = self
+            .client
+            .put(format!(
+                "{}/{}/favourites/{}",
+                &self.address, quest_id, user_id
+            ))
+            .json(&favourite)
+            .send()
             .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            _ => Err(Error::InvalidResponse),
+        }
     }
 
-    async fn create_quest(&self, quest: QuestData) -> Result<QuestId, Error> {
-        let quest_id = self.quest_service.create_quest(quest).await?;
-        self.nats_client.emit(QuestEvent::Created(quest_id)).await?;
-        return Ok(quest_id);
+    async fn is_favourite(&self, quest_id: &QuestId, user_id: &UserId) -> Result<bool, Error> {
+        let response = self
+            .client
+            .get(format!(
+                "{}/{}/favourites/{}",
+                &self.address, quest_id, user_id
+            ))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<bool>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
     }
 
-    async fn update_quest(&self, id: &QuestId, data: QuestData) -> Result<bool, Error> {
-        let quest_modified = self.quest_service.update_quest(id, data).await?;
-        if quest_modified {
-            self.nats_client
-                .emit(QuestEvent::Modified(id.clone()))
-                .await?;
+    async fn list_favourites(&self, user_id: &UserId) -> Result<Box<[Favourite]>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/favourites/by-user/{}", &self.address, user_id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Box<[Favourite]>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
         }
-        return Ok(quest_modified);
     }
 
-    async fn modify_quest(&self, id: &QuestId, data: PartialQuestData) -> Result<bool, Error> {
-        let quest_modified = self.quest_service.modify_quest(id, data).await?;
-        if quest_modified {
-            self.nats_client
-                .emit(QuestEvent::Modified(id.clone()))
-                .await?;
+    async fn import_quest(&self, package: QuestPackage) -> Result<QuestId, Error> {
+        let response = self
+            .client
+            .post(format!("{}/packages", &self.address))
+            .json(&package)
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<QuestId>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            StatusCode::BAD_REQUEST => Err(Error::InvalidQuestPackage(
+                response.text().await.map_err(|_| Error::InvalidResponse)?,
+            )),
+            _ => Err(Error::InvalidResponse),
         }
-        return Ok(quest_modified);
     }
 }
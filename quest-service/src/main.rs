@@ -1,16 +1,27 @@
-use std::{env, sync::Arc};
+use std::{env, io, sync::Arc, time::Duration};
 
 use codequest_common::{
-    Credentials, Error, PartialQuestData, Quest, QuestData, QuestEntry, QuestId, UserId,
-    load_secret_key, services::QuestService,
+    Credentials, Error, GeneratorTestReport, Hint, PartialQuestData, PrewarmProgress, Quest,
+    QuestData, QuestEntry, QuestFilter, QuestId, UserId,
+    comment::{
+        Comment, CommentExport, CommentId, CommentReport, CommentThread, NewComment,
+        ReportedComment,
+    },
+    judge::TestCase,
+    load_secret_key,
+    nats::ConsumerHealth,
+    package::{MAX_GENERATOR_SIZE, QuestPackage},
+    rating::{Favourite, QuestRating, RatingData, UserRating},
+    services::QuestService,
 };
 use codequest_quest_service::{
-    DatabaseQuestService, QuestServiceNatsWrapper,
-    quest_context::{InMemoryQuestContextCache, QuestContextGenerator},
+    BackendQuestService, DatabaseQuestService, generation::GenerationConfig,
+    generator_store::GeneratorStore, quest_context::QuestContextGenerator,
 };
 use dotenv::dotenv;
 use rocket::{
     State, catchers,
+    data::{Data, Limits, ToByteUnit as _},
     response::{
         content::{RawJson, RawText},
         status,
@@ -22,6 +33,10 @@ use rocket::{
 mod defaults {
     pub const SECRET_KEY_FILE: &'static str = "./secrets/secret_key";
     pub const PORT: u16 = 8000;
+    pub const GENERATOR_DIR: &str = "./quests/generators";
+    pub const OFFICIAL_QUESTS_DIR: &str = "./quests/official";
+    pub const GENERATOR_TEST_USERS: usize = 20;
+    pub const GENERATOR_SANDBOX_FILE: &str = "./quests/sandbox.json";
 }
 
 #[rocket::get("/")]
@@ -31,6 +46,22 @@ async fn list_quests(
     quest_service.list_quests().await.map(|quests| Json(quests))
 }
 
+#[rocket::get("/by-author/<author>", rank = 2)]
+async fn list_quests_by_author(
+    author: UserId,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Json<Box<[QuestEntry]>>, Error> {
+    quest_service.list_quests_by_author(&author).await.map(Json)
+}
+
+#[rocket::get("/search?<filter..>")]
+async fn search_quests(
+    filter: QuestFilter,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Json<Box<[QuestEntry]>>, Error> {
+    quest_service.search_quests(&filter).await.map(Json)
+}
+
 #[rocket::get("/<id>")]
 async fn get_quest(
     id: QuestId,
@@ -43,6 +74,18 @@ async fn get_quest(
         .ok_or(status::NotFound(RawJson(""))))
 }
 
+#[rocket::get("/<id>/hints")]
+async fn get_hints(
+    id: QuestId,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<Json<Box<[Hint]>>, status::NotFound<RawJson<&'static str>>>, Error> {
+    Ok(quest_service
+        .get_hints(&id)
+        .await?
+        .map(Json)
+        .ok_or(status::NotFound(RawJson(""))))
+}
+
 #[rocket::get("/<id>/author")]
 async fn get_quest_author(
     id: QuestId,
@@ -93,6 +136,20 @@ async fn verify_answer(
         .ok_or(status::NotFound(RawText(""))))
 }
 
+#[rocket::get("/<quest_id>/tests/<user_id>/<index>")]
+async fn get_test_case(
+    quest_id: QuestId,
+    user_id: UserId,
+    index: u32,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<Json<TestCase>, status::NotFound<RawJson<&'static str>>>, Error> {
+    Ok(quest_service
+        .get_test_case(&quest_id, &user_id, index)
+        .await?
+        .map(Json)
+        .ok_or(status::NotFound(RawJson(""))))
+}
+
 #[rocket::post("/", data = "<data>")]
 async fn create_quest(
     data: Json<QuestData>,
@@ -128,15 +185,347 @@ async fn modify_quest(
     })
 }
 
+#[rocket::delete("/<id>")]
+async fn delete_quest(
+    id: QuestId,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<status::NoContent, status::NotFound<()>>, Error> {
+    Ok(match quest_service.delete_quest(&id).await? {
+        true => Ok(status::NoContent),
+        false => Err(status::NotFound(())),
+    })
+}
+
+#[rocket::delete("/<id>/contexts")]
+async fn clear_quest_contexts(
+    id: QuestId,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<status::NoContent, Error> {
+    quest_service
+        .clear_quest_contexts(&id)
+        .await
+        .map(|_| status::NoContent)
+}
+
+#[rocket::post("/<id>/contexts/regenerate")]
+async fn regenerate_quest_contexts(
+    id: QuestId,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<status::NoContent, status::NotFound<()>>, Error> {
+    Ok(match quest_service.regenerate_quest_contexts(&id).await? {
+        true => Ok(status::NoContent),
+        false => Err(status::NotFound(())),
+    })
+}
+
+#[rocket::get("/contexts/prewarm")]
+async fn list_prewarm_progress(
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Json<Box<[PrewarmProgress]>>, Error> {
+    quest_service.list_prewarm_progress().await.map(Json)
+}
+
+#[rocket::get("/<id>/package")]
+async fn export_quest(
+    id: QuestId,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<Json<QuestPackage>, status::NotFound<()>>, Error> {
+    Ok(quest_service
+        .export_quest(&id)
+        .await?
+        .map(Json)
+        .ok_or(status::NotFound(())))
+}
+
+#[rocket::post("/packages", data = "<package>")]
+async fn import_quest(
+    package: Json<QuestPackage>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Json<QuestId>, Error> {
+    quest_service.import_quest(package.0).await.map(Json)
+}
+
+#[rocket::post("/<id>/generator/test?<users>")]
+async fn test_generator(
+    id: QuestId,
+    users: Option<usize>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<Json<GeneratorTestReport>, status::NotFound<()>>, Error> {
+    Ok(quest_service
+        .test_generator(&id, users.unwrap_or(defaults::GENERATOR_TEST_USERS))
+        .await?
+        .map(Json)
+        .ok_or(status::NotFound(())))
+}
+
+#[rocket::put("/<id>/generator", data = "<generator>")]
+async fn upload_generator(
+    id: QuestId,
+    generator: Data<'_>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<String, Error> {
+    // One byte more than allowed, so oversized generators are rejected instead of cut off.
+    let generator = generator
+        .open((MAX_GENERATOR_SIZE + 1).bytes())
+        .into_bytes()
+        .await?;
+    quest_service
+        .upload_generator(&id, generator.into_inner())
+        .await
+        .map(|uploaded| uploaded.to_string())
+}
+
+#[rocket::get("/<id>/comments?<thread>")]
+async fn list_comments(
+    id: QuestId,
+    thread: CommentThread,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Json<Box<[Comment]>>, Error> {
+    quest_service.list_comments(&id, thread).await.map(Json)
+}
+
+#[rocket::post("/<id>/comments", data = "<comment>")]
+async fn post_comment(
+    id: QuestId,
+    comment: Json<NewComment>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<Json<CommentId>, status::NotFound<()>>, Error> {
+    Ok(quest_service
+        .post_comment(&id, comment.0)
+        .await?
+        .map(Json)
+        .ok_or(status::NotFound(())))
+}
+
+// The comment routes are ranked below the quest routes they'd collide with otherwise.
+#[rocket::get("/comments/<id>", rank = 2)]
+async fn get_comment(
+    id: CommentId,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<Json<Comment>, status::NotFound<()>>, Error> {
+    Ok(quest_service
+        .get_comment(&id)
+        .await?
+        .map(Json)
+        .ok_or(status::NotFound(())))
+}
+
+#[rocket::put("/comments/<id>", data = "<body>", rank = 2)]
+async fn edit_comment(
+    id: CommentId,
+    body: Json<String>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<status::NoContent, status::NotFound<()>>, Error> {
+    Ok(match quest_service.edit_comment(&id, body.0).await? {
+        true => Ok(status::NoContent),
+        false => Err(status::NotFound(())),
+    })
+}
+
+#[rocket::delete("/comments/<id>", rank = 2)]
+async fn delete_comment(
+    id: CommentId,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<status::NoContent, status::NotFound<()>>, Error> {
+    Ok(match quest_service.delete_comment(&id).await? {
+        true => Ok(status::NoContent),
+        false => Err(status::NotFound(())),
+    })
+}
+
+#[rocket::post("/comments/<id>/reports", data = "<report>", rank = 2)]
+async fn report_comment(
+    id: CommentId,
+    report: Json<CommentReport>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<status::NoContent, status::NotFound<()>>, Error> {
+    let report = report.0;
+    Ok(
+        match quest_service
+            .report_comment(&id, &report.reporter, report.reason)
+            .await?
+        {
+            true => Ok(status::NoContent),
+            false => Err(status::NotFound(())),
+        },
+    )
+}
+
+#[rocket::delete("/comments/<id>/reports", rank = 2)]
+async fn dismiss_comment_reports(
+    id: CommentId,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<status::NoContent, status::NotFound<()>>, Error> {
+    Ok(match quest_service.dismiss_comment_reports(&id).await? {
+        true => Ok(status::NoContent),
+        false => Err(status::NotFound(())),
+    })
+}
+
+#[rocket::put("/comments/<id>/hidden", data = "<hidden>", rank = 2)]
+async fn set_comment_hidden(
+    id: CommentId,
+    hidden: Json<bool>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<status::NoContent, status::NotFound<()>>, Error> {
+    Ok(
+        match quest_service.set_comment_hidden(&id, hidden.0).await? {
+            true => Ok(status::NoContent),
+            false => Err(status::NotFound(())),
+        },
+    )
+}
+
+#[rocket::get("/comments/reported")]
+async fn list_reported_comments(
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Json<Box<[ReportedComment]>>, Error> {
+    quest_service.list_reported_comments().await.map(Json)
+}
+
+#[rocket::get("/comments/by-author/<author>", rank = 2)]
+async fn export_comments(
+    author: UserId,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Json<CommentExport>, Error> {
+    quest_service.export_comments(&author).await.map(Json)
+}
+
+#[rocket::put("/<id>/ratings", data = "<rating>")]
+async fn rate_quest(
+    id: QuestId,
+    rating: Json<RatingData>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<status::NoContent, status::NotFound<()>>, Error> {
+    Ok(match quest_service.rate_quest(&id, rating.0).await? {
+        true => Ok(status::NoContent),
+        false => Err(status::NotFound(())),
+    })
+}
+
+#[rocket::get("/<id>/ratings")]
+async fn list_ratings(
+    id: QuestId,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Json<Box<[QuestRating]>>, Error> {
+    quest_service.list_ratings(&id).await.map(Json)
+}
+
+#[rocket::get("/ratings/by-user/<user_id>", rank = 2)]
+async fn list_user_ratings(
+    user_id: UserId,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Json<Box<[UserRating]>>, Error> {
+    quest_service.list_user_ratings(&user_id).await.map(Json)
+}
+
+#[rocket::get("/<id>/ratings/<user_id>")]
+async fn get_rating(
+    id: QuestId,
+    user_id: UserId,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<Json<QuestRating>, status::NotFound<()>>, Error> {
+    Ok(quest_service
+        .get_rating(&id, &user_id)
+        .await?
+        .map(Json)
+        .ok_or(status::NotFound(())))
+}
+
+#[rocket::put("/<id>/favourites/<user_id>", data = "<favourite>")]
+async fn set_favourite(
+    id: QuestId,
+    user_id: UserId,
+    favourite: Json<bool>,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Result<status::NoContent, status::NotFound<()>>, Error> {
+    Ok(
+        match quest_service
+            .set_favourite(&id, &user_id, favourite.0)
+            .await?
+        {
+            true => Ok(status::NoContent),
+            false => Err(status::NotFound(())),
+        },
+    )
+}
+
+#[rocket::get("/<id>/favourites/<user_id>")]
+async fn is_favourite(
+    id: QuestId,
+    user_id: UserId,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Json<bool>, Error> {
+    quest_service.is_favourite(&id, &user_id).await.map(Json)
+}
+
+#[rocket::get("/favourites/by-user/<user_id>", rank = 2)]
+async fn list_favourites(
+    user_id: UserId,
+    quest_service: &State<Arc<dyn QuestService>>,
+) -> Result<Json<Box<[Favourite]>>, Error> {
+    quest_service.list_favourites(&user_id).await.map(Json)
+}
+
+#[rocket::get("/health")]
+fn health(consumer_health: &State<ConsumerHealth>) -> ConsumerHealth {
+    consumer_health.inner().clone()
+}
+
 #[rocket::catch(default)]
 fn catch_all() -> &'static str {
     ""
 }
 
+/// `export <quest ID> <file>` and `import <file>`, which move quest packages in and out of the
+/// quest-service at `QUEST_SERVICE_ADDRESS`.
+async fn run_package_command(args: &[String]) {
+    let address = env::var("QUEST_SERVICE_ADDRESS")
+        .unwrap_or_else(|_| format!("http://127.0.0.1:{}/quests", defaults::PORT));
+    let quest_service = BackendQuestService::new(address);
+    match args {
+        [command, id, path] if command == "export" => {
+            let id = QuestId::try_parse(id).expect("invalid quest ID");
+            let package = quest_service
+                .export_quest(&id)
+                .await
+                .expect("failed to export quest")
+                .expect("quest doesn't exist");
+            let json = serde_json::to_vec_pretty(&package).expect("failed to encode package");
+            std::fs::write(path, json).expect("failed to write package");
+            println!("exported quest {} to {}", id, path);
+        }
+        [command, path] if command == "import" => {
+            let json = std::fs::read(path).expect("failed to read package");
+            let package =
+                serde_json::from_slice::<QuestPackage>(&json).expect("failed to decode package");
+            match quest_service.import_quest(package).await {
+                Ok(id) => println!("imported quest {} from {}", id, path),
+                Err(Error::InvalidQuestPackage(reason)) => {
+                    eprintln!("invalid package: {}", reason);
+                    std::process::exit(1);
+                }
+                Err(e) => panic!("failed to import quest: {}", e),
+            }
+        }
+        _ => {
+            eprintln!("usage: codequest-quest-service export <quest ID> <file>");
+            eprintln!("       codequest-quest-service import <file>");
+            std::process::exit(2);
+        }
+    }
+}
+
 #[rocket::main]
 async fn main() -> Result<(), rocket::Error> {
     dotenv().ok();
 
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if matches!(args.first().map(String::as_str), Some("export" | "import")) {
+        run_package_command(&args).await;
+        return Ok(());
+    }
+
     let db_credentials = {
         let username =
             env::var("DB_USERNAME_QUEST_SERVICE").expect("DB_USERNAME_QUEST_SERVICE not set");
@@ -163,21 +552,86 @@ async fn main() -> Result<(), rocket::Error> {
 
     let rocket_config = rocket::Config::figment()
         .merge(("secret_key", secret_key))
-        .merge(("port", port));
+        .merge(("port", port))
+        // Packages carry generator executables.
+        .merge(("limits", Limits::default().limit("json", 64.mebibytes())));
+
+    // A JSON array with the command generators run through. Without it, authors can't upload or
+    // test generators, and only the ones installed by admins run.
+    let sandbox_file = env::var("GENERATOR_SANDBOX_FILE")
+        .unwrap_or_else(|_| defaults::GENERATOR_SANDBOX_FILE.to_owned());
+    let sandbox = match std::fs::read(&sandbox_file) {
+        Ok(json) => Some(
+            serde_json::from_slice::<Vec<String>>(&json)
+                .unwrap_or_else(|_| panic!("invalid generator sandbox in {}", sandbox_file)),
+        ),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => panic!("failed to read {}: {}", sandbox_file, e),
+    }
+    .filter(|sandbox| !sandbox.is_empty());
 
-    let quest_context_provider =
-        InMemoryQuestContextCache::new(Arc::new(QuestContextGenerator::new("./quests/generators")));
+    let generator = match env::var("GENERATOR_SECRET_FILE") {
+        Ok(path) => QuestContextGenerator::new(defaults::GENERATOR_DIR)
+            .with_secret(load_secret_key(path).expect("failed to load generator secret")),
+        Err(_) => QuestContextGenerator::new(defaults::GENERATOR_DIR),
+    }
+    .with_sandbox(sandbox.clone().unwrap_or_default());
+    let generation_config = {
+        let defaults = GenerationConfig::default();
+        let count = |name: &str, default: usize| {
+            env::var(name)
+                .map(|count| {
+                    count
+                        .parse::<usize>()
+                        .unwrap_or_else(|_| panic!("invalid {}: '{}'", name, count))
+                })
+                .unwrap_or(default)
+        };
+        let millis = |name: &str, default: Duration| {
+            env::var(name)
+                .map(|ms| {
+                    Duration::from_millis(
+                        ms.parse::<u64>()
+                            .unwrap_or_else(|_| panic!("invalid {}: '{}'", name, ms)),
+                    )
+                })
+                .unwrap_or(default)
+        };
+        GenerationConfig {
+            workers: count("GENERATOR_WORKERS", defaults.workers),
+            wait_timeout: millis("GENERATOR_WAIT_MS", defaults.wait_timeout),
+            generator_timeout: millis("GENERATOR_TIMEOUT_MS", defaults.generator_timeout),
+            background_workers: count("GENERATOR_BACKGROUND_WORKERS", defaults.background_workers),
+            sandbox,
+        }
+    };
     let quest_service = DatabaseQuestService::new(
         &db_address,
         &db_name,
         db_credentials,
-        Arc::new(quest_context_provider),
+        Arc::new(generator),
+        generation_config,
+        GeneratorStore::new(defaults::GENERATOR_DIR),
+        &nats_address,
     )
     .await
     .expect("failed to start DatabaseQuestService");
-    let quest_service = QuestServiceNatsWrapper::new(Arc::new(quest_service), nats_address)
+
+    let official_quests_dir = env::var("OFFICIAL_QUESTS_DIR")
+        .unwrap_or_else(|_| defaults::OFFICIAL_QUESTS_DIR.to_owned());
+    let report = quest_service
+        .seed_official_quests(&official_quests_dir)
         .await
-        .expect("failed to start nats wrapper");
+        .expect("failed to seed official quests");
+    println!(
+        "official quests from {}: {} created, {} updated, {} unchanged, {} archived, {} failed",
+        official_quests_dir,
+        report.created,
+        report.updated,
+        report.unchanged,
+        report.archived,
+        report.failed
+    );
 
     rocket::custom(&rocket_config)
         .register("/", catchers![catch_all])
@@ -185,16 +639,47 @@ async fn main() -> Result<(), rocket::Error> {
             "/quests",
             routes![
                 list_quests,
+                list_quests_by_author,
                 get_quest,
                 get_quest_author,
+                get_hints,
                 get_input,
                 get_answer,
                 verify_answer,
+                get_test_case,
                 create_quest,
                 update_quest,
                 modify_quest,
+                search_quests,
+                delete_quest,
+                clear_quest_contexts,
+                regenerate_quest_contexts,
+                list_prewarm_progress,
+                export_quest,
+                import_quest,
+                test_generator,
+                upload_generator,
+                list_comments,
+                post_comment,
+                get_comment,
+                edit_comment,
+                delete_comment,
+                report_comment,
+                dismiss_comment_reports,
+                set_comment_hidden,
+                list_reported_comments,
+                export_comments,
+                rate_quest,
+                list_ratings,
+                get_rating,
+                list_user_ratings,
+                set_favourite,
+                is_favourite,
+                list_favourites,
             ],
         )
+        .mount("/", routes![health])
+        .manage(quest_service.consumer_health())
         .manage(Arc::new(quest_service) as Arc<dyn QuestService>)
         .launch()
         .await?;
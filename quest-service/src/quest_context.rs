@@ -1,7 +1,10 @@
-use std::{collections::HashMap, io::ErrorKind, path::PathBuf, sync::Arc};
+use std::{collections::HashMap, env, io::ErrorKind, path::PathBuf, sync::Arc};
 
-use codequest_common::{Error, QuestId, UserId};
-use rocket::{async_trait, tokio::sync::RwLock};
+use codequest_common::{Error, QuestId, UserId, judge::TestCase};
+use rocket::{
+    async_trait,
+    tokio::sync::{Mutex, RwLock},
+};
 use tokio::process::Command;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -16,6 +19,15 @@ impl QuestContext {
     }
 }
 
+impl From<QuestContext> for TestCase {
+    fn from(context: QuestContext) -> Self {
+        Self {
+            input: context.input,
+            answer: context.answer,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ContextKey {
     quest: QuestId,
@@ -55,33 +67,84 @@ pub trait QuestContextProvider: Send + Sync {
             .await
             .map(|res| res.map(|context| context.answer))
     }
+
+    /// The context of the judge's test case at `index` for a program submitted by the user.
+    async fn get_test_case(
+        &self,
+        _quest_id: &QuestId,
+        _user_id: &UserId,
+        _index: u32,
+    ) -> Result<Option<QuestContext>, Error> {
+        Err(Error::Unsupported)
+    }
+
+    /// Forgets all contexts of the quest. Providers that don't cache have nothing to do.
+    async fn clear_quest(&self, _quest_id: &QuestId) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 pub struct QuestContextGenerator {
     generator_dir_path: PathBuf,
+    secret: Option<String>,
+    sandbox: Vec<String>,
 }
 
 impl QuestContextGenerator {
     pub fn new(generator_dir_path: impl Into<PathBuf>) -> Self {
         Self {
             generator_dir_path: generator_dir_path.into(),
+            secret: None,
+            sandbox: Vec::new(),
         }
     }
-}
 
-#[async_trait]
-impl QuestContextProvider for QuestContextGenerator {
-    async fn get_context(
+    /// Passes `secret` to the generators, which mix it into their seeds.
+    pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Runs the generators through this command, e.g. `nsjail` with its options, which gets the
+    /// generator's path and arguments appended.
+    pub fn with_sandbox(mut self, sandbox: Vec<String>) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Runs the quest's generator with the user's ID and `extra_args` as arguments, and the
+    /// quest's ID and the secret as the only environment variables besides `PATH`. The generator
+    /// prints the input and the answer, separated by a NUL byte.
+    async fn generate(
         &self,
         quest_id: &QuestId,
         user_id: &UserId,
+        extra_args: &[String],
     ) -> Result<Option<QuestContext>, Error> {
         let generator_path = self.generator_dir_path.join(quest_id.to_string());
-        let result = match Command::new(generator_path)
+        // The sandbox would fail instead of not finding the generator.
+        if !tokio::fs::try_exists(&generator_path).await? {
+            return Ok(None);
+        }
+        // Killed if the caller gives up waiting for it.
+        let mut command = match self.sandbox.split_first() {
+            Some((sandbox, args)) => {
+                let mut command = Command::new(sandbox);
+                command.args(args).arg(generator_path);
+                command
+            }
+            None => Command::new(generator_path),
+        };
+        command
             .arg(user_id.to_string())
-            .output()
-            .await
-        {
+            .args(extra_args)
+            .env_clear()
+            .envs(env::var_os("PATH").map(|path| ("PATH", path)))
+            .env(codequest_generator::QUEST_ID_VAR, quest_id.to_string());
+        if let Some(secret) = &self.secret {
+            command.env(codequest_generator::SECRET_VAR, secret);
+        }
+        let result = match command.kill_on_drop(true).output().await {
             Ok(result) => result,
             Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
             Err(e) => return Err(e.into()),
@@ -97,12 +160,35 @@ impl QuestContextProvider for QuestContextGenerator {
 
         let output = String::from_utf8(result.stdout).map_err(|_| Error::InvalidResponse)?;
         let (input, answer) = output.split_once('\0').ok_or(Error::InvalidResponse)?;
-        return Ok(Some(QuestContext::new(input.to_owned(), answer.to_owned())));
+        Ok(Some(QuestContext::new(input.to_owned(), answer.to_owned())))
+    }
+}
+
+#[async_trait]
+impl QuestContextProvider for QuestContextGenerator {
+    async fn get_context(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+    ) -> Result<Option<QuestContext>, Error> {
+        self.generate(quest_id, user_id, &[]).await
+    }
+
+    /// Test cases are generated with the test's index as an additional argument, so generators
+    /// can derive different inputs for each test.
+    async fn get_test_case(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+        index: u32,
+    ) -> Result<Option<QuestContext>, Error> {
+        self.generate(quest_id, user_id, &[index.to_string()]).await
     }
 }
 
 pub struct InMemoryQuestContextCache {
     contexts: RwLock<HashMap<ContextKey, Option<QuestContext>>>,
+    in_flight: Mutex<HashMap<ContextKey, Arc<Mutex<()>>>>,
     backend: Arc<dyn QuestContextProvider>,
 }
 
@@ -110,14 +196,35 @@ impl InMemoryQuestContextCache {
     pub fn new(backend: Arc<dyn QuestContextProvider>) -> Self {
         Self {
             contexts: RwLock::new(HashMap::new()),
+            in_flight: Mutex::new(HashMap::new()),
             backend,
         }
     }
 
+    /// Concurrent requests for the same key wait for the first one to fetch the context instead
+    /// of fetching it again.
     async fn fetch_and_cache(&self, key: ContextKey) -> Result<Option<QuestContext>, Error> {
-        let context = self.backend.get_context(&key.quest, &key.user).await?;
-        self.contexts.write().await.insert(key, context.clone());
-        Ok(context)
+        let key_lock = self
+            .in_flight
+            .lock()
+            .await
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        let _guard = key_lock.lock().await;
+        if let Some(context) = self.contexts.read().await.get(&key) {
+            return Ok(context.clone());
+        }
+
+        let result = self.backend.get_context(&key.quest, &key.user).await;
+        if let Ok(context) = &result {
+            self.contexts
+                .write()
+                .await
+                .insert(key.clone(), context.clone());
+        }
+        self.in_flight.lock().await.remove(&key);
+        result
     }
 }
 
@@ -167,4 +274,22 @@ impl QuestContextProvider for InMemoryQuestContextCache {
             .await?
             .map(|context| context.answer))
     }
+
+    // Test cases are only generated once per submission, caching them wouldn't pay off.
+    async fn get_test_case(
+        &self,
+        quest_id: &QuestId,
+        user_id: &UserId,
+        index: u32,
+    ) -> Result<Option<QuestContext>, Error> {
+        self.backend.get_test_case(quest_id, user_id, index).await
+    }
+
+    async fn clear_quest(&self, quest_id: &QuestId) -> Result<(), Error> {
+        self.contexts
+            .write()
+            .await
+            .retain(|key, _| key.quest != *quest_id);
+        self.backend.clear_quest(quest_id).await
+    }
 }
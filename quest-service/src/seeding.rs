@@ -0,0 +1,169 @@
+//! Keeps the official quests in line with a directory of quest packages.
+//!
+//! The directory holds packages as `*.json` files, or unpacked as subdirectories with a
+//! `manifest.json`, a `description.md` and optionally a `generator`. At startup every package is
+//! imported as an official quest unless it's installed unchanged already, and quests seeded from a
+//! package that's gone from the directory are archived. Quests archived by an admin are left
+//! alone, only the ones seeding archived are restored once their package is back.
+
+use std::{io::ErrorKind, path::Path};
+
+use codequest_common::{
+    Error, PartialQuestData, QuestId,
+    package::{PACKAGE_FORMAT_VERSION, QuestManifest, QuestPackage},
+    services::QuestService,
+};
+use tokio::fs;
+
+use crate::DatabaseQuestService;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeedReport {
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub archived: usize,
+    /// Packages that couldn't be loaded or imported.
+    pub failed: usize,
+}
+
+/// Loads the packages in `dir`, `None` if there is no such directory. Packages that can't be
+/// loaded are reported and counted in `failed`.
+pub async fn load_packages(
+    dir: impl AsRef<Path>,
+    failed: &mut usize,
+) -> Result<Option<Vec<QuestPackage>>, Error> {
+    let dir = dir.as_ref();
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut packages = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let package = if entry.file_type().await?.is_dir() {
+            load_unpacked(&path).await
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            load_packed(&path).await
+        } else {
+            continue;
+        };
+        match package {
+            Ok(package) => packages.push(package),
+            Err(e) => {
+                eprintln!("failed to load quest package {}: {}", path.display(), e);
+                *failed += 1;
+            }
+        }
+    }
+    Ok(Some(packages))
+}
+
+async fn load_packed(path: &Path) -> Result<QuestPackage, Error> {
+    Ok(serde_json::from_slice(&fs::read(path).await?)?)
+}
+
+async fn load_unpacked(dir: &Path) -> Result<QuestPackage, Error> {
+    let manifest =
+        serde_json::from_slice::<QuestManifest>(&fs::read(dir.join("manifest.json")).await?)?;
+    let description = fs::read_to_string(dir.join("description.md"))
+        .await?
+        .trim_end()
+        .to_owned();
+    let generator = match fs::read(dir.join("generator")).await {
+        Ok(generator) => Some(generator),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    Ok(QuestPackage {
+        format_version: PACKAGE_FORMAT_VERSION,
+        manifest,
+        description,
+        generator,
+    })
+}
+
+impl DatabaseQuestService {
+    /// Imports the packages in `dir` as official quests and archives the seeded quests that aren't
+    /// in it anymore. Does nothing if `dir` doesn't exist.
+    pub async fn seed_official_quests(&self, dir: impl AsRef<Path>) -> Result<SeedReport, Error> {
+        let mut report = SeedReport::default();
+        let Some(packages) = load_packages(dir, &mut report.failed).await? else {
+            return Ok(report);
+        };
+
+        let mut seeded = Vec::with_capacity(packages.len());
+        for mut package in packages {
+            package.manifest.official = true;
+            package.manifest.name = package.manifest.name.trim().to_owned();
+            let id = package.manifest.id;
+            match self.seed_quest(package).await {
+                Ok(Some(true)) => report.created += 1,
+                Ok(Some(false)) => report.updated += 1,
+                Ok(None) => report.unchanged += 1,
+                Err(e) => {
+                    eprintln!("failed to seed quest {}: {}", id, e);
+                    report.failed += 1;
+                }
+            }
+            seeded.push(id);
+        }
+
+        // A package that failed to load might be one of the seeded quests.
+        if report.failed > 0 {
+            eprintln!("not archiving seeded quests because some packages failed");
+            return Ok(report);
+        }
+        let removed = sqlx::query_scalar::<_, QuestId>(
+            "SELECT id FROM quests WHERE (seeded AND NOT archived AND NOT (id = ANY($1)))",
+        )
+        .bind(&seeded)
+        .fetch_all(&self.pool)
+        .await?;
+        for id in removed {
+            self.modify_quest(&id, PartialQuestData::empty().with_archived(true))
+                .await?;
+            sqlx::query("UPDATE quests SET seed_archived = true WHERE (id = $1)")
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+            report.archived += 1;
+        }
+        Ok(report)
+    }
+
+    /// Imports the package unless it's installed already or the quest was archived by an admin.
+    /// Returns whether the quest was created, `None` if nothing changed.
+    async fn seed_quest(&self, package: QuestPackage) -> Result<Option<bool>, Error> {
+        let id = package.manifest.id;
+        let state = sqlx::query_as::<_, (bool, bool, bool)>(
+            "SELECT seeded, archived, seed_archived FROM quests WHERE (id = $1)",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        match state {
+            Some((_, true, false)) => return Ok(None),
+            Some((true, false, _)) if self.export_quest(&id).await?.as_ref() == Some(&package) => {
+                return Ok(None);
+            }
+            _ => (),
+        }
+
+        self.import_quest(package).await?;
+        if state.is_some_and(|(_, _, seed_archived)| seed_archived) {
+            self.modify_quest(&id, PartialQuestData::empty().with_archived(false))
+                .await?;
+        }
+        sqlx::query("UPDATE quests SET seeded = true WHERE (id = $1)")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(Some(state.is_none()))
+    }
+}
@@ -0,0 +1,16 @@
+CREATE TABLE quest_attempts (
+    user_id        UUID NOT NULL,
+    quest_id       UUID NOT NULL,
+    started_at     TIMESTAMPTZ,
+    wrong_answers  INTEGER NOT NULL DEFAULT 0,
+    solved_at      TIMESTAMPTZ,
+    PRIMARY KEY (user_id, quest_id)
+);
+
+CREATE TABLE daily_activity (
+    user_id            UUID NOT NULL,
+    day                DATE NOT NULL,
+    answers_submitted  BIGINT NOT NULL DEFAULT 0,
+    quests_completed   BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, day)
+);
@@ -0,0 +1,28 @@
+CREATE TABLE achievement_definitions (
+    id           TEXT PRIMARY KEY,
+    name         TEXT NOT NULL,
+    description  TEXT NOT NULL,
+    criterion    TEXT NOT NULL,
+    threshold    BIGINT NOT NULL,
+    position     INTEGER NOT NULL
+);
+
+INSERT INTO achievement_definitions (id, name, description, criterion, threshold, position) VALUES
+    ('first_solve', 'First Steps', 'Solve your first quest.', 'quests_solved', 1, 1),
+    ('ten_quests', 'Seasoned Adventurer', 'Solve 10 quests.', 'quests_solved', 10, 2),
+    ('early_bird', 'Early Bird', 'Solve a quest within an hour of its release.', 'solved_after_release', 3600, 3),
+    ('week_streak', 'Dedicated', 'Submit answers on 7 days in a row.', 'daily_streak', 7, 4),
+    ('crowd_pleaser', 'Crowd Pleaser', 'Write a quest that 50 people have solved.', 'quest_solvers', 50, 5);
+
+CREATE TABLE user_achievements (
+    user_id         UUID NOT NULL,
+    achievement_id  TEXT NOT NULL REFERENCES achievement_definitions (id) ON DELETE CASCADE,
+    unlocked_at     TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, achievement_id)
+);
+
+CREATE TABLE quests (
+    quest_id     UUID PRIMARY KEY,
+    author       UUID,
+    released_at  TIMESTAMPTZ NOT NULL
+);
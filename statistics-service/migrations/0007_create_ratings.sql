@@ -0,0 +1,11 @@
+CREATE TABLE user_ratings (
+    user_id  UUID PRIMARY KEY,
+    rating   DOUBLE PRECISION NOT NULL,
+    matches  BIGINT NOT NULL
+);
+
+CREATE TABLE quest_ratings (
+    quest_id  UUID PRIMARY KEY,
+    rating    DOUBLE PRECISION NOT NULL,
+    matches   BIGINT NOT NULL
+);
@@ -0,0 +1,176 @@
+//! Achievements are defined in the `achievement_definitions` table. Each definition pairs a
+//! [`Criterion`] with a threshold, so achievements for existing criteria can be added or tuned
+//! without touching the code.
+
+use chrono::{DateTime, Utc};
+use codequest_common::{
+    Error, UserId, achievement::Achievement, event::AchievementEvent, event::ProgressionEvent,
+    outbox,
+};
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+
+use crate::LONGEST_STREAK_QUERY;
+
+/// How progress towards an achievement is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Criterion {
+    /// Solved quests. Unlocked at `threshold` or more.
+    QuestsSolved,
+    /// Longest daily streak in days. Unlocked at `threshold` or more.
+    DailyStreak,
+    /// Most solvers of any quest the user wrote. Unlocked at `threshold` or more.
+    QuestSolvers,
+    /// Fewest seconds between a quest's release and the user solving it. Unlocked at
+    /// `threshold` or less.
+    SolvedAfterRelease,
+}
+
+impl Criterion {
+    /// The value of `achievement_definitions.criterion`.
+    fn key(self) -> &'static str {
+        match self {
+            Self::QuestsSolved => "quests_solved",
+            Self::DailyStreak => "daily_streak",
+            Self::QuestSolvers => "quest_solvers",
+            Self::SolvedAfterRelease => "solved_after_release",
+        }
+    }
+
+    /// Computes the user's progress, `NULL` if there is none yet.
+    fn progress_query(self) -> &'static str {
+        match self {
+            Self::QuestsSolved => {
+                "SELECT metric_value FROM statistics
+                    WHERE (user_id = $1 AND metric_key = 'quests_completed')"
+            }
+            Self::DailyStreak => LONGEST_STREAK_QUERY,
+            Self::QuestSolvers => {
+                "SELECT MAX(solvers) FROM (
+                    SELECT COUNT(*) AS solvers FROM quest_attempts
+                    JOIN quests USING (quest_id)
+                    WHERE (quests.author = $1 AND quest_attempts.solved_at IS NOT NULL)
+                    GROUP BY quest_id
+                ) quest_solvers"
+            }
+            Self::SolvedAfterRelease => {
+                "SELECT MIN(EXTRACT(EPOCH FROM quest_attempts.solved_at - quests.released_at))::BIGINT
+                    FROM quest_attempts JOIN quests USING (quest_id)
+                    WHERE (quest_attempts.user_id = $1 AND quest_attempts.solved_at IS NOT NULL)"
+            }
+        }
+    }
+
+    fn is_reached(self) -> &'static str {
+        match self {
+            Self::QuestsSolved | Self::DailyStreak | Self::QuestSolvers => "$3 >= threshold",
+            Self::SolvedAfterRelease => "$3 <= threshold",
+        }
+    }
+}
+
+/// Unlocks the achievements `event` may have earned and publishes an
+/// [`AchievementEvent::Unlocked`] for each of them.
+pub(crate) async fn evaluate(
+    event: &ProgressionEvent,
+    occurred_at: DateTime<Utc>,
+    correlation_id: Uuid,
+    conn: &mut PgConnection,
+) -> Result<(), Error> {
+    match event {
+        ProgressionEvent::QuestStarted { .. } | ProgressionEvent::HintRevealed { .. } => (),
+        ProgressionEvent::AnswerSubmitted { user_id, .. } => {
+            unlock(
+                user_id,
+                Criterion::DailyStreak,
+                occurred_at,
+                correlation_id,
+                conn,
+            )
+            .await?;
+        }
+        ProgressionEvent::QuestCompleted { user_id, quest_id } => {
+            for criterion in [Criterion::QuestsSolved, Criterion::SolvedAfterRelease] {
+                unlock(user_id, criterion, occurred_at, correlation_id, conn).await?;
+            }
+            let author = sqlx::query_scalar::<_, Option<UserId>>(
+                "SELECT author FROM quests WHERE (quest_id = $1)",
+            )
+            .bind(quest_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .flatten();
+            if let Some(author) = author {
+                unlock(
+                    &author,
+                    Criterion::QuestSolvers,
+                    occurred_at,
+                    correlation_id,
+                    conn,
+                )
+                .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn unlock(
+    user_id: &UserId,
+    criterion: Criterion,
+    unlocked_at: DateTime<Utc>,
+    correlation_id: Uuid,
+    conn: &mut PgConnection,
+) -> Result<(), Error> {
+    let Some(progress) = sqlx::query_scalar::<_, Option<i64>>(criterion.progress_query())
+        .bind(user_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .flatten()
+    else {
+        return Ok(());
+    };
+
+    let unlocked = sqlx::query_scalar::<_, String>(&format!(
+        "INSERT INTO user_achievements (user_id, achievement_id, unlocked_at)
+            SELECT $1, id, $2 FROM achievement_definitions WHERE (criterion = $4 AND {})
+            ON CONFLICT DO NOTHING
+            RETURNING achievement_id",
+        criterion.is_reached()
+    ))
+    .bind(user_id)
+    .bind(unlocked_at)
+    .bind(progress)
+    .bind(criterion.key())
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for achievement_id in unlocked {
+        outbox::enqueue_correlated(
+            &mut *conn,
+            &AchievementEvent::Unlocked {
+                user_id: *user_id,
+                achievement_id,
+            },
+            correlation_id,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+pub(crate) async fn get_user_achievements(
+    executor: impl PgExecutor<'_>,
+    user_id: &UserId,
+) -> Result<Vec<Achievement>, Error> {
+    Ok(sqlx::query_as::<_, Achievement>(
+        "SELECT definitions.id, definitions.name, definitions.description, unlocked.unlocked_at
+            FROM achievement_definitions definitions
+            LEFT JOIN user_achievements unlocked
+                ON (unlocked.achievement_id = definitions.id AND unlocked.user_id = $1)
+            ORDER BY definitions.position",
+    )
+    .bind(user_id)
+    .fetch_all(executor)
+    .await?)
+}
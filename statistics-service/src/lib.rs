@@ -1,20 +1,77 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
+use chrono::{DateTime, NaiveDate, Utc};
 use codequest_common::{
-    Credentials, Error, UserId,
-    event::{ProgressionEvent, QuestEvent},
-    nats::NatsClient,
+    Credentials, Error, QuestId, UserId,
+    achievement::Achievement,
+    event::{
+        ErasureEvent, EventEnvelope, ProgressionEvent, QuestEvent, StatisticsEvent, UserEvent,
+    },
+    nats::{ConsumerHealth, NatsClient},
+    outbox::{self, OutboxRelay},
     services::{QuestService, StatisticsService},
-    statistics::Metric,
+    statistics::{DailyActivity, Metric, QuestStatistics, WRONG_ANSWER_BUCKETS},
 };
 use reqwest::{Client, StatusCode};
-use rocket::async_trait;
-use sqlx::{PgPool, postgres::PgPoolOptions};
+use rocket::{async_trait, tokio::sync::oneshot};
+use sqlx::{Connection as _, PgConnection, PgPool, postgres::PgPoolOptions};
+use uuid::Uuid;
 
-async fn stat_plus_one(
+mod achievements;
+mod ratings;
+
+/// How long processed event IDs are remembered. Longer than the streams keep their events, so
+/// anything JetStream can still redeliver is recognised.
+const PROCESSED_EVENT_RETENTION: Duration = Duration::from_secs(60 * 60 * 24 * 31);
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Records that the event with `event_id` is being handled in the transaction `conn` belongs to.
+/// Returns `false` if it was handled before, in which case the caller must skip it. Legacy events
+/// published without an envelope are identified by their position in their stream.
+async fn mark_processed(conn: &mut PgConnection, event_id: Uuid) -> Result<bool, Error> {
+    let result =
+        sqlx::query("INSERT INTO processed_events (event_id) VALUES ($1) ON CONFLICT DO NOTHING")
+            .bind(event_id)
+            .execute(conn)
+            .await?;
+    Ok(result.rows_affected() == 1)
+}
+
+/// The tables recomputed by [`DatabaseStatisticsService::rebuild`].
+const REBUILT_TABLES: &[&str] = &[
+    "statistics",
+    "quest_attempts",
+    "daily_activity",
+    "user_ratings",
+    "quest_ratings",
+];
+/// The tables holding data about a user, by `user_id`.
+const USER_TABLES: &[&str] = &[
+    "statistics",
+    "quest_attempts",
+    "daily_activity",
+    "user_ratings",
+    "user_achievements",
+];
+
+/// The user's longest run of consecutive days with submitted answers. Consecutive days share the
+/// same difference between date and row number.
+const LONGEST_STREAK_QUERY: &str = "SELECT COALESCE(MAX(length), 0) FROM (
+    SELECT COUNT(*) AS length FROM (
+        SELECT day - (ROW_NUMBER() OVER (ORDER BY day))::INTEGER AS streak
+        FROM daily_activity WHERE (user_id = $1 AND answers_submitted > 0)
+    ) days GROUP BY streak
+) streaks";
+
+async fn stat_plus_one(stat: &str, user_id: &UserId, conn: &mut PgConnection) -> Result<(), Error> {
+    stat_add(stat, user_id, 1, conn).await
+}
+
+async fn stat_add(
     stat: &str,
     user_id: &UserId,
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    amount: i64,
+    conn: &mut PgConnection,
 ) -> Result<(), Error> {
     sqlx::query(
         "INSERT INTO statistics (user_id, metric_key, metric_value)
@@ -24,14 +81,212 @@ async fn stat_plus_one(
     )
     .bind(user_id)
     .bind(stat)
-    .bind(1)
-    .execute(pool)
+    .bind(amount)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn add_activity(
+    user_id: &UserId,
+    day: NaiveDate,
+    answers_submitted: i64,
+    quests_completed: i64,
+    conn: &mut PgConnection,
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT INTO daily_activity (user_id, day, answers_submitted, quests_completed)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (user_id, day) DO UPDATE SET
+                    answers_submitted = daily_activity.answers_submitted + EXCLUDED.answers_submitted,
+                    quests_completed = daily_activity.quests_completed + EXCLUDED.quests_completed",
+    )
+    .bind(user_id)
+    .bind(day)
+    .bind(answers_submitted)
+    .bind(quests_completed)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Updates the statistics for a progression event that occurred at `occurred_at`.
+async fn apply_progression_event(
+    event: &ProgressionEvent,
+    occurred_at: DateTime<Utc>,
+    conn: &mut PgConnection,
+) -> Result<(), Error> {
+    match event {
+        ProgressionEvent::QuestStarted { user_id, quest_id } => {
+            sqlx::query(
+                "INSERT INTO quest_attempts (user_id, quest_id, started_at) VALUES ($1, $2, $3)
+                    ON CONFLICT (user_id, quest_id) DO UPDATE SET
+                        started_at = COALESCE(quest_attempts.started_at, EXCLUDED.started_at)",
+            )
+            .bind(user_id)
+            .bind(quest_id)
+            .bind(occurred_at)
+            .execute(&mut *conn)
+            .await?;
+        }
+        ProgressionEvent::AnswerSubmitted {
+            user_id,
+            quest_id,
+            correct,
+        } => {
+            stat_plus_one("answers_submitted", user_id, &mut *conn).await?;
+            add_activity(user_id, occurred_at.date_naive(), 1, 0, &mut *conn).await?;
+            if let Some(quest_id) = quest_id
+                && !correct
+            {
+                sqlx::query(
+                    "INSERT INTO quest_attempts (user_id, quest_id, wrong_answers) VALUES ($1, $2, 1)
+                        ON CONFLICT (user_id, quest_id) DO UPDATE SET
+                            wrong_answers = quest_attempts.wrong_answers + 1",
+                )
+                .bind(user_id)
+                .bind(quest_id)
+                .execute(&mut *conn)
+                .await?;
+            }
+        }
+        ProgressionEvent::QuestCompleted { user_id, quest_id } => {
+            stat_plus_one("quests_completed", user_id, &mut *conn).await?;
+            add_activity(user_id, occurred_at.date_naive(), 0, 1, &mut *conn).await?;
+            sqlx::query(
+                "INSERT INTO quest_attempts (user_id, quest_id, solved_at) VALUES ($1, $2, $3)
+                    ON CONFLICT (user_id, quest_id) DO UPDATE SET solved_at = EXCLUDED.solved_at",
+            )
+            .bind(user_id)
+            .bind(quest_id)
+            .bind(occurred_at)
+            .execute(&mut *conn)
+            .await?;
+        }
+        ProgressionEvent::HintRevealed {
+            user_id, penalty, ..
+        } => {
+            stat_plus_one("hints_revealed", user_id, &mut *conn).await?;
+            stat_add("hint_penalty", user_id, *penalty as i64, &mut *conn).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Remembers when a quest was released, for release-related achievements.
+async fn record_quest_release(
+    quest_id: &QuestId,
+    author: &UserId,
+    released_at: DateTime<Utc>,
+    conn: &mut PgConnection,
+) -> Result<(), Error> {
+    sqlx::query(
+        "INSERT INTO quests (quest_id, author, released_at) VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING",
+    )
+    .bind(quest_id)
+    .bind(author)
+    .bind(released_at)
+    .execute(conn)
     .await?;
     Ok(())
 }
 
+async fn erase_user(user_id: &UserId, conn: &mut PgConnection) -> Result<(), Error> {
+    for table in USER_TABLES {
+        sqlx::query(&format!("DELETE FROM {} WHERE (user_id = $1)", table))
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    sqlx::query("UPDATE quests SET author = NULL WHERE (author = $1)")
+        .bind(user_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Applies a progression event in the transaction `conn` belongs to. Returns `false` if it was
+/// handled before, in which case nothing changed.
+async fn handle_progression_event(
+    envelope: &EventEnvelope<ProgressionEvent>,
+    conn: &mut PgConnection,
+) -> Result<bool, Error> {
+    if !mark_processed(conn, envelope.id).await? {
+        return Ok(false);
+    }
+    apply_progression_event(&envelope.event, envelope.occurred_at, conn).await?;
+    achievements::evaluate(
+        &envelope.event,
+        envelope.occurred_at,
+        envelope.correlation_id(),
+        conn,
+    )
+    .await?;
+    if let Some((quest_id, difficulty)) = ratings::rate(&envelope.event, conn).await? {
+        outbox::enqueue_correlated(
+            &mut *conn,
+            &StatisticsEvent::QuestRated {
+                quest_id,
+                difficulty,
+            },
+            envelope.correlation_id(),
+        )
+        .await?;
+    }
+    Ok(true)
+}
+
+/// Applies a user event in the transaction `conn` belongs to. Returns `false` if it was handled
+/// before or doesn't concern statistics.
+async fn handle_user_event(
+    envelope: &EventEnvelope<UserEvent>,
+    conn: &mut PgConnection,
+) -> Result<bool, Error> {
+    match &envelope.event {
+        UserEvent::Deleted(user_id) => {
+            if !mark_processed(conn, envelope.id).await? {
+                return Ok(false);
+            }
+            erase_user(user_id, conn).await?;
+            outbox::enqueue_correlated(
+                &mut *conn,
+                &ErasureEvent::Completed {
+                    user_id: *user_id,
+                    service: "statistics-service".to_owned(),
+                },
+                envelope.correlation_id(),
+            )
+            .await?;
+            Ok(true)
+        }
+        UserEvent::Created(_) | UserEvent::LockedOut { .. } | UserEvent::Renamed { .. } => {
+            Ok(false)
+        }
+    }
+}
+
+/// Applies a quest event of a quest by `author` in the transaction `conn` belongs to. Returns
+/// `false` if it was handled before.
+async fn handle_quest_event(
+    envelope: &EventEnvelope<QuestEvent>,
+    author: &UserId,
+    conn: &mut PgConnection,
+) -> Result<bool, Error> {
+    if !mark_processed(conn, envelope.id).await? {
+        return Ok(false);
+    }
+    let (quest_id, stat) = quest_metric(&envelope.event);
+    stat_plus_one(stat, author, conn).await?;
+    if let QuestEvent::Created(_) = envelope.event {
+        record_quest_release(&quest_id, author, envelope.occurred_at, conn).await?;
+    }
+    Ok(true)
+}
+
 pub struct DatabaseStatisticsService {
     pool: PgPool,
+    consumer_health: ConsumerHealth,
 }
 
 impl DatabaseStatisticsService {
@@ -44,120 +299,489 @@ impl DatabaseStatisticsService {
     ) -> Result<Self, Error> {
         let nats_address = nats_address.as_ref();
 
-        let pool = PgPoolOptions::new()
-            .max_connections(20)
-            .connect(
-                format!(
-                    "postgres://{}:{}@{}/{}",
-                    credentials.username,
-                    credentials.password,
-                    address.as_ref(),
-                    db_name.as_ref()
-                )
-                .as_str(),
-            )
-            .await?;
+        let pool = connect(address, db_name, credentials).await?;
+
+        let consumer_health = ConsumerHealth::new();
 
         let _join_handle = {
             let pool = pool.clone();
             let nats_client = NatsClient::new(nats_address).await?;
+            let consumer_health = consumer_health.clone();
             rocket::tokio::spawn(async move {
                 println!("NATS event worker started: ProgressionEvents");
-                let _x = nats_client
+                nats_client
                     .consume::<ProgressionEvent>(
                         "PROGRESSION_EVENTS",
                         "statistics-service".to_owned(),
-                        async move |event| {
-                            match event {
-                                ProgressionEvent::AnswerSubmitted {
-                                    user_id,
-                                    correct: _,
-                                } => stat_plus_one("answers_submitted", &user_id, &pool).await?,
-                                ProgressionEvent::QuestCompleted {
-                                    user_id,
-                                    quest_id: _,
-                                } => stat_plus_one("quests_completed", &user_id, &pool).await?,
+                        consumer_health,
+                        async move |envelope| {
+                            let mut tx = pool.begin().await?;
+                            if handle_progression_event(&envelope, &mut tx).await? {
+                                tx.commit().await?;
                             }
                             Ok(())
                         },
                     )
-                    .await
-                    .expect("NATS event worker crashed: ProgressionEvents");
+                    .await;
             })
         };
 
-        let (tx, mut rx) = rocket::tokio::sync::mpsc::unbounded_channel::<QuestEvent>();
         let _join_handle = {
+            let pool = pool.clone();
             let nats_client = NatsClient::new(nats_address).await?;
+            let consumer_health = consumer_health.clone();
+            rocket::tokio::spawn(async move {
+                println!("NATS event worker started: UserEvents");
+                nats_client
+                    .consume::<UserEvent>(
+                        "USER_EVENTS",
+                        "statistics-service".to_owned(),
+                        consumer_health,
+                        async move |envelope| {
+                            let mut tx = pool.begin().await?;
+                            if handle_user_event(&envelope, &mut tx).await? {
+                                tx.commit().await?;
+                            }
+                            Ok(())
+                        },
+                    )
+                    .await;
+            })
+        };
+
+        // Quest events are handled by a separate task because the quest service can't be called
+        // from a consumer handler directly. The handler waits for the result so failed events
+        // are still retried.
+        let (tx, mut rx) = rocket::tokio::sync::mpsc::unbounded_channel::<(
+            EventEnvelope<QuestEvent>,
+            oneshot::Sender<Result<(), Error>>,
+        )>();
+        let _join_handle = {
+            let nats_client = NatsClient::new(nats_address).await?;
+            let consumer_health = consumer_health.clone();
             rocket::tokio::spawn(async move {
                 println!("NATS event worker started: QuestEvents 1");
-                let _x = nats_client
+                nats_client
                     .consume::<QuestEvent>(
                         "QUEST_EVENTS",
                         "statistics-service".to_owned(),
-                        async move |event| {
-                            tx.send(event).unwrap();
-                            Ok(())
+                        consumer_health,
+                        async move |envelope| {
+                            let (result_tx, result_rx) = oneshot::channel();
+                            tx.send((envelope, result_tx))
+                                .map_err(|_| Error::IncoherentState)?;
+                            result_rx.await.map_err(|_| Error::IncoherentState)?
                         },
                     )
-                    .await
-                    .expect("NATS event worker crashed: QuestEvents 1");
+                    .await;
             })
         };
         let _join_handle = {
             let pool = pool.clone();
             rocket::tokio::spawn(async move {
                 println!("NATS event worker started: QuestEvents 2");
-                async move {
-                    while let Some(event) = rx.recv().await {
-                        match event {
-                            QuestEvent::Created(quest_id) => {
-                                if let Some(Some(author)) =
-                                    quest_service.get_quest_author(&quest_id).await?
-                                {
-                                    stat_plus_one("quests_created", &author, &pool).await?;
-                                }
-                            }
-                            QuestEvent::Modified(quest_id) => {
-                                if let Some(Some(author)) =
-                                    quest_service.get_quest_author(&quest_id).await?
-                                {
-                                    stat_plus_one("quests_modified", &author, &pool).await?;
-                                }
-                            }
-                            QuestEvent::Deleted(quest_id) => {
-                                if let Some(Some(author)) =
-                                    quest_service.get_quest_author(&quest_id).await?
-                                {
-                                    stat_plus_one("quests_deleted", &author, &pool).await?;
-                                }
+                while let Some((envelope, result_tx)) = rx.recv().await {
+                    let (quest_id, _) = quest_metric(&envelope.event);
+                    let result = async {
+                        if let Some(Some(author)) =
+                            quest_service.get_quest_author(&quest_id).await?
+                        {
+                            let mut tx = pool.begin().await?;
+                            if handle_quest_event(&envelope, &author, &mut tx).await? {
+                                tx.commit().await?;
                             }
                         }
+                        Ok(())
                     }
-                    Result::<(), Error>::Ok(())
+                    .await;
+                    // The consumer gave up waiting if the receiver is gone.
+                    let _ = result_tx.send(result);
                 }
-                .await
-                .expect("NATS event worker crashed: QuestEvents 2");
             })
         };
 
-        sqlx::migrate!().run(&pool).await?;
+        let _join_handle = {
+            let pool = pool.clone();
+            rocket::tokio::spawn(async move {
+                loop {
+                    if let Err(e) = sqlx::query(
+                        "DELETE FROM processed_events WHERE processed_at < now() - make_interval(secs => $1)",
+                    )
+                    .bind(PROCESSED_EVENT_RETENTION.as_secs_f64())
+                    .execute(&pool)
+                    .await
+                    {
+                        eprintln!("failed to purge processed events: {}", e);
+                    }
+                    rocket::tokio::time::sleep(PURGE_INTERVAL).await;
+                }
+            })
+        };
 
-        Ok(Self { pool })
+        OutboxRelay::new(pool.clone(), nats_address, "statistics-service").spawn();
+
+        Ok(Self {
+            pool,
+            consumer_health,
+        })
+    }
+
+    /// The state of the service's NATS consumers, for its `/health` route.
+    pub fn consumer_health(&self) -> ConsumerHealth {
+        self.consumer_health.clone()
+    }
+
+    /// Recomputes the statistics from the events still stored in the streams into shadow tables
+    /// and swaps them in atomically. Returns how many events were replayed.
+    ///
+    /// The consumers keep running while the streams are replayed. They are only blocked while
+    /// the events published in the meantime are caught up on and the tables are swapped.
+    /// Metrics of events older than the streams' retention are lost.
+    pub async fn rebuild<S: AsRef<str>>(
+        address: S,
+        db_name: S,
+        credentials: Credentials,
+        nats_address: impl AsRef<str>,
+        quest_service: Arc<dyn QuestService>,
+    ) -> Result<u64, Error> {
+        let pool = connect(address, db_name, credentials).await?;
+        let nats_client = NatsClient::new(nats_address).await?;
+        // The temporary views only exist for this connection.
+        let mut conn = pool.acquire().await?;
+
+        let mut tx = conn.begin().await?;
+        // The shadow tables are reached through temporary views with the same names, which take
+        // precedence over the real tables, so the events are applied exactly like the consumers
+        // do it.
+        for table in REBUILT_TABLES {
+            sqlx::query(&format!("DROP TABLE IF EXISTS {}_rebuild", table))
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(&format!(
+                "CREATE TABLE {0}_rebuild (LIKE {0} INCLUDING ALL)",
+                table
+            ))
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(&format!(
+                "CREATE TEMPORARY VIEW {0} AS SELECT * FROM {0}_rebuild",
+                table
+            ))
+            .execute(&mut *tx)
+            .await?;
+        }
+        let mut replay = StreamReplay::new(&nats_client, &*quest_service);
+        replay.apply(&mut tx).await?;
+        tx.commit().await?;
+
+        let mut tx = conn.begin().await?;
+        // Consumers mark events as processed before they touch the statistics, so locking
+        // `processed_events` first lets the ones already past that point finish instead of
+        // deadlocking against the rebuild.
+        sqlx::query("LOCK TABLE processed_events IN EXCLUSIVE MODE")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&format!(
+            "LOCK TABLE {} IN ACCESS EXCLUSIVE MODE",
+            REBUILT_TABLES.join(", ")
+        ))
+        .execute(&mut *tx)
+        .await?;
+        replay.apply(&mut tx).await?;
+
+        // Events the durable consumers haven't acknowledged yet are already counted now.
+        sqlx::query(
+            "INSERT INTO processed_events (event_id) SELECT * FROM UNNEST($1::UUID[])
+                ON CONFLICT DO NOTHING",
+        )
+        .bind(&replay.event_ids)
+        .execute(&mut *tx)
+        .await?;
+
+        for table in REBUILT_TABLES {
+            sqlx::query(&format!("DROP VIEW pg_temp.{}", table))
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(&format!("DROP TABLE {}", table))
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(&format!("ALTER TABLE {0}_rebuild RENAME TO {0}", table))
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(&format!(
+                "ALTER INDEX {0}_rebuild_pkey RENAME TO {0}_pkey",
+                table
+            ))
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // The quest service may have missed changes, so every difficulty is published again.
+        let difficulties = sqlx::query_as::<_, (QuestId, f64)>(
+            "SELECT quest_id, rating FROM quest_ratings WHERE (matches >= $1)",
+        )
+        .bind(ratings::MIN_RATED_MATCHES)
+        .fetch_all(&mut *tx)
+        .await?;
+        for (quest_id, rating) in difficulties {
+            outbox::enqueue(
+                &mut *tx,
+                &StatisticsEvent::QuestRated {
+                    quest_id,
+                    difficulty: rating.round() as i32,
+                },
+            )
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(replay.replayed)
+    }
+}
+
+/// Applies the events stored in the streams to the statistics without consulting
+/// `processed_events`. Every call continues where the previous one stopped.
+struct StreamReplay<'a> {
+    nats_client: &'a NatsClient,
+    quest_service: &'a dyn QuestService,
+    authors: HashMap<QuestId, Option<Option<UserId>>>,
+    progression_sequence: u64,
+    quest_sequence: u64,
+    user_sequence: u64,
+    /// The progression and quest events applied so far.
+    event_ids: Vec<Uuid>,
+    replayed: u64,
+}
+
+impl<'a> StreamReplay<'a> {
+    fn new(nats_client: &'a NatsClient, quest_service: &'a dyn QuestService) -> Self {
+        Self {
+            nats_client,
+            quest_service,
+            authors: HashMap::new(),
+            progression_sequence: 0,
+            quest_sequence: 0,
+            user_sequence: 0,
+            event_ids: Vec::new(),
+            replayed: 0,
+        }
+    }
+
+    async fn apply(&mut self, conn: &mut PgConnection) -> Result<(), Error> {
+        let mut events = self
+            .nats_client
+            .replay_from::<ProgressionEvent>("PROGRESSION_EVENTS", self.progression_sequence + 1)
+            .await?;
+        while let Some(envelope) = events.next().await? {
+            self.replayed += 1;
+            self.event_ids.push(envelope.id);
+            apply_progression_event(&envelope.event, envelope.occurred_at, &mut *conn).await?;
+            ratings::rate(&envelope.event, &mut *conn).await?;
+        }
+        self.progression_sequence = events.last_sequence();
+
+        let mut events = self
+            .nats_client
+            .replay_from::<QuestEvent>("QUEST_EVENTS", self.quest_sequence + 1)
+            .await?;
+        while let Some(envelope) = events.next().await? {
+            self.replayed += 1;
+            self.event_ids.push(envelope.id);
+            let (quest_id, stat) = quest_metric(&envelope.event);
+            let author = match self.authors.get(&quest_id) {
+                Some(author) => *author,
+                None => {
+                    let author = self.quest_service.get_quest_author(&quest_id).await?;
+                    self.authors.insert(quest_id, author);
+                    author
+                }
+            };
+            if let Some(Some(author)) = author {
+                stat_plus_one(stat, &author, &mut *conn).await?;
+                if let QuestEvent::Created(_) = envelope.event {
+                    record_quest_release(&quest_id, &author, envelope.occurred_at, &mut *conn)
+                        .await?;
+                }
+            }
+        }
+        self.quest_sequence = events.last_sequence();
+
+        let mut events = self
+            .nats_client
+            .replay_from::<UserEvent>("USER_EVENTS", self.user_sequence + 1)
+            .await?;
+        while let Some(envelope) = events.next().await? {
+            self.replayed += 1;
+            if let UserEvent::Deleted(user_id) = envelope.event {
+                erase_user(&user_id, &mut *conn).await?;
+            }
+        }
+        self.user_sequence = events.last_sequence();
+
+        Ok(())
+    }
+}
+
+async fn connect<S: AsRef<str>>(
+    address: S,
+    db_name: S,
+    credentials: Credentials,
+) -> Result<PgPool, Error> {
+    let pool = PgPoolOptions::new()
+        .max_connections(20)
+        .connect(
+            format!(
+                "postgres://{}:{}@{}/{}",
+                credentials.username,
+                credentials.password,
+                address.as_ref(),
+                db_name.as_ref()
+            )
+            .as_str(),
+        )
+        .await?;
+
+    sqlx::migrate!().run(&pool).await?;
+
+    Ok(pool)
+}
+
+/// Quest metrics are credited to the quest's author.
+fn quest_metric(event: &QuestEvent) -> (QuestId, &'static str) {
+    match event {
+        QuestEvent::Created(quest_id) => (*quest_id, "quests_created"),
+        QuestEvent::Modified(quest_id) => (*quest_id, "quests_modified"),
+        QuestEvent::Deleted(quest_id) => (*quest_id, "quests_deleted"),
     }
 }
 
 #[async_trait]
 impl StatisticsService for DatabaseStatisticsService {
     async fn get_user_metrics(&self, user_id: &UserId) -> Result<Vec<Metric>, Error> {
-        let metrics = sqlx::query_as::<_, Metric>(
-            "SELECT metric_key as key, metric_value::TEXT as value FROM statistics WHERE (user_id = $1)",
+        let counters = sqlx::query_as::<_, (String, i64)>(
+            "SELECT metric_key, metric_value FROM statistics WHERE (user_id = $1) ORDER BY metric_key",
         )
         .bind(user_id)
         .fetch_all(&self.pool)
         .await?;
+        let mut metrics = counters
+            .into_iter()
+            .filter_map(|(key, value)| Metric::counter(&key, value as u64))
+            .collect::<Vec<_>>();
+
+        let (attempted, solved_first_try, wrong_answers, average_solve_time) =
+            sqlx::query_as::<_, (i64, i64, i64, Option<i64>)>(
+                "SELECT
+                    COUNT(*) FILTER (WHERE wrong_answers > 0 OR solved_at IS NOT NULL),
+                    COUNT(*) FILTER (WHERE wrong_answers = 0 AND solved_at IS NOT NULL),
+                    COALESCE(SUM(wrong_answers), 0),
+                    EXTRACT(EPOCH FROM AVG(solved_at - started_at)
+                        FILTER (WHERE solved_at >= started_at))::BIGINT
+                FROM quest_attempts WHERE (user_id = $1)",
+            )
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await?;
+        if let Some(seconds) = average_solve_time {
+            metrics.push(Metric::AverageSolveTime(seconds as u64));
+        }
+        if attempted > 0 {
+            metrics.push(Metric::FirstTrySuccessRate(
+                solved_first_try as f64 / attempted as f64,
+            ));
+            metrics.push(Metric::WrongAnswersPerQuest(
+                wrong_answers as f64 / attempted as f64,
+            ));
+        }
+
+        let longest_streak = sqlx::query_scalar::<_, i64>(LONGEST_STREAK_QUERY)
+            .bind(user_id)
+            .fetch_one(&self.pool)
+            .await?;
+        if longest_streak > 0 {
+            metrics.push(Metric::LongestDailyStreak(longest_streak as u64));
+        }
+
+        let mut conn = self.pool.acquire().await?;
+        if let Some(rating) = ratings::get_skill_rating(user_id, &mut conn).await? {
+            metrics.push(Metric::SkillRating(rating));
+        }
+
         Ok(metrics)
     }
+
+    async fn get_user_activity(
+        &self,
+        user_id: &UserId,
+        days: u32,
+    ) -> Result<Vec<DailyActivity>, Error> {
+        let activity = sqlx::query_as::<_, DailyActivity>(
+            "SELECT
+                series.day::DATE AS day,
+                COALESCE(activity.answers_submitted, 0) AS answers_submitted,
+                COALESCE(activity.quests_completed, 0) AS quests_completed
+            FROM generate_series(
+                (now() AT TIME ZONE 'UTC')::DATE - ($2::INTEGER - 1),
+                (now() AT TIME ZONE 'UTC')::DATE,
+                INTERVAL '1 day'
+            ) AS series(day)
+            LEFT JOIN daily_activity activity
+                ON (activity.user_id = $1 AND activity.day = series.day::DATE)
+            ORDER BY series.day",
+        )
+        .bind(user_id)
+        .bind(days as i32)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(activity)
+    }
+
+    async fn get_quest_statistics(&self, quest_id: &QuestId) -> Result<QuestStatistics, Error> {
+        let (players, solvers, average_attempts_to_solve, median_solve_time) =
+            sqlx::query_as::<_, (i64, i64, Option<f64>, Option<f64>)>(
+                "SELECT
+                    COUNT(*) FILTER (WHERE started_at IS NOT NULL),
+                    COUNT(*) FILTER (WHERE solved_at IS NOT NULL),
+                    (AVG(wrong_answers + 1) FILTER (WHERE solved_at IS NOT NULL))::DOUBLE PRECISION,
+                    PERCENTILE_CONT(0.5) WITHIN GROUP (
+                        ORDER BY EXTRACT(EPOCH FROM solved_at - started_at)::DOUBLE PRECISION
+                    ) FILTER (WHERE solved_at >= started_at)
+                FROM quest_attempts WHERE (quest_id = $1)",
+            )
+            .bind(quest_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        let buckets = sqlx::query_as::<_, (i32, i64)>(
+            "SELECT LEAST(wrong_answers, $2) AS bucket, COUNT(*) FROM quest_attempts
+                WHERE (quest_id = $1 AND (wrong_answers > 0 OR solved_at IS NOT NULL))
+                GROUP BY bucket",
+        )
+        .bind(quest_id)
+        .bind(WRONG_ANSWER_BUCKETS as i32)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut wrong_answer_distribution = vec![0; WRONG_ANSWER_BUCKETS + 1];
+        for (bucket, users) in buckets {
+            wrong_answer_distribution[bucket as usize] = users as u64;
+        }
+
+        let mut conn = self.pool.acquire().await?;
+        let difficulty = ratings::get_difficulty(quest_id, &mut conn).await?;
+
+        Ok(QuestStatistics {
+            players: players as u64,
+            solvers: solvers as u64,
+            average_attempts_to_solve,
+            median_solve_time: median_solve_time.map(|seconds| seconds.round() as u64),
+            wrong_answer_distribution,
+            difficulty,
+        })
+    }
+
+    async fn get_user_achievements(&self, user_id: &UserId) -> Result<Vec<Achievement>, Error> {
+        achievements::get_user_achievements(&self.pool, user_id).await
+    }
 }
 
 pub struct BackendStatisticsService {
@@ -192,4 +816,95 @@ impl StatisticsService for BackendStatisticsService {
             _ => Err(Error::InvalidResponse),
         }
     }
+
+    async fn get_user_activity(
+        &self,
+        user_id: &UserId,
+        days: u32,
+    ) -> Result<Vec<DailyActivity>, Error> {
+        let response = self
+            .client
+            .get(format!(
+                "{}/{}/activity?days={}",
+                &self.address, user_id, days
+            ))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Vec<DailyActivity>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn get_quest_statistics(&self, quest_id: &QuestId) -> Result<QuestStatistics, Error> {
+        let response = self
+            .client
+            .get(format!("{}/quests/{}", &self.address, quest_id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<QuestStatistics>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn get_user_achievements(&self, user_id: &UserId) -> Result<Vec<Achievement>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}/achievements", &self.address, user_id))
+            .send()
+            .await
+            .map_err(|_| Error::ServerUnreachable)?;
+
+        match response.status() {
+            StatusCode::OK => response
+                .json::<Vec<Achievement>>()
+                .await
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codequest_common::{
+        UserId,
+        event::{EventEnvelope, StreamPosition, UserEvent},
+    };
+    use rocket::serde::json::serde_json;
+
+    use super::*;
+
+    #[rocket::async_test]
+    #[ignore = "needs a PostgreSQL database at DATABASE_URL"]
+    async fn redelivered_legacy_events_are_skipped() {
+        let pool = PgPool::connect(&std::env::var("DATABASE_URL").unwrap())
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+
+        let payload = serde_json::to_vec(&UserEvent::Created(UserId::new())).unwrap();
+        let position = StreamPosition {
+            stream: "USER_EVENTS",
+            sequence: 1,
+            published_at: chrono::Utc::now(),
+        };
+        // Rolled back when dropped, so the database is left as it was.
+        let mut tx = pool.begin().await.unwrap();
+        let delivery = EventEnvelope::<UserEvent>::decode(&payload, position).unwrap();
+        assert!(mark_processed(&mut tx, delivery.id).await.unwrap());
+        let redelivery = EventEnvelope::<UserEvent>::decode(&payload, position).unwrap();
+        assert!(!mark_processed(&mut tx, redelivery.id).await.unwrap());
+    }
 }
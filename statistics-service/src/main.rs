@@ -1,7 +1,12 @@
 use std::{env, sync::Arc};
 
 use codequest_common::{
-    Credentials, Error, UserId, load_secret_key, services::StatisticsService, statistics::Metric,
+    Credentials, Error, QuestId, UserId,
+    achievement::Achievement,
+    load_secret_key,
+    nats::ConsumerHealth,
+    services::StatisticsService,
+    statistics::{DailyActivity, Metric, QuestStatistics},
 };
 use codequest_quest_service::BackendQuestService;
 use codequest_statistics_service::DatabaseStatisticsService;
@@ -11,6 +16,8 @@ use rocket::{State, catchers, routes, serde::json::Json};
 mod defaults {
     pub const SECRET_KEY_FILE: &'static str = "./secrets/secret_key";
     pub const PORT: u16 = 8000;
+    pub const ACTIVITY_DAYS: u32 = 30;
+    pub const MAX_ACTIVITY_DAYS: u32 = 366;
 }
 
 #[rocket::get("/<user_id>")]
@@ -24,6 +31,48 @@ async fn user_metrics(
         .map(|metrics| Json(metrics))
 }
 
+#[rocket::get("/quests/<quest_id>")]
+async fn quest_statistics(
+    quest_id: QuestId,
+    statistics_service: &State<Arc<dyn StatisticsService>>,
+) -> Result<Json<QuestStatistics>, Error> {
+    statistics_service
+        .get_quest_statistics(&quest_id)
+        .await
+        .map(Json)
+}
+
+#[rocket::get("/<user_id>/activity?<days>", rank = 2)]
+async fn user_activity(
+    user_id: UserId,
+    days: Option<u32>,
+    statistics_service: &State<Arc<dyn StatisticsService>>,
+) -> Result<Json<Vec<DailyActivity>>, Error> {
+    let days = days
+        .unwrap_or(defaults::ACTIVITY_DAYS)
+        .clamp(1, defaults::MAX_ACTIVITY_DAYS);
+    statistics_service
+        .get_user_activity(&user_id, days)
+        .await
+        .map(Json)
+}
+
+#[rocket::get("/<user_id>/achievements", rank = 2)]
+async fn user_achievements(
+    user_id: UserId,
+    statistics_service: &State<Arc<dyn StatisticsService>>,
+) -> Result<Json<Vec<Achievement>>, Error> {
+    statistics_service
+        .get_user_achievements(&user_id)
+        .await
+        .map(Json)
+}
+
+#[rocket::get("/health")]
+fn health(consumer_health: &State<ConsumerHealth>) -> ConsumerHealth {
+    consumer_health.inner().clone()
+}
+
 #[rocket::catch(default)]
 fn catch_all() -> &'static str {
     ""
@@ -66,6 +115,20 @@ async fn main() -> Result<(), rocket::Error> {
 
     let quest_service = BackendQuestService::new(quest_service_address);
 
+    if env::args().nth(1).as_deref() == Some("rebuild") {
+        let replayed = DatabaseStatisticsService::rebuild(
+            &db_address,
+            &db_name,
+            db_credentials,
+            nats_address,
+            Arc::new(quest_service),
+        )
+        .await
+        .expect("failed to rebuild statistics");
+        println!("statistics rebuilt from {} events", replayed);
+        return Ok(());
+    }
+
     let statistics_service = DatabaseStatisticsService::new(
         &db_address,
         &db_name,
@@ -78,7 +141,17 @@ async fn main() -> Result<(), rocket::Error> {
 
     rocket::custom(&rocket_config)
         .register("/", catchers![catch_all])
-        .mount("/statistics", routes![user_metrics])
+        .mount(
+            "/statistics",
+            routes![
+                user_metrics,
+                user_activity,
+                user_achievements,
+                quest_statistics
+            ],
+        )
+        .mount("/", routes![health])
+        .manage(statistics_service.consumer_health())
         .manage(Arc::new(statistics_service) as Arc<dyn StatisticsService>)
         .launch()
         .await?;
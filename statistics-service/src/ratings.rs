@@ -0,0 +1,137 @@
+//! Users and quests are rated against each other like players in Elo. Every wrong answer is a
+//! match the user lost against the quest, every solve a match the user won, which counts for
+//! less the longer the user took. A quest's rating is its empirical difficulty.
+
+use std::time::Duration;
+
+use codequest_common::{Error, QuestId, UserId, event::ProgressionEvent};
+use sqlx::{Encode, PgConnection, Postgres, Type};
+
+const INITIAL_RATING: f64 = 1500.0;
+const K_FACTOR: f64 = 32.0;
+/// Solves that took this long or longer only count as `1 - SLOW_SOLVE_PENALTY` of a win.
+const SLOW_SOLVE: Duration = Duration::from_secs(60 * 60 * 24);
+const SLOW_SOLVE_PENALTY: f64 = 0.25;
+/// Ratings based on fewer matches are too unreliable to be shown or published.
+pub(crate) const MIN_RATED_MATCHES: i64 = 5;
+
+/// Rates the match `event` stands for. Returns the quest's new difficulty if it has to be
+/// published.
+///
+/// Must run after the event was applied to `quest_attempts`.
+pub(crate) async fn rate(
+    event: &ProgressionEvent,
+    conn: &mut PgConnection,
+) -> Result<Option<(QuestId, i32)>, Error> {
+    let (user_id, quest_id, score) = match event {
+        ProgressionEvent::AnswerSubmitted {
+            user_id,
+            quest_id: Some(quest_id),
+            correct: false,
+        } => (*user_id, *quest_id, 0.0),
+        ProgressionEvent::QuestCompleted { user_id, quest_id } => {
+            let solve_time = sqlx::query_scalar::<_, Option<f64>>(
+                "SELECT EXTRACT(EPOCH FROM solved_at - started_at)::DOUBLE PRECISION
+                    FROM quest_attempts WHERE (user_id = $1 AND quest_id = $2)",
+            )
+            .bind(user_id)
+            .bind(quest_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .flatten();
+            let slowness = solve_time.map_or(0.0, |seconds| {
+                (seconds / SLOW_SOLVE.as_secs_f64()).clamp(0.0, 1.0)
+            });
+            (*user_id, *quest_id, 1.0 - SLOW_SOLVE_PENALTY * slowness)
+        }
+        _ => return Ok(None),
+    };
+
+    let (user_rating, _) = load("user_ratings", "user_id", user_id, conn).await?;
+    let (quest_rating, quest_matches) = load("quest_ratings", "quest_id", quest_id, conn).await?;
+
+    let expected = 1.0 / (1.0 + 10f64.powf((quest_rating - user_rating) / 400.0));
+    let change = K_FACTOR * (score - expected);
+    store(
+        "user_ratings",
+        "user_id",
+        user_id,
+        user_rating + change,
+        conn,
+    )
+    .await?;
+    store(
+        "quest_ratings",
+        "quest_id",
+        quest_id,
+        quest_rating - change,
+        conn,
+    )
+    .await?;
+
+    let difficulty = (quest_rating - change).round() as i32;
+    let published = quest_matches + 1 == MIN_RATED_MATCHES
+        || (quest_matches >= MIN_RATED_MATCHES && difficulty != quest_rating.round() as i32);
+    Ok(published.then_some((quest_id, difficulty)))
+}
+
+/// The rating and number of matches of the subject with `id`.
+async fn load<T>(
+    table: &str,
+    column: &str,
+    id: T,
+    conn: &mut PgConnection,
+) -> Result<(f64, i64), Error>
+where
+    T: for<'q> Encode<'q, Postgres> + Type<Postgres> + Send + 'static,
+{
+    Ok(sqlx::query_as::<_, (f64, i64)>(&format!(
+        "SELECT rating, matches FROM {} WHERE ({} = $1)",
+        table, column
+    ))
+    .bind(id)
+    .fetch_optional(conn)
+    .await?
+    .unwrap_or((INITIAL_RATING, 0)))
+}
+
+/// Stores the rating after another match.
+async fn store<T>(
+    table: &str,
+    column: &str,
+    id: T,
+    rating: f64,
+    conn: &mut PgConnection,
+) -> Result<(), Error>
+where
+    T: for<'q> Encode<'q, Postgres> + Type<Postgres> + Send + 'static,
+{
+    sqlx::query(&format!(
+        "INSERT INTO {0} ({1}, rating, matches) VALUES ($1, $2, 1)
+            ON CONFLICT ({1}) DO UPDATE SET rating = EXCLUDED.rating, matches = {0}.matches + 1",
+        table, column
+    ))
+    .bind(id)
+    .bind(rating)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// The user's skill rating, `None` while it is provisional.
+pub(crate) async fn get_skill_rating(
+    user_id: &UserId,
+    conn: &mut PgConnection,
+) -> Result<Option<i32>, Error> {
+    let (rating, matches) = load("user_ratings", "user_id", *user_id, conn).await?;
+    Ok((matches >= MIN_RATED_MATCHES).then(|| rating.round() as i32))
+}
+
+/// The quest's difficulty rating, `None` while it is provisional.
+pub(crate) async fn get_difficulty(
+    quest_id: &QuestId,
+    conn: &mut PgConnection,
+) -> Result<Option<i32>, Error> {
+    let (rating, matches) = load("quest_ratings", "quest_id", *quest_id, conn).await?;
+    Ok((matches >= MIN_RATED_MATCHES).then(|| rating.round() as i32))
+}
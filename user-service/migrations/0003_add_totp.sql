@@ -0,0 +1,15 @@
+CREATE TABLE totp_secrets (
+    user_id            UUID PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    secret_nonce       BYTEA NOT NULL,
+    secret_ciphertext  BYTEA NOT NULL,
+    confirmed          BOOLEAN NOT NULL DEFAULT false,
+    last_used_step     BIGINT NOT NULL DEFAULT 0,
+    created_at         TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE totp_recovery_codes (
+    user_id    UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    code_hash  TEXT NOT NULL,
+    used_at    TIMESTAMPTZ,
+    PRIMARY KEY (user_id, code_hash)
+);
@@ -0,0 +1,8 @@
+CREATE TABLE login_throttles (
+    kind          TEXT NOT NULL,
+    key           TEXT NOT NULL,
+    failures      INTEGER NOT NULL DEFAULT 0,
+    last_failure  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    locked_until  TIMESTAMPTZ,
+    PRIMARY KEY (kind, key)
+);
@@ -0,0 +1,11 @@
+CREATE TABLE erasure_requests (
+    user_id       UUID PRIMARY KEY,
+    requested_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE erasure_confirmations (
+    user_id       UUID NOT NULL REFERENCES erasure_requests (user_id) ON DELETE CASCADE,
+    service       TEXT NOT NULL,
+    confirmed_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_id, service)
+);
@@ -0,0 +1,16 @@
+ALTER TABLE users
+ADD is_admin BOOLEAN NOT NULL DEFAULT false,
+ADD banned_at TIMESTAMPTZ,
+ADD ban_reason TEXT,
+ADD sessions_revoked_at TIMESTAMPTZ;
+
+CREATE TABLE admin_audit_log (
+    id          UUID PRIMARY KEY DEFAULT uuidv7(),
+    admin_id    UUID NOT NULL,
+    action      TEXT NOT NULL,
+    target      TEXT NOT NULL,
+    details     TEXT,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX admin_audit_log_created_at_idx ON admin_audit_log (created_at DESC);
@@ -0,0 +1,4 @@
+ALTER TABLE outbox
+ADD event_id UUID NOT NULL DEFAULT uuidv7(),
+ADD schema_version INTEGER NOT NULL DEFAULT 1,
+ADD correlation_id UUID;
@@ -0,0 +1,2 @@
+-- Admin rights are granted and revoked from the command line, without an admin.
+ALTER TABLE admin_audit_log ALTER COLUMN admin_id DROP NOT NULL;
@@ -2,15 +2,23 @@ use std::{
     collections::HashMap,
     fs::File as StdFile,
     io,
+    net::IpAddr,
     path::{Path, PathBuf},
-    sync::Arc,
 };
 
 use argon2::{Argon2, PasswordHasher, password_hash::SaltString};
+use chrono::{DateTime, Utc};
 use codequest_common::{
-    Credentials, Error, User, UserId, Username, event::UserEvent, nats::NatsClient,
+    Credentials, ErasureStatus, Error, Lockout, LoginResult, TotpEnrollment, User, UserAccount,
+    UserId, UserProfile, Username,
+    admin::{AdminAction, AuditEntry},
+    escape_like,
+    event::{ERASURE_SERVICES, ErasureEvent, UserEvent},
+    nats::{ConsumerHealth, NatsClient},
+    outbox::{self, OutboxRelay},
     services::UserService,
 };
+use rand::Rng as _;
 use reqwest::{Client, StatusCode};
 use rocket::{
     async_trait,
@@ -18,7 +26,12 @@ use rocket::{
     tokio::{fs::File as TokioFile, io::AsyncWriteExt as _, sync::RwLock},
 };
 use serde::{Deserialize, Serialize};
-use sqlx::{PgPool, postgres::PgPoolOptions};
+use sqlx::{PgConnection, PgPool, postgres::PgPoolOptions};
+
+use crate::{throttle::ThrottleKind, totp::TotpCipher};
+
+pub mod throttle;
+pub mod totp;
 
 pub struct InMemoryUserService {
     users: RwLock<HashMap<UserId, (Username, String)>>,
@@ -59,7 +72,16 @@ impl UserService for InMemoryUserService {
             .map(|(username, _)| User::build(id.clone(), username.clone())))
     }
 
-    async fn login(&self, username: &Username, password: &str) -> Result<Option<UserId>, Error> {
+    async fn get_user_profile(&self, _username: &Username) -> Result<Option<UserProfile>, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn login(
+        &self,
+        username: &Username,
+        password: &str,
+        _client_ip: Option<IpAddr>,
+    ) -> Result<LoginResult, Error> {
         let hash = self.hash_password(password);
         Ok(self
             .users
@@ -69,7 +91,9 @@ impl UserService for InMemoryUserService {
             .find(|(_, (correct_username, correct_hash))| {
                 correct_username == username && hash == *correct_hash
             })
-            .map(|(id, _)| id.clone()))
+            .map_or(LoginResult::InvalidCredentials, |(id, _)| {
+                LoginResult::Success(id.clone())
+            }))
     }
 
     async fn create_user(
@@ -100,6 +124,10 @@ impl UserService for InMemoryUserService {
         Ok(self.users.write().await.remove(id).is_some())
     }
 
+    async fn get_erasure_status(&self, _id: &UserId) -> Result<Option<ErasureStatus>, Error> {
+        Err(Error::Unsupported)
+    }
+
     async fn change_password(
         &self,
         id: &UserId,
@@ -126,9 +154,96 @@ impl UserService for InMemoryUserService {
         })
     }
 
+    async fn change_username(&self, id: &UserId, username: Username) -> Result<bool, Error> {
+        let mut users = self.users.write().await;
+        if users
+            .iter()
+            .any(|(other_id, (used_username, _))| other_id != id && *used_username == username)
+        {
+            return Ok(false);
+        }
+        Ok(if let Some((current_username, _)) = users.get_mut(id) {
+            *current_username = username;
+            true
+        } else {
+            false
+        })
+    }
+
     async fn user_exists(&self, id: &UserId) -> Result<bool, Error> {
         Ok(self.users.read().await.contains_key(id))
     }
+
+    async fn is_totp_enabled(&self, _id: &UserId) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    async fn verify_totp(&self, _id: &UserId, _code: &str) -> Result<LoginResult, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn begin_totp_enrollment(&self, _id: &UserId) -> Result<Option<TotpEnrollment>, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn confirm_totp_enrollment(
+        &self,
+        _id: &UserId,
+        _code: &str,
+    ) -> Result<Option<Box<[String]>>, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn disable_totp(
+        &self,
+        _id: &UserId,
+        _password: &str,
+        _code: &str,
+    ) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn get_user_account(&self, _id: &UserId) -> Result<Option<UserAccount>, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn search_users(&self, _query: &str) -> Result<Box<[UserAccount]>, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn ban_user(&self, _id: &UserId, _reason: &str) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn unban_user(&self, _id: &UserId) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn reset_password(&self, _id: &UserId) -> Result<Option<String>, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn revoke_sessions(&self, _id: &UserId) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn set_admin(&self, _id: &UserId, _is_admin: bool) -> Result<bool, Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn record_admin_action(
+        &self,
+        _admin_id: &UserId,
+        _action: AdminAction,
+        _target: &str,
+        _details: Option<&str>,
+    ) -> Result<(), Error> {
+        Err(Error::Unsupported)
+    }
+
+    async fn list_admin_actions(&self, _limit: u32) -> Result<Box<[AuditEntry]>, Error> {
+        Err(Error::Unsupported)
+    }
 }
 
 pub struct FileUserService {
@@ -188,8 +303,19 @@ impl UserService for FileUserService {
         self.in_memory_user_service.get_user(id).await
     }
 
-    async fn login(&self, username: &Username, password: &str) -> Result<Option<UserId>, Error> {
-        self.in_memory_user_service.login(username, password).await
+    async fn get_user_profile(&self, username: &Username) -> Result<Option<UserProfile>, Error> {
+        self.in_memory_user_service.get_user_profile(username).await
+    }
+
+    async fn login(
+        &self,
+        username: &Username,
+        password: &str,
+        client_ip: Option<IpAddr>,
+    ) -> Result<LoginResult, Error> {
+        self.in_memory_user_service
+            .login(username, password, client_ip)
+            .await
     }
 
     async fn create_user(
@@ -219,6 +345,10 @@ impl UserService for FileUserService {
         Ok(deleted)
     }
 
+    async fn get_erasure_status(&self, id: &UserId) -> Result<Option<ErasureStatus>, Error> {
+        self.in_memory_user_service.get_erasure_status(id).await
+    }
+
     async fn change_password(
         &self,
         id: &UserId,
@@ -237,14 +367,113 @@ impl UserService for FileUserService {
         return Ok(password_was_changed);
     }
 
+    async fn change_username(&self, id: &UserId, username: Username) -> Result<bool, Error> {
+        let username_was_changed = self
+            .in_memory_user_service
+            .change_username(id, username)
+            .await?;
+
+        if username_was_changed {
+            self.save().await?;
+        }
+
+        return Ok(username_was_changed);
+    }
+
     async fn user_exists(&self, id: &UserId) -> Result<bool, Error> {
         self.in_memory_user_service.user_exists(id).await
     }
+
+    async fn is_totp_enabled(&self, id: &UserId) -> Result<bool, Error> {
+        self.in_memory_user_service.is_totp_enabled(id).await
+    }
+
+    async fn verify_totp(&self, id: &UserId, code: &str) -> Result<LoginResult, Error> {
+        self.in_memory_user_service.verify_totp(id, code).await
+    }
+
+    async fn begin_totp_enrollment(&self, id: &UserId) -> Result<Option<TotpEnrollment>, Error> {
+        self.in_memory_user_service.begin_totp_enrollment(id).await
+    }
+
+    async fn confirm_totp_enrollment(
+        &self,
+        id: &UserId,
+        code: &str,
+    ) -> Result<Option<Box<[String]>>, Error> {
+        self.in_memory_user_service
+            .confirm_totp_enrollment(id, code)
+            .await
+    }
+
+    async fn disable_totp(&self, id: &UserId, password: &str, code: &str) -> Result<bool, Error> {
+        self.in_memory_user_service
+            .disable_totp(id, password, code)
+            .await
+    }
+
+    async fn get_user_account(&self, id: &UserId) -> Result<Option<UserAccount>, Error> {
+        self.in_memory_user_service.get_user_account(id).await
+    }
+
+    async fn search_users(&self, query: &str) -> Result<Box<[UserAccount]>, Error> {
+        self.in_memory_user_service.search_users(query).await
+    }
+
+    async fn ban_user(&self, id: &UserId, reason: &str) -> Result<bool, Error> {
+        self.in_memory_user_service.ban_user(id, reason).await
+    }
+
+    async fn unban_user(&self, id: &UserId) -> Result<bool, Error> {
+        self.in_memory_user_service.unban_user(id).await
+    }
+
+    async fn reset_password(&self, id: &UserId) -> Result<Option<String>, Error> {
+        self.in_memory_user_service.reset_password(id).await
+    }
+
+    async fn revoke_sessions(&self, id: &UserId) -> Result<bool, Error> {
+        self.in_memory_user_service.revoke_sessions(id).await
+    }
+
+    async fn set_admin(&self, id: &UserId, is_admin: bool) -> Result<bool, Error> {
+        self.in_memory_user_service.set_admin(id, is_admin).await
+    }
+
+    async fn record_admin_action(
+        &self,
+        admin_id: &UserId,
+        action: AdminAction,
+        target: &str,
+        details: Option<&str>,
+    ) -> Result<(), Error> {
+        self.in_memory_user_service
+            .record_admin_action(admin_id, action, target, details)
+            .await
+    }
+
+    async fn list_admin_actions(&self, limit: u32) -> Result<Box<[AuditEntry]>, Error> {
+        self.in_memory_user_service.list_admin_actions(limit).await
+    }
+}
+
+const USER_SEARCH_LIMIT: i64 = 50;
+const TEMPORARY_PASSWORD_LENGTH: usize = 16;
+
+fn generate_temporary_password() -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZabcdefghjkmnpqrstuvwxyz23456789";
+
+    let mut rng = rand::rng();
+    (0..TEMPORARY_PASSWORD_LENGTH)
+        .map(|_| ALPHABET[rng.random_range(0..ALPHABET.len())] as char)
+        .collect()
 }
 
 pub struct DatabaseUserService {
     salt: SaltString,
     pool: PgPool,
+    totp_cipher: TotpCipher,
+    consumer_health: ConsumerHealth,
 }
 
 impl DatabaseUserService {
@@ -253,6 +482,8 @@ impl DatabaseUserService {
         db_name: S,
         credentials: Credentials,
         salt: SaltString,
+        totp_cipher: TotpCipher,
+        nats_address: impl AsRef<str>,
     ) -> Result<Self, Error> {
         let pool = PgPoolOptions::new()
             .max_connections(20)
@@ -270,7 +501,52 @@ impl DatabaseUserService {
 
         sqlx::migrate!().run(&pool).await?;
 
-        Ok(Self { salt, pool })
+        let consumer_health = ConsumerHealth::new();
+
+        let _join_handle = {
+            let pool = pool.clone();
+            let nats_client = NatsClient::new(nats_address.as_ref()).await?;
+            let consumer_health = consumer_health.clone();
+            rocket::tokio::spawn(async move {
+                println!("NATS event worker started: ErasureEvents");
+                nats_client
+                    .consume::<ErasureEvent>(
+                        "ERASURE_EVENTS",
+                        "user-service".to_owned(),
+                        consumer_health,
+                        async move |envelope| {
+                            match envelope.event {
+                                ErasureEvent::Completed { user_id, service } => {
+                                    sqlx::query(
+                                        "INSERT INTO erasure_confirmations (user_id, service) VALUES ($1, $2)
+                                            ON CONFLICT DO NOTHING",
+                                    )
+                                    .bind(user_id)
+                                    .bind(&service)
+                                    .execute(&pool)
+                                    .await?;
+                                }
+                            }
+                            Ok(())
+                        },
+                    )
+                    .await;
+            })
+        };
+
+        OutboxRelay::new(pool.clone(), nats_address.as_ref(), "user-service").spawn();
+
+        Ok(Self {
+            salt,
+            pool,
+            totp_cipher,
+            consumer_health,
+        })
+    }
+
+    /// The state of the service's NATS consumers, for its `/health` route.
+    pub fn consumer_health(&self) -> ConsumerHealth {
+        self.consumer_health.clone()
     }
 
     fn hash_password(&self, password: &str) -> String {
@@ -279,6 +555,157 @@ impl DatabaseUserService {
             .unwrap()
             .to_string()
     }
+
+    async fn verify_password(&self, id: &UserId, password: &str) -> Result<bool, Error> {
+        let password_hash = self.hash_password(password);
+        Ok(sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM users WHERE (id = $1 AND password_hash = $2))",
+        )
+        .bind(id)
+        .bind(&password_hash)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    /// Loads and decrypts the user's TOTP secret together with the last time step that was
+    /// accepted for it.
+    async fn load_totp(
+        &self,
+        id: &UserId,
+        confirmed: bool,
+    ) -> Result<Option<(totp_rs::TOTP, u64)>, Error> {
+        let Some((username, nonce, ciphertext, last_used_step)) =
+            sqlx::query_as::<_, (Username, Vec<u8>, Vec<u8>, i64)>(
+                "SELECT users.username, totp_secrets.secret_nonce, totp_secrets.secret_ciphertext, totp_secrets.last_used_step
+                    FROM totp_secrets JOIN users ON (users.id = totp_secrets.user_id)
+                    WHERE (totp_secrets.user_id = $1 AND totp_secrets.confirmed = $2)",
+            )
+            .bind(id)
+            .bind(confirmed)
+            .fetch_optional(&self.pool)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let secret = self.totp_cipher.decrypt(&nonce, &ciphertext)?;
+        let totp = totp::build_totp(secret, &username)?;
+        Ok(Some((totp, last_used_step as u64)))
+    }
+
+    async fn get_user_id(&self, username: &Username) -> Result<Option<UserId>, Error> {
+        Ok(
+            sqlx::query_scalar::<_, UserId>("SELECT id FROM users WHERE (username = $1)")
+                .bind(username)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
+    }
+
+    /// Returns the number of seconds until the longest active lockout among `keys` ends.
+    async fn remaining_lockout(
+        &self,
+        keys: &[(ThrottleKind, String)],
+    ) -> Result<Option<u64>, Error> {
+        let mut remaining = None;
+        for (kind, key) in keys {
+            let seconds = sqlx::query_scalar::<_, i64>(
+                "SELECT CEIL(EXTRACT(EPOCH FROM (locked_until - now())))::BIGINT FROM login_throttles
+                    WHERE (kind = $1 AND key = $2 AND locked_until > now())",
+            )
+            .bind(kind.as_str())
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await?;
+            remaining = remaining.max(seconds.map(|seconds| seconds.max(1) as u64));
+        }
+        Ok(remaining)
+    }
+
+    /// Counts a failed login against `key` and returns the length of the lockout in seconds
+    /// if this failure caused one.
+    async fn record_login_failure(
+        &self,
+        tx: &mut PgConnection,
+        kind: ThrottleKind,
+        key: &str,
+    ) -> Result<Option<u64>, Error> {
+        let policy = kind.policy();
+        let failures = sqlx::query_scalar::<_, i32>(
+            "INSERT INTO login_throttles (kind, key, failures) VALUES ($1, $2, 1)
+                ON CONFLICT (kind, key) DO UPDATE SET
+                    failures = CASE
+                        WHEN login_throttles.last_failure < now() - make_interval(secs => $3) THEN 1
+                        ELSE login_throttles.failures + 1
+                    END,
+                    last_failure = now()
+                RETURNING failures",
+        )
+        .bind(kind.as_str())
+        .bind(key)
+        .bind(policy.reset_after.as_secs_f64())
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let Some(lockout) = policy.lockout_for(failures as u32) else {
+            return Ok(None);
+        };
+        sqlx::query(
+            "UPDATE login_throttles SET locked_until = now() + make_interval(secs => $3) WHERE (kind = $1 AND key = $2)",
+        )
+        .bind(kind.as_str())
+        .bind(key)
+        .bind(lockout.as_secs_f64())
+        .execute(&mut *tx)
+        .await?;
+        Ok(Some(lockout.as_secs()))
+    }
+
+    async fn clear_login_failures(&self, kind: ThrottleKind, key: &str) -> Result<(), Error> {
+        sqlx::query("DELETE FROM login_throttles WHERE (kind = $1 AND key = $2)")
+            .bind(kind.as_str())
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Checks a TOTP or recovery code, consuming it if it's valid.
+    async fn check_totp_code(&self, id: &UserId, code: &str) -> Result<bool, Error> {
+        let code = totp::normalize_code(code);
+        if !totp::is_totp_code(&code) {
+            return self.use_recovery_code(id, &code).await;
+        }
+
+        let Some((totp, last_used_step)) = self.load_totp(id, true).await? else {
+            return Ok(false);
+        };
+        let Some(step) = totp::find_matching_step(&totp, &code, last_used_step) else {
+            return Ok(false);
+        };
+
+        // Guarded by last_used_step so two concurrent requests can't both use the same code.
+        let res = sqlx::query(
+            "UPDATE totp_secrets SET last_used_step = $2 WHERE (user_id = $1 AND last_used_step < $2)",
+        )
+        .bind(id)
+        .bind(step as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected() == 1)
+    }
+
+    async fn use_recovery_code(&self, id: &UserId, code: &str) -> Result<bool, Error> {
+        let code_hash = self.hash_password(&totp::normalize_code(code));
+        let res = sqlx::query(
+            "UPDATE totp_recovery_codes SET used_at = now() WHERE (user_id = $1 AND code_hash = $2 AND used_at IS NULL)",
+        )
+        .bind(id)
+        .bind(&code_hash)
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected() == 1)
+    }
 }
 
 #[async_trait]
@@ -292,15 +719,91 @@ impl UserService for DatabaseUserService {
         )
     }
 
-    async fn login(&self, username: &Username, password: &str) -> Result<Option<UserId>, Error> {
+    async fn get_user_profile(&self, username: &Username) -> Result<Option<UserProfile>, Error> {
+        Ok(sqlx::query_as::<_, UserProfile>(
+            "SELECT id, username, created_at FROM users WHERE (username = $1)",
+        )
+        .bind(username)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn login(
+        &self,
+        username: &Username,
+        password: &str,
+        client_ip: Option<IpAddr>,
+    ) -> Result<LoginResult, Error> {
+        let mut throttle_keys = vec![(ThrottleKind::Username, username.to_string())];
+        if let Some(client_ip) = client_ip {
+            throttle_keys.push((ThrottleKind::Ip, client_ip.to_string()));
+        }
+
+        if let Some(retry_after) = self.remaining_lockout(&throttle_keys).await? {
+            return Ok(LoginResult::LockedOut(Lockout {
+                retry_after,
+                user_id: self.get_user_id(username).await?,
+                started: false,
+            }));
+        }
+
         let password_hash = self.hash_password(password);
-        Ok(sqlx::query_scalar::<_, UserId>(
-            "SELECT id FROM users WHERE (username = $1 AND password_hash = $2)",
+        let user = sqlx::query_as::<_, (UserId, bool, bool)>(
+            "SELECT id, EXISTS(SELECT 1 FROM totp_secrets WHERE (user_id = users.id AND confirmed)),
+                    banned_at IS NOT NULL
+                FROM users WHERE (username = $1 AND password_hash = $2)",
         )
         .bind(username)
         .bind(&password_hash)
         .fetch_optional(&self.pool)
-        .await?)
+        .await?;
+
+        let Some((id, totp_enabled, banned)) = user else {
+            let mut tx = self.pool.begin().await?;
+            let mut retry_after = None;
+            for (kind, key) in &throttle_keys {
+                if let Some(lockout) = self.record_login_failure(&mut tx, *kind, key).await? {
+                    retry_after = retry_after.max(Some(lockout));
+                }
+            }
+            let Some(retry_after) = retry_after else {
+                tx.commit().await?;
+                return Ok(LoginResult::InvalidCredentials);
+            };
+
+            let user_id = self.get_user_id(username).await?;
+            if let Some(user_id) = user_id {
+                outbox::enqueue(
+                    &mut *tx,
+                    &UserEvent::LockedOut {
+                        user_id,
+                        retry_after,
+                    },
+                )
+                .await?;
+            }
+            tx.commit().await?;
+            return Ok(LoginResult::LockedOut(Lockout {
+                retry_after,
+                user_id,
+                started: true,
+            }));
+        };
+
+        if banned {
+            return Ok(LoginResult::Banned);
+        }
+        if totp_enabled {
+            // The counter is cleared once the code was verified as well, otherwise knowing the
+            // password would be enough to keep guessing other users' passwords for free.
+            return Ok(LoginResult::TotpRequired(id));
+        }
+
+        // Only the username counter is cleared, otherwise an attacker could reset the counter
+        // of their address by logging into an account of their own.
+        self.clear_login_failures(ThrottleKind::Username, username.as_str())
+            .await?;
+        Ok(LoginResult::Success(id))
     }
 
     async fn create_user(
@@ -309,36 +812,83 @@ impl UserService for DatabaseUserService {
         password: &str,
     ) -> Result<Option<UserId>, Error> {
         let password_hash = self.hash_password(password);
-        match sqlx::query_scalar::<_, UserId>(
+        let mut tx = self.pool.begin().await?;
+        let id = match sqlx::query_scalar::<_, UserId>(
             "INSERT INTO users (username, password_hash) VALUES ($1, $2) RETURNING id",
         )
         .bind(username)
         .bind(&password_hash)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *tx)
         .await
         {
-            Ok(id) => Ok(Some(id)),
+            Ok(id) => id,
             Err(sqlx::Error::Database(db_error)) if db_error.constraint() == Some("users_pkey") => {
-                Ok(None)
+                return Ok(None);
             }
-            Err(e) => Err(e.into()),
-        }
+            Err(e) => return Err(e.into()),
+        };
+        outbox::enqueue(&mut *tx, &UserEvent::Created(id)).await?;
+        tx.commit().await?;
+        Ok(Some(id))
     }
 
     async fn delete_user(&self, id: &UserId) -> Result<bool, Error> {
-        let res = sqlx::query("DELETE FROM users WHERE (id = $1)")
+        let mut transaction = self.pool.begin().await?;
+
+        let Some(username) = sqlx::query_scalar::<_, Username>(
+            "DELETE FROM users WHERE (id = $1) RETURNING username",
+        )
+        .bind(id)
+        .fetch_optional(&mut *transaction)
+        .await?
+        else {
+            return Ok(false);
+        };
+
+        sqlx::query("DELETE FROM login_throttles WHERE (kind = $1 AND key = $2)")
+            .bind(ThrottleKind::Username.as_str())
+            .bind(username.as_str())
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query("INSERT INTO erasure_requests (user_id) VALUES ($1) ON CONFLICT DO NOTHING")
             .bind(id)
-            .execute(&self.pool)
+            .execute(&mut *transaction)
             .await?;
+        outbox::enqueue(&mut *transaction, &UserEvent::Deleted(*id)).await?;
 
-        match res.rows_affected() {
-            0 => Ok(false),
-            1 => Ok(true),
-            x => unreachable!(
-                "SQL 'DELETE FROM users' query is constrained by primary key (id) but multiple rows ({}) were affected",
-                x
-            ),
-        }
+        transaction.commit().await?;
+        Ok(true)
+    }
+
+    async fn get_erasure_status(&self, id: &UserId) -> Result<Option<ErasureStatus>, Error> {
+        let Some(requested_at) = sqlx::query_scalar::<_, DateTime<Utc>>(
+            "SELECT requested_at FROM erasure_requests WHERE (user_id = $1)",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let confirmed = sqlx::query_scalar::<_, String>(
+            "SELECT service FROM erasure_confirmations WHERE (user_id = $1) ORDER BY confirmed_at",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+        let pending = ERASURE_SERVICES
+            .iter()
+            .filter(|service| !confirmed.iter().any(|confirmed| confirmed == *service))
+            .map(|service| service.to_string())
+            .collect();
+
+        Ok(Some(ErasureStatus {
+            user_id: *id,
+            requested_at,
+            confirmed,
+            pending,
+        }))
     }
 
     async fn change_password(
@@ -368,6 +918,43 @@ impl UserService for DatabaseUserService {
         }
     }
 
+    async fn change_username(&self, id: &UserId, username: Username) -> Result<bool, Error> {
+        let mut tx = self.pool.begin().await?;
+        let res = match sqlx::query("UPDATE users SET username = $2 WHERE (id = $1)")
+            .bind(id)
+            .bind(&username)
+            .execute(&mut *tx)
+            .await
+        {
+            Ok(res) => res,
+            Err(sqlx::Error::Database(db_error))
+                if db_error.constraint() == Some("users_username_key") =>
+            {
+                return Ok(false);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        match res.rows_affected() {
+            0 => Ok(false),
+            1 => {
+                outbox::enqueue(
+                    &mut *tx,
+                    &UserEvent::Renamed {
+                        user_id: *id,
+                        username,
+                    },
+                )
+                .await?;
+                tx.commit().await?;
+                Ok(true)
+            }
+            x => unreachable!(
+                "SQL 'UPDATE users' query is constrained by primary key (id) but multiple rows ({}) were affected",
+                x
+            ),
+        }
+    }
+
     async fn user_exists(&self, id: &UserId) -> Result<bool, Error> {
         Ok(
             sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE (id = $1))")
@@ -376,11 +963,276 @@ impl UserService for DatabaseUserService {
                 .await?,
         )
     }
-}
 
-pub struct BackendUserService {
-    address: String,
-    client: Client,
+    async fn is_totp_enabled(&self, id: &UserId) -> Result<bool, Error> {
+        Ok(sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM totp_secrets WHERE (user_id = $1 AND confirmed))",
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn verify_totp(&self, id: &UserId, code: &str) -> Result<LoginResult, Error> {
+        let throttle_key = [(ThrottleKind::Totp, id.to_string())];
+        if let Some(retry_after) = self.remaining_lockout(&throttle_key).await? {
+            return Ok(LoginResult::LockedOut(Lockout {
+                retry_after,
+                user_id: Some(*id),
+                started: false,
+            }));
+        }
+
+        if self.check_totp_code(id, code).await? {
+            self.clear_login_failures(ThrottleKind::Totp, &throttle_key[0].1)
+                .await?;
+            if let Some(user) = self.get_user(id).await? {
+                self.clear_login_failures(ThrottleKind::Username, user.username.as_str())
+                    .await?;
+            }
+            return Ok(LoginResult::Success(*id));
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let Some(retry_after) = self
+            .record_login_failure(&mut tx, ThrottleKind::Totp, &throttle_key[0].1)
+            .await?
+        else {
+            tx.commit().await?;
+            return Ok(LoginResult::InvalidCredentials);
+        };
+        outbox::enqueue(
+            &mut *tx,
+            &UserEvent::LockedOut {
+                user_id: *id,
+                retry_after,
+            },
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(LoginResult::LockedOut(Lockout {
+            retry_after,
+            user_id: Some(*id),
+            started: true,
+        }))
+    }
+
+    async fn begin_totp_enrollment(&self, id: &UserId) -> Result<Option<TotpEnrollment>, Error> {
+        let Some(user) = self.get_user(id).await? else {
+            return Ok(None);
+        };
+
+        let secret = totp::generate_secret();
+        let (nonce, ciphertext) = self.totp_cipher.encrypt(&secret)?;
+        let totp = totp::build_totp(secret, &user.username)?;
+
+        let res = sqlx::query(
+            "INSERT INTO totp_secrets (user_id, secret_nonce, secret_ciphertext) VALUES ($1, $2, $3)
+                ON CONFLICT (user_id) DO UPDATE SET
+                    secret_nonce = EXCLUDED.secret_nonce,
+                    secret_ciphertext = EXCLUDED.secret_ciphertext,
+                    last_used_step = 0,
+                    created_at = now()
+                WHERE NOT totp_secrets.confirmed",
+        )
+        .bind(id)
+        .bind(&nonce)
+        .bind(&ciphertext)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(match res.rows_affected() {
+            0 => None,
+            _ => Some(totp::enrollment(&totp)),
+        })
+    }
+
+    async fn confirm_totp_enrollment(
+        &self,
+        id: &UserId,
+        code: &str,
+    ) -> Result<Option<Box<[String]>>, Error> {
+        let code = totp::normalize_code(code);
+        let Some((totp, last_used_step)) = self.load_totp(id, false).await? else {
+            return Ok(None);
+        };
+        let Some(step) = totp::find_matching_step(&totp, &code, last_used_step) else {
+            return Ok(None);
+        };
+
+        let recovery_codes = totp::generate_recovery_codes();
+
+        let mut tx = self.pool.begin().await?;
+        let res = sqlx::query(
+            "UPDATE totp_secrets SET confirmed = true, last_used_step = $2 WHERE (user_id = $1 AND NOT confirmed)",
+        )
+        .bind(id)
+        .bind(step as i64)
+        .execute(&mut *tx)
+        .await?;
+        if res.rows_affected() != 1 {
+            return Ok(None);
+        }
+        sqlx::query("DELETE FROM totp_recovery_codes WHERE (user_id = $1)")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        for recovery_code in &recovery_codes {
+            sqlx::query("INSERT INTO totp_recovery_codes (user_id, code_hash) VALUES ($1, $2)")
+                .bind(id)
+                .bind(self.hash_password(&totp::normalize_code(recovery_code)))
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(Some(recovery_codes.into_boxed_slice()))
+    }
+
+    async fn disable_totp(&self, id: &UserId, password: &str, code: &str) -> Result<bool, Error> {
+        if !self.verify_password(id, password).await? || !self.check_totp_code(id, code).await? {
+            return Ok(false);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM totp_secrets WHERE (user_id = $1)")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM totp_recovery_codes WHERE (user_id = $1)")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    async fn get_user_account(&self, id: &UserId) -> Result<Option<UserAccount>, Error> {
+        Ok(sqlx::query_as::<_, UserAccount>(
+            "SELECT id, username, created_at, is_admin, banned_at, ban_reason, sessions_revoked_at
+                FROM users WHERE (id = $1)",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?)
+    }
+
+    async fn search_users(&self, query: &str) -> Result<Box<[UserAccount]>, Error> {
+        Ok(sqlx::query_as::<_, UserAccount>(
+            "SELECT id, username, created_at, is_admin, banned_at, ban_reason, sessions_revoked_at
+                FROM users WHERE (username ILIKE $1 ESCAPE '\\' OR id::TEXT = $2)
+                ORDER BY username LIMIT $3",
+        )
+        .bind(format!("%{}%", escape_like(query)))
+        .bind(query)
+        .bind(USER_SEARCH_LIMIT)
+        .fetch_all(&self.pool)
+        .await?
+        .into_boxed_slice())
+    }
+
+    async fn ban_user(&self, id: &UserId, reason: &str) -> Result<bool, Error> {
+        let res = sqlx::query(
+            "UPDATE users SET banned_at = now(), ban_reason = $2, sessions_revoked_at = now()
+                WHERE (id = $1)",
+        )
+        .bind(id)
+        .bind(reason)
+        .execute(&self.pool)
+        .await?;
+        Ok(res.rows_affected() == 1)
+    }
+
+    async fn unban_user(&self, id: &UserId) -> Result<bool, Error> {
+        let res =
+            sqlx::query("UPDATE users SET banned_at = NULL, ban_reason = NULL WHERE (id = $1)")
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+        Ok(res.rows_affected() == 1)
+    }
+
+    async fn reset_password(&self, id: &UserId) -> Result<Option<String>, Error> {
+        let password = generate_temporary_password();
+        let res = sqlx::query(
+            "UPDATE users SET password_hash = $2, sessions_revoked_at = now() WHERE (id = $1)",
+        )
+        .bind(id)
+        .bind(self.hash_password(&password))
+        .execute(&self.pool)
+        .await?;
+        Ok((res.rows_affected() == 1).then_some(password))
+    }
+
+    async fn revoke_sessions(&self, id: &UserId) -> Result<bool, Error> {
+        let res = sqlx::query("UPDATE users SET sessions_revoked_at = now() WHERE (id = $1)")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(res.rows_affected() == 1)
+    }
+
+    async fn set_admin(&self, id: &UserId, is_admin: bool) -> Result<bool, Error> {
+        let action = if is_admin {
+            AdminAction::GrantAdmin
+        } else {
+            AdminAction::RevokeAdmin
+        };
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("INSERT INTO admin_audit_log (admin_id, action, target) VALUES (NULL, $1, $2)")
+            .bind(action.as_str())
+            .bind(id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        let res = sqlx::query("UPDATE users SET is_admin = $2 WHERE (id = $1)")
+            .bind(id)
+            .bind(is_admin)
+            .execute(&mut *tx)
+            .await?;
+        if res.rows_affected() != 1 {
+            return Ok(false);
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn record_admin_action(
+        &self,
+        admin_id: &UserId,
+        action: AdminAction,
+        target: &str,
+        details: Option<&str>,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            "INSERT INTO admin_audit_log (admin_id, action, target, details) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(admin_id)
+        .bind(action.as_str())
+        .bind(target)
+        .bind(details)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn list_admin_actions(&self, limit: u32) -> Result<Box<[AuditEntry]>, Error> {
+        Ok(sqlx::query_as::<_, AuditEntry>(
+            "SELECT log.admin_id, users.username AS admin_username, log.action, log.target,
+                    log.details, log.created_at
+                FROM admin_audit_log log LEFT JOIN users ON (users.id = log.admin_id)
+                ORDER BY log.created_at DESC LIMIT $1",
+        )
+        .bind(i64::from(limit))
+        .fetch_all(&self.pool)
+        .await?
+        .into_boxed_slice())
+    }
+}
+
+pub struct BackendUserService {
+    address: String,
+    client: Client,
 }
 
 impl BackendUserService {
@@ -396,6 +1248,7 @@ impl BackendUserService {
 pub struct LoginRequest<'a> {
     pub username: Username,
     pub password: &'a str,
+    pub client_ip: Option<IpAddr>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -411,6 +1264,55 @@ pub struct ChangePasswordRequest<'a> {
     pub new_password: &'a str,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct ChangeUsernameRequest {
+    pub user_id: UserId,
+    pub username: Username,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TotpEnrollmentRequest {
+    pub user_id: UserId,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TotpCodeRequest<'a> {
+    pub user_id: UserId,
+    pub code: &'a str,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DisableTotpRequest<'a> {
+    pub user_id: UserId,
+    pub password: &'a str,
+    pub code: &'a str,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AdminUserRequest {
+    pub user_id: UserId,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SetAdminRequest {
+    pub user_id: UserId,
+    pub is_admin: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BanUserRequest<'a> {
+    pub user_id: UserId,
+    pub reason: &'a str,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AdminActionRequest<'a> {
+    pub admin_id: UserId,
+    pub action: AdminAction,
+    pub target: &'a str,
+    pub details: Option<&'a str>,
+}
+
 #[async_trait]
 impl UserService for BackendUserService {
     async fn get_user(&self, id: &UserId) -> Result<Option<User>, Error> {
@@ -426,10 +1328,29 @@ impl UserService for BackendUserService {
         }
     }
 
-    async fn login(&self, username: &Username, password: &str) -> Result<Option<UserId>, Error> {
+    async fn get_user_profile(&self, username: &Username) -> Result<Option<UserProfile>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/by-username/{}", &self.address, username))
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => Ok(Some(response.json().await?)),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn login(
+        &self,
+        username: &Username,
+        password: &str,
+        client_ip: Option<IpAddr>,
+    ) -> Result<LoginResult, Error> {
         let credentials = LoginRequest {
             username: username.clone(),
             password,
+            client_ip,
         };
         let response = self
             .client
@@ -438,8 +1359,9 @@ impl UserService for BackendUserService {
             .send()
             .await?;
         match response.status() {
-            StatusCode::OK => Ok(Some(UserId::try_parse(response.text().await?)?)),
-            StatusCode::UNAUTHORIZED => Ok(None),
+            StatusCode::OK | StatusCode::TOO_MANY_REQUESTS => Ok(response.json().await?),
+            StatusCode::FORBIDDEN => Ok(LoginResult::Banned),
+            StatusCode::UNAUTHORIZED => Ok(LoginResult::InvalidCredentials),
             _ => Err(Error::InvalidResponse),
         }
     }
@@ -476,6 +1398,19 @@ impl UserService for BackendUserService {
         }
     }
 
+    async fn get_erasure_status(&self, id: &UserId) -> Result<Option<ErasureStatus>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}/erasure", &self.address, id))
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => Ok(Some(response.json().await?)),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
     async fn change_password(
         &self,
         id: &UserId,
@@ -503,77 +1438,272 @@ impl UserService for BackendUserService {
         }
     }
 
+    async fn change_username(&self, id: &UserId, username: Username) -> Result<bool, Error> {
+        let request_data = ChangeUsernameRequest {
+            user_id: *id,
+            username,
+        };
+        let response = self
+            .client
+            .post(format!("{}/change-username", &self.address))
+            .json(&request_data)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => response
+                .text()
+                .await?
+                .parse::<bool>()
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
     async fn user_exists(&self, id: &UserId) -> Result<bool, Error> {
         Ok(self.get_user(id).await?.is_some())
     }
-}
 
-pub struct UserServiceNatsWrapper {
-    user_service: Arc<dyn UserService>,
-    nats_client: NatsClient,
-}
-
-impl UserServiceNatsWrapper {
-    pub async fn new(
-        user_service: Arc<dyn UserService>,
-        nats_address: impl AsRef<str>,
-    ) -> Result<Self, Error> {
-        let nats_client = NatsClient::new(nats_address).await?;
-        Ok(Self {
-            user_service,
-            nats_client,
-        })
+    async fn is_totp_enabled(&self, id: &UserId) -> Result<bool, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}/totp", &self.address, id))
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => response
+                .text()
+                .await?
+                .parse::<bool>()
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
     }
-}
 
-#[async_trait]
-impl UserService for UserServiceNatsWrapper {
-    async fn get_user(&self, id: &UserId) -> Result<Option<User>, Error> {
-        self.user_service.get_user(id).await
+    async fn verify_totp(&self, id: &UserId, code: &str) -> Result<LoginResult, Error> {
+        let request_data = TotpCodeRequest { user_id: *id, code };
+        let response = self
+            .client
+            .post(format!("{}/totp/verify", &self.address))
+            .json(&request_data)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK | StatusCode::TOO_MANY_REQUESTS => Ok(response.json().await?),
+            StatusCode::UNAUTHORIZED => Ok(LoginResult::InvalidCredentials),
+            _ => Err(Error::InvalidResponse),
+        }
     }
 
-    async fn login(&self, username: &Username, password: &str) -> Result<Option<UserId>, Error> {
-        self.user_service.login(username, password).await
+    async fn begin_totp_enrollment(&self, id: &UserId) -> Result<Option<TotpEnrollment>, Error> {
+        let request_data = TotpEnrollmentRequest { user_id: *id };
+        let response = self
+            .client
+            .post(format!("{}/totp/enroll", &self.address))
+            .json(&request_data)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => Ok(Some(response.json().await?)),
+            StatusCode::CONFLICT => Ok(None),
+            _ => Err(Error::InvalidResponse),
+        }
     }
 
-    async fn create_user(
+    async fn confirm_totp_enrollment(
         &self,
-        username: Username,
-        password: &str,
-    ) -> Result<Option<UserId>, Error> {
-        let res = self.user_service.create_user(username, password).await;
-        match res {
-            Ok(Some(id)) => self.nats_client.emit(UserEvent::Created(id)).await?,
-            _ => (),
+        id: &UserId,
+        code: &str,
+    ) -> Result<Option<Box<[String]>>, Error> {
+        let request_data = TotpCodeRequest { user_id: *id, code };
+        let response = self
+            .client
+            .post(format!("{}/totp/confirm", &self.address))
+            .json(&request_data)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => Ok(Some(response.json().await?)),
+            StatusCode::UNAUTHORIZED => Ok(None),
+            _ => Err(Error::InvalidResponse),
         }
-        return res;
     }
 
-    async fn delete_user(&self, id: &UserId) -> Result<bool, Error> {
-        let res = self.user_service.delete_user(id).await;
-        match res {
-            Ok(true) => {
-                self.nats_client
-                    .emit(UserEvent::Deleted(id.clone()))
-                    .await?
-            }
-            _ => (),
+    async fn disable_totp(&self, id: &UserId, password: &str, code: &str) -> Result<bool, Error> {
+        let request_data = DisableTotpRequest {
+            user_id: *id,
+            password,
+            code,
+        };
+        let response = self
+            .client
+            .post(format!("{}/totp/disable", &self.address))
+            .json(&request_data)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => response
+                .text()
+                .await?
+                .parse::<bool>()
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
         }
-        return res;
     }
 
-    async fn change_password(
+    async fn get_user_account(&self, id: &UserId) -> Result<Option<UserAccount>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/{}/account", &self.address, id))
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => Ok(Some(response.json().await?)),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn search_users(&self, query: &str) -> Result<Box<[UserAccount]>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/search", &self.address))
+            .query(&[("query", query)])
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn ban_user(&self, id: &UserId, reason: &str) -> Result<bool, Error> {
+        let request_data = BanUserRequest {
+            user_id: *id,
+            reason,
+        };
+        let response = self
+            .client
+            .post(format!("{}/admin/ban", &self.address))
+            .json(&request_data)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => response
+                .text()
+                .await?
+                .parse::<bool>()
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn unban_user(&self, id: &UserId) -> Result<bool, Error> {
+        let request_data = AdminUserRequest { user_id: *id };
+        let response = self
+            .client
+            .post(format!("{}/admin/unban", &self.address))
+            .json(&request_data)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => response
+                .text()
+                .await?
+                .parse::<bool>()
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn reset_password(&self, id: &UserId) -> Result<Option<String>, Error> {
+        let request_data = AdminUserRequest { user_id: *id };
+        let response = self
+            .client
+            .post(format!("{}/admin/reset-password", &self.address))
+            .json(&request_data)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => Ok(Some(response.text().await?)),
+            StatusCode::NOT_FOUND => Ok(None),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn revoke_sessions(&self, id: &UserId) -> Result<bool, Error> {
+        let request_data = AdminUserRequest { user_id: *id };
+        let response = self
+            .client
+            .post(format!("{}/admin/revoke-sessions", &self.address))
+            .json(&request_data)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => response
+                .text()
+                .await?
+                .parse::<bool>()
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn set_admin(&self, id: &UserId, is_admin: bool) -> Result<bool, Error> {
+        let request_data = SetAdminRequest {
+            user_id: *id,
+            is_admin,
+        };
+        let response = self
+            .client
+            .post(format!("{}/admin/set-admin", &self.address))
+            .json(&request_data)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => response
+                .text()
+                .await?
+                .parse::<bool>()
+                .map_err(|_| Error::InvalidResponse),
+            _ => Err(Error::InvalidResponse),
+        }
+    }
+
+    async fn record_admin_action(
         &self,
-        id: &UserId,
-        old_password: &str,
-        new_password: &str,
-    ) -> Result<bool, Error> {
-        self.user_service
-            .change_password(id, old_password, new_password)
-            .await
+        admin_id: &UserId,
+        action: AdminAction,
+        target: &str,
+        details: Option<&str>,
+    ) -> Result<(), Error> {
+        let request_data = AdminActionRequest {
+            admin_id: *admin_id,
+            action,
+            target,
+            details,
+        };
+        let response = self
+            .client
+            .post(format!("{}/admin/audit", &self.address))
+            .json(&request_data)
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::NO_CONTENT => Ok(()),
+            _ => Err(Error::InvalidResponse),
+        }
     }
 
-    async fn user_exists(&self, id: &UserId) -> Result<bool, Error> {
-        self.user_service.user_exists(id).await
+    async fn list_admin_actions(&self, limit: u32) -> Result<Box<[AuditEntry]>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/admin/audit", &self.address))
+            .query(&[("limit", limit)])
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => Err(Error::InvalidResponse),
+        }
     }
 }
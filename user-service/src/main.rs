@@ -1,11 +1,15 @@
 use std::{env, sync::Arc};
 
 use codequest_common::{
-    Credentials, Error, User, UserId, load_salt, load_secret_key, services::UserService,
+    Credentials, ErasureStatus, Error, LoginResult, TotpEnrollment, User, UserAccount, UserId,
+    UserProfile, Username, admin::AuditEntry, load_salt, load_secret_key, nats::ConsumerHealth,
+    services::UserService,
 };
 use codequest_user_service::{
-    ChangePasswordRequest, CreateUserRequest, DatabaseUserService, LoginRequest,
-    UserServiceNatsWrapper,
+    AdminActionRequest, AdminUserRequest, BackendUserService, BanUserRequest,
+    ChangePasswordRequest, ChangeUsernameRequest, CreateUserRequest, DatabaseUserService,
+    DisableTotpRequest, LoginRequest, SetAdminRequest, TotpCodeRequest, TotpEnrollmentRequest,
+    totp::TotpCipher,
 };
 use dotenv::dotenv;
 use rocket::{
@@ -18,7 +22,9 @@ use rocket::{
 mod defaults {
     pub const SALT_FILE: &'static str = "./secrets/salt";
     pub const SECRET_KEY_FILE: &'static str = "./secrets/secret_key";
+    pub const TOTP_KEY_FILE: &'static str = "./secrets/totp_key";
     pub const PORT: u16 = 8000;
+    pub const AUDIT_LOG_LIMIT: u32 = 100;
 }
 
 #[rocket::get("/<user_id>")]
@@ -33,6 +39,23 @@ async fn get_user(
     })
 }
 
+#[rocket::get("/by-username/<username>", rank = 2)]
+async fn get_user_profile(
+    username: &str,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<Result<Json<UserProfile>, status::NotFound<RawJson<&'static str>>>, Error> {
+    let Ok(username) = Username::new(username) else {
+        return Ok(Err(status::NotFound(RawJson(""))));
+    };
+    Ok(
+        if let Some(profile) = user_service.get_user_profile(&username).await? {
+            Ok(Json(profile))
+        } else {
+            Err(status::NotFound(RawJson("")))
+        },
+    )
+}
+
 #[rocket::post("/", format = "json", data = "<request_data>")]
 async fn create_user(
     request_data: Json<CreateUserRequest<'_>>,
@@ -63,6 +86,20 @@ async fn delete_user(
     })
 }
 
+#[rocket::get("/<user_id>/erasure")]
+async fn get_erasure_status(
+    user_id: UserId,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<Result<Json<ErasureStatus>, status::NotFound<RawJson<&'static str>>>, Error> {
+    Ok(
+        if let Some(erasure_status) = user_service.get_erasure_status(&user_id).await? {
+            Ok(Json(erasure_status))
+        } else {
+            Err(status::NotFound(RawJson("")))
+        },
+    )
+}
+
 #[rocket::post("/change-password", format = "json", data = "<request_data>")]
 async fn change_password(
     request_data: Json<ChangePasswordRequest<'_>>,
@@ -78,26 +115,260 @@ async fn change_password(
         .map(|password_was_changed| password_was_changed.to_string())
 }
 
+#[rocket::post("/change-username", format = "json", data = "<request_data>")]
+async fn change_username(
+    request_data: Json<ChangeUsernameRequest>,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<String, Error> {
+    let request_data = request_data.0;
+    user_service
+        .change_username(&request_data.user_id, request_data.username)
+        .await
+        .map(|username_was_changed| username_was_changed.to_string())
+}
+
 #[rocket::post("/login", format = "json", data = "<request_data>")]
 async fn login(
     request_data: Json<LoginRequest<'_>>,
     user_service: &State<Arc<dyn UserService>>,
-) -> Result<Result<(http::Status, String), status::Unauthorized<&'static str>>, Error> {
+) -> Result<(http::Status, Json<LoginResult>), Error> {
+    let result = user_service
+        .login(
+            &request_data.username,
+            request_data.password,
+            request_data.client_ip,
+        )
+        .await?;
+    Ok((login_status(&result), Json(result)))
+}
+
+fn login_status(result: &LoginResult) -> http::Status {
+    match result {
+        LoginResult::Success(_) | LoginResult::TotpRequired(_) => http::Status::Ok,
+        LoginResult::InvalidCredentials => http::Status::Unauthorized,
+        LoginResult::LockedOut(_) => http::Status::TooManyRequests,
+        LoginResult::Banned => http::Status::Forbidden,
+    }
+}
+
+#[rocket::get("/<user_id>/account")]
+async fn get_user_account(
+    user_id: UserId,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<Result<Json<UserAccount>, status::NotFound<RawJson<&'static str>>>, Error> {
+    Ok(
+        if let Some(account) = user_service.get_user_account(&user_id).await? {
+            Ok(Json(account))
+        } else {
+            Err(status::NotFound(RawJson("")))
+        },
+    )
+}
+
+#[rocket::get("/search?<query>")]
+async fn search_users(
+    query: &str,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<Json<Box<[UserAccount]>>, Error> {
+    user_service.search_users(query).await.map(Json)
+}
+
+#[rocket::post("/admin/ban", format = "json", data = "<request_data>")]
+async fn ban_user(
+    request_data: Json<BanUserRequest<'_>>,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<String, Error> {
+    user_service
+        .ban_user(&request_data.user_id, request_data.reason)
+        .await
+        .map(|user_was_banned| user_was_banned.to_string())
+}
+
+#[rocket::post("/admin/unban", format = "json", data = "<request_data>")]
+async fn unban_user(
+    request_data: Json<AdminUserRequest>,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<String, Error> {
+    user_service
+        .unban_user(&request_data.user_id)
+        .await
+        .map(|user_was_unbanned| user_was_unbanned.to_string())
+}
+
+#[rocket::post("/admin/reset-password", format = "json", data = "<request_data>")]
+async fn reset_password(
+    request_data: Json<AdminUserRequest>,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<Result<String, status::NotFound<()>>, Error> {
+    Ok(user_service
+        .reset_password(&request_data.user_id)
+        .await?
+        .ok_or(status::NotFound(())))
+}
+
+#[rocket::post("/admin/revoke-sessions", format = "json", data = "<request_data>")]
+async fn revoke_sessions(
+    request_data: Json<AdminUserRequest>,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<String, Error> {
+    user_service
+        .revoke_sessions(&request_data.user_id)
+        .await
+        .map(|sessions_were_revoked| sessions_were_revoked.to_string())
+}
+
+#[rocket::post("/admin/set-admin", format = "json", data = "<request_data>")]
+async fn set_admin(
+    request_data: Json<SetAdminRequest>,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<String, Error> {
+    user_service
+        .set_admin(&request_data.user_id, request_data.is_admin)
+        .await
+        .map(|user_exists| user_exists.to_string())
+}
+
+#[rocket::post("/admin/audit", format = "json", data = "<request_data>")]
+async fn record_admin_action(
+    request_data: Json<AdminActionRequest<'_>>,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<status::NoContent, Error> {
+    user_service
+        .record_admin_action(
+            &request_data.admin_id,
+            request_data.action,
+            request_data.target,
+            request_data.details,
+        )
+        .await
+        .map(|_| status::NoContent)
+}
+
+#[rocket::get("/admin/audit?<limit>")]
+async fn list_admin_actions(
+    limit: Option<u32>,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<Json<Box<[AuditEntry]>>, Error> {
+    user_service
+        .list_admin_actions(limit.unwrap_or(defaults::AUDIT_LOG_LIMIT))
+        .await
+        .map(Json)
+}
+
+#[rocket::get("/<user_id>/totp")]
+async fn is_totp_enabled(
+    user_id: UserId,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<String, Error> {
+    user_service
+        .is_totp_enabled(&user_id)
+        .await
+        .map(|enabled| enabled.to_string())
+}
+
+#[rocket::post("/totp/verify", format = "json", data = "<request_data>")]
+async fn verify_totp(
+    request_data: Json<TotpCodeRequest<'_>>,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<(http::Status, Json<LoginResult>), Error> {
+    let result = user_service
+        .verify_totp(&request_data.user_id, request_data.code)
+        .await?;
+    Ok((login_status(&result), Json(result)))
+}
+
+#[rocket::post("/totp/enroll", format = "json", data = "<request_data>")]
+async fn begin_totp_enrollment(
+    request_data: Json<TotpEnrollmentRequest>,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<Result<Json<TotpEnrollment>, status::Conflict<&'static str>>, Error> {
     Ok(
         match user_service
-            .login(&request_data.username, request_data.password)
+            .begin_totp_enrollment(&request_data.user_id)
             .await?
         {
-            Some(user_id) => Ok((http::Status::Ok, user_id.to_string())),
+            Some(enrollment) => Ok(Json(enrollment)),
+            None => Err(status::Conflict("")),
+        },
+    )
+}
+
+#[rocket::post("/totp/confirm", format = "json", data = "<request_data>")]
+async fn confirm_totp_enrollment(
+    request_data: Json<TotpCodeRequest<'_>>,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<Result<Json<Box<[String]>>, status::Unauthorized<&'static str>>, Error> {
+    Ok(
+        match user_service
+            .confirm_totp_enrollment(&request_data.user_id, request_data.code)
+            .await?
+        {
+            Some(recovery_codes) => Ok(Json(recovery_codes)),
             None => Err(status::Unauthorized("")),
         },
     )
 }
 
+#[rocket::post("/totp/disable", format = "json", data = "<request_data>")]
+async fn disable_totp(
+    request_data: Json<DisableTotpRequest<'_>>,
+    user_service: &State<Arc<dyn UserService>>,
+) -> Result<String, Error> {
+    user_service
+        .disable_totp(
+            &request_data.user_id,
+            request_data.password,
+            request_data.code,
+        )
+        .await
+        .map(|totp_was_disabled| totp_was_disabled.to_string())
+}
+
+#[rocket::get("/health")]
+fn health(consumer_health: &State<ConsumerHealth>) -> ConsumerHealth {
+    consumer_health.inner().clone()
+}
+
+/// `admin grant <user ID>` and `admin revoke <user ID>`, which change admin rights through the
+/// user-service at `USER_SERVICE_ADDRESS`.
+async fn run_admin_command(args: &[String]) {
+    let address = env::var("USER_SERVICE_ADDRESS")
+        .unwrap_or_else(|_| format!("http://127.0.0.1:{}/user", defaults::PORT));
+    let user_service = BackendUserService::new(address);
+    let (id, is_admin) = match args {
+        [_, command, id] if command == "grant" => (id, true),
+        [_, command, id] if command == "revoke" => (id, false),
+        _ => {
+            eprintln!("usage: codequest-user-service admin grant <user ID>");
+            eprintln!("       codequest-user-service admin revoke <user ID>");
+            std::process::exit(2);
+        }
+    };
+    let id = UserId::try_parse(id).expect("invalid user ID");
+    if !user_service
+        .set_admin(&id, is_admin)
+        .await
+        .expect("failed to change admin rights")
+    {
+        eprintln!("user {} doesn't exist", id);
+        std::process::exit(1);
+    }
+    match is_admin {
+        true => println!("granted admin rights to {}", id),
+        false => println!("revoked admin rights of {}", id),
+    }
+}
+
 #[rocket::main]
 async fn main() -> Result<(), rocket::Error> {
     dotenv().ok();
 
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if args.first().is_some_and(|command| command == "admin") {
+        run_admin_command(&args).await;
+        return Ok(());
+    }
+
     let db_credentials = {
         let username =
             env::var("DB_USERNAME_USER_SERVICE").expect("DB_USERNAME_USER_SERVICE not set");
@@ -118,6 +389,12 @@ async fn main() -> Result<(), rocket::Error> {
     )
     .expect("failed to load secret key");
 
+    let totp_key = load_secret_key(
+        env::var("TOTP_KEY_FILE").unwrap_or_else(|_| defaults::TOTP_KEY_FILE.to_owned()),
+    )
+    .expect("failed to load totp key");
+    let totp_cipher = TotpCipher::from_base64(totp_key).expect("invalid totp key");
+
     let port = env::var("USER_SERVICE_PORT")
         .map(|port| {
             port.parse::<u16>()
@@ -129,18 +406,47 @@ async fn main() -> Result<(), rocket::Error> {
         .merge(("secret_key", secret_key))
         .merge(("port", port));
 
-    let user_service = DatabaseUserService::new(&db_address, &db_name, db_credentials, salt)
-        .await
-        .expect("failed to start DatabaseUserService");
-    let user_service = UserServiceNatsWrapper::new(Arc::new(user_service), nats_address)
-        .await
-        .expect("failed to start nats wrapper");
+    let user_service = DatabaseUserService::new(
+        &db_address,
+        &db_name,
+        db_credentials,
+        salt,
+        totp_cipher,
+        &nats_address,
+    )
+    .await
+    .expect("failed to start DatabaseUserService");
 
     rocket::custom(&rocket_config)
         .mount(
             "/user",
-            routes![get_user, create_user, delete_user, change_password, login],
+            routes![
+                get_user,
+                get_user_profile,
+                create_user,
+                delete_user,
+                get_erasure_status,
+                change_password,
+                change_username,
+                login,
+                is_totp_enabled,
+                verify_totp,
+                begin_totp_enrollment,
+                confirm_totp_enrollment,
+                disable_totp,
+                get_user_account,
+                search_users,
+                ban_user,
+                unban_user,
+                reset_password,
+                revoke_sessions,
+                set_admin,
+                record_admin_action,
+                list_admin_actions,
+            ],
         )
+        .mount("/", routes![health])
+        .manage(user_service.consumer_health())
         .manage(Arc::new(user_service) as Arc<dyn UserService>)
         .launch()
         .await?;
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleKind {
+    Username,
+    Ip,
+    /// Wrong TOTP codes, keyed by user ID.
+    Totp,
+}
+
+impl ThrottleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Username => "username",
+            Self::Ip => "ip",
+            Self::Totp => "totp",
+        }
+    }
+
+    pub fn policy(&self) -> ThrottlePolicy {
+        match self {
+            Self::Username => ThrottlePolicy::USERNAME,
+            Self::Ip => ThrottlePolicy::IP,
+            Self::Totp => ThrottlePolicy::TOTP,
+        }
+    }
+}
+
+/// How many failed logins are tolerated for a key before it gets locked, and for how long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThrottlePolicy {
+    pub free_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Failures older than this are forgotten.
+    pub reset_after: Duration,
+}
+
+impl ThrottlePolicy {
+    pub const USERNAME: Self = Self {
+        free_attempts: 5,
+        base_delay: Duration::from_secs(30),
+        max_delay: Duration::from_secs(60 * 60),
+        reset_after: Duration::from_secs(60 * 60 * 24),
+    };
+
+    // A single address can legitimately be shared by many users (NAT, campus networks).
+    pub const IP: Self = Self {
+        free_attempts: 20,
+        base_delay: Duration::from_secs(30),
+        max_delay: Duration::from_secs(60 * 60),
+        reset_after: Duration::from_secs(60 * 60 * 24),
+    };
+
+    // Re-entering the password starts a new pending login, so wrong codes are also counted
+    // per account. A code only has a million values, so few guesses are allowed.
+    pub const TOTP: Self = Self {
+        free_attempts: 5,
+        base_delay: Duration::from_secs(5 * 60),
+        max_delay: Duration::from_secs(60 * 60 * 24),
+        reset_after: Duration::from_secs(60 * 60 * 24),
+    };
+
+    /// The lockout caused by reaching `failures` consecutive failures, doubling with every
+    /// failure past the free attempts.
+    pub fn lockout_for(&self, failures: u32) -> Option<Duration> {
+        if failures < self.free_attempts {
+            return None;
+        }
+        let exponent = (failures - self.free_attempts).min(16);
+        Some(
+            self.base_delay
+                .saturating_mul(1 << exponent)
+                .min(self.max_delay),
+        )
+    }
+}
@@ -0,0 +1,121 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use aes_gcm::{
+    Aes256Gcm, Key, KeyInit, Nonce,
+    aead::{Aead, AeadCore, OsRng},
+};
+use base64::{Engine as _, prelude::BASE64_STANDARD};
+use codequest_common::{Error, TotpEnrollment, Username};
+use rand::{Rng, RngCore};
+use totp_rs::{Algorithm, TOTP};
+
+pub const ISSUER: &str = "CodeQuest";
+pub const STEP_SECONDS: u64 = 30;
+pub const SECRET_LENGTH: usize = 20;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Encrypts TOTP secrets before they are written to the database.
+pub struct TotpCipher {
+    cipher: Aes256Gcm,
+}
+
+impl TotpCipher {
+    pub fn from_base64(key: impl AsRef<str>) -> Result<Self, Error> {
+        let key = BASE64_STANDARD
+            .decode(key.as_ref())
+            .map_err(|_| Error::Crypto)?;
+        if key.len() != 32 {
+            return Err(Error::Crypto);
+        }
+        Ok(Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+        })
+    }
+
+    /// Returns `(nonce, ciphertext)`.
+    pub fn encrypt(&self, secret: &[u8]) -> Result<(Vec<u8>, Vec<u8>), Error> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, secret)
+            .map_err(|_| Error::Crypto)?;
+        Ok((nonce.to_vec(), ciphertext))
+    }
+
+    pub fn decrypt(&self, nonce: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        if nonce.len() != 12 {
+            return Err(Error::Crypto);
+        }
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| Error::Crypto)
+    }
+}
+
+pub fn generate_secret() -> Vec<u8> {
+    let mut secret = vec![0; SECRET_LENGTH];
+    rand::rng().fill_bytes(&mut secret);
+    secret
+}
+
+pub fn build_totp(secret: Vec<u8>, username: &Username) -> Result<TOTP, Error> {
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        0,
+        STEP_SECONDS,
+        secret,
+        Some(ISSUER.to_owned()),
+        username.to_string(),
+    )
+    .map_err(|_| Error::Crypto)
+}
+
+pub fn enrollment(totp: &TOTP) -> TotpEnrollment {
+    TotpEnrollment {
+        secret: totp.get_secret_base32(),
+        otpauth_uri: totp.get_url(),
+    }
+}
+
+pub fn current_step() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is before the unix epoch")
+        .as_secs()
+        / STEP_SECONDS
+}
+
+/// Checks `code` against the current time step and its direct neighbours and returns the
+/// matching step. Steps at or below `last_used_step` are rejected so a code can't be replayed.
+pub fn find_matching_step(totp: &TOTP, code: &str, last_used_step: u64) -> Option<u64> {
+    let step = current_step();
+    (step.saturating_sub(1)..=step + 1)
+        .filter(|candidate| *candidate > last_used_step)
+        .find(|candidate| totp.check(code, candidate * STEP_SECONDS))
+}
+
+pub fn is_totp_code(code: &str) -> bool {
+    code.len() == 6 && code.chars().all(|c| c.is_ascii_digit())
+}
+
+pub fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+pub fn generate_recovery_codes() -> Vec<String> {
+    const ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+    let mut rng = rand::rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code = (0..10)
+                .map(|_| ALPHABET[rng.random_range(0..ALPHABET.len())] as char)
+                .collect::<String>();
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect()
+}